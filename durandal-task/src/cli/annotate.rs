@@ -3,6 +3,7 @@ use clap::Args;
 use dialoguer::{theme::ColorfulTheme, Input};
 use durandal_core::CliMetaCommand;
 
+use super::print_feedback;
 use crate::{
     config::Config,
     task::{active_tasks, Processable},
@@ -38,7 +39,7 @@ impl CliMetaCommand for Annotate {
                 .interact_text()?
         };
 
-        task.annotate(&msg)?;
+        print_feedback(&task.annotate(&msg)?);
 
         Ok(())
     }
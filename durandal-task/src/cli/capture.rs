@@ -0,0 +1,70 @@
+use std::io::{self, Read};
+
+use anyhow::{Context, Result};
+use clap::Args;
+use console::style;
+use durandal_core::CliMetaCommand;
+
+use crate::{config::Config, parser::InlineTask};
+
+/// Quickly add tasks written on a single line, without any prompts.
+///
+/// Like `Call Bob about budget @phone due:fri ~30m !high proj:finance`, where
+/// `@context`, `+tag`, `proj:`, `due:`, `wait:`, `sched:`, `until:`, `recur:`
+/// and `pri:` work like in taskwarrior, `~30m` (or `h`, `d`) is the estimate
+/// and `!high` the brainpower. Tasks without a context go to the inbox.
+#[derive(Args)]
+pub struct Capture {
+    /// The task. Without one, or with '-', tasks are read from stdin, one per
+    /// line.
+    #[clap(allow_hyphen_values = true)]
+    task: Vec<String>,
+}
+
+impl CliMetaCommand for Capture {
+    type Meta = Config;
+
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        let lines = match self.task.as_slice() {
+            [] => read_stdin()?,
+            [dash] if dash == "-" => read_stdin()?,
+            words => vec![words.join(" ")],
+        };
+
+        // check everything first, so a typo doesn't leave half the tasks added
+        let mut builders = Vec::new();
+        for (n, line) in lines.iter().enumerate() {
+            let builder = InlineTask::parse(line)
+                .and_then(|task| task.builder(config))
+                .with_context(|| format!("On line {}", n + 1))?;
+            builders.push(builder);
+        }
+
+        for builder in builders {
+            let task = builder.build()?;
+
+            println!(
+                "{}",
+                style(format!(
+                    "Captured {} {}",
+                    task.id().unwrap_or_default(),
+                    task.description()
+                ))
+                .green()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+fn read_stdin() -> Result<Vec<String>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+
+    Ok(input
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(String::from)
+        .collect())
+}
@@ -1,26 +1,25 @@
 use anyhow::Result;
 use clap::Args;
-use comfy_table::Color;
 use durandal_core::CliMetaCommand;
 
-use crate::{
-    config::Config,
-    task::active_tasks,
-    task_table::{display_table, Field},
-};
+use crate::{config::Config, output::View, task::active_tasks, task_table::Field, theme::theme};
 
 /// Display the ACTIVE task, if one exists.
 #[derive(Args)]
-pub struct Current;
+pub struct Current {
+    #[clap(flatten)]
+    view: View,
+}
 
 impl CliMetaCommand for Current {
     type Meta = Config;
 
-    fn run(&self, _config: &Self::Meta) -> Result<()> {
+    fn run(&self, config: &Self::Meta) -> Result<()> {
         let active = active_tasks()?;
         let cols = vec![Field::ID, Field::Project, Field::AnnotatedDescription];
-        display_table(&active, &cols, Color::DarkYellow);
 
-        Ok(())
+        let mut output = self.view.output(config)?;
+        output.section("active", None, &active, &cols, theme().highlight)?;
+        output.finish()
     }
 }
@@ -3,9 +3,12 @@ use clap::Args;
 use console::style;
 use durandal_core::CliMetaCommand;
 
+use super::print_feedback;
 use crate::{
     config::Config,
-    task::{active_tasks, Processable, Project},
+    project::Project,
+    prompt::Terminal,
+    task::{active_tasks, Processable},
     workflow::set_next_task,
 };
 
@@ -19,7 +22,7 @@ pub struct Done;
 impl CliMetaCommand for Done {
     type Meta = Config;
 
-    fn run(&self, _config: &Self::Meta) -> Result<()> {
+    fn run(&self, config: &Self::Meta) -> Result<()> {
         let candidates = active_tasks()?;
 
         if candidates.is_empty() {
@@ -37,7 +40,7 @@ impl CliMetaCommand for Done {
             "{}",
             style(format!("Finishing: {}", active.description())).green()
         );
-        active.finish()?;
+        print_feedback(&active.finish()?);
 
         if let Some(resume) = active.task_to_resume()? {
             // If we had a task to resume, do so then exit
@@ -45,7 +48,7 @@ impl CliMetaCommand for Done {
                 "{}",
                 style(format!("Resuming previous task: {}", resume.description())).magenta()
             );
-            resume.begin()?;
+            print_feedback(&resume.begin()?);
             return Ok(());
         }
 
@@ -53,8 +56,8 @@ impl CliMetaCommand for Done {
         // mostly still fine
         if let Some(proj) = active.project().cloned() {
             let project = Project::from(proj);
-            if !project.tasks()?.is_empty() {
-                set_next_task(project)?;
+            if !project.own_tasks()?.is_empty() {
+                set_next_task(config, &mut Terminal::new(), project)?;
             }
         }
 
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use clap::{Args, Subcommand};
+use console::style;
+use durandal_core::CliMetaCommand;
+
+use crate::{
+    config::{expand_home, Config},
+    hook::{self, HookEvent},
+};
+
+/// Taskwarrior hooks enforcing durandal's task conventions.
+///
+/// The hooks fill in default `brain` and `est` values, check for an @context
+/// tag and only allow a single +next task per project.
+#[derive(Args)]
+pub struct Hook {
+    #[clap(subcommand)]
+    command: HookCommands,
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Run the on-add hook, reading the task from stdin.
+    OnAdd,
+    /// Run the on-modify hook, reading the tasks from stdin.
+    OnModify,
+    /// Link the hooks into the taskwarrior hooks directory.
+    Install {
+        /// The hooks directory. Defaults to the one in the config.
+        #[clap(short, long)]
+        directory: Option<String>,
+
+        /// Replace any existing durandal hooks.
+        #[clap(short, long)]
+        force: bool,
+    },
+}
+
+impl CliMetaCommand for Hook {
+    type Meta = Config;
+
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        match self.command {
+            HookCommands::OnAdd => run_hook(HookEvent::OnAdd, config),
+            HookCommands::OnModify => run_hook(HookEvent::OnModify, config),
+            HookCommands::Install {
+                ref directory,
+                force,
+            } => {
+                let dir = expand_home(directory.as_ref().unwrap_or(&config.hooks.directory));
+                let target = std::env::current_exe()
+                    .with_context(|| "Could not determine the durandal-task executable")?;
+
+                let installed = hook::install(&dir, &target, force)?;
+
+                if installed.is_empty() {
+                    println!("{}", style("Hooks already installed").yellow());
+                }
+
+                for link in installed {
+                    println!("{}", style(format!("Installed {}", link)).green());
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Run the hook for `event` against stdin/stdout, exiting with the code the
+/// hook protocol expects.
+pub fn run_hook(event: HookEvent, config: &Config) -> Result<()> {
+    let stdin = std::io::stdin();
+    let code = event.run(config, stdin.lock(), std::io::stdout())?;
+
+    if code != 0 {
+        std::process::exit(code);
+    }
+
+    Ok(())
+}
@@ -1,12 +1,14 @@
 use crate::{
-    config::Config,
+    config::{expand_home, Config},
+    headless,
+    prompt::Terminal,
     task::TaskClient,
     task_table::{Field, TaskTable},
-    workflow::inbox_task,
+    theme::theme,
+    workflow::{inbox_task, inbox_task_saving, resume_inbox_task, triage, Session},
 };
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::Args;
-use comfy_table::Color;
 use console::style;
 use durandal_core::CliMetaCommand;
 
@@ -14,9 +16,25 @@ use super::RFCUtil;
 
 /// Daily inbox review.
 ///
-/// Will also run the rfc_util subcommand.
+/// Will also run the rfc_util subcommand, except when answering from a file.
 #[derive(Args)]
-pub struct Inbox;
+pub struct Inbox {
+    /// Answer the prompts from a TOML/YAML answers file ('-' for stdin)
+    /// instead of the terminal, processing items until the answers run out.
+    /// Prints a JSON report of what was done.
+    #[clap(long, value_name = "FILE")]
+    answers: Option<String>,
+
+    /// Pick up the item an interrupted inbox review was on before moving on
+    /// to the rest of the inbox.
+    #[clap(long, conflicts_with = "answers")]
+    resume: bool,
+
+    /// Start by picking several items at a time to trash, tickle, file or
+    /// categorize together, before going through the rest one by one.
+    #[clap(long, conflicts_with = "answers")]
+    bulk: bool,
+}
 
 impl CliMetaCommand for Inbox {
     type Meta = Config;
@@ -25,6 +43,51 @@ impl CliMetaCommand for Inbox {
         let mut client = TaskClient::new();
         client.filter("status:pending +in");
 
+        if let Some(ref path) = self.answers {
+            return headless::run(path, |prompter, done| {
+                while prompter.remaining() > 0 {
+                    client.refresh_tasks()?;
+
+                    match client.tasks().first() {
+                        Some(task) => done.push(inbox_task(config, prompter, task.clone())?),
+                        None => break,
+                    }
+                }
+
+                Ok(())
+            });
+        }
+
+        let mut prompter = Terminal::new();
+        let session = expand_home(&config.session_file);
+
+        if self.resume {
+            if resume_inbox_task(config, &mut prompter, &session)?.is_none() {
+                println!("{}", style("Nothing to resume").yellow());
+            }
+        } else if Session::load(&session)?.is_some() {
+            // starting over would leave a task that was being done started, and
+            // lose the time already spent on it
+            bail!("The last inbox review was interrupted, continue it with `inbox --resume`");
+        }
+
+        if self.bulk {
+            loop {
+                client.refresh_tasks()?;
+                if client.tasks().is_empty() {
+                    break;
+                }
+
+                println!(
+                    "\n\nThere are {} items in your inbox:\n",
+                    client.tasks().len()
+                );
+                if triage(config, &mut prompter, client.tasks())?.is_none() {
+                    break;
+                }
+            }
+        }
+
         // process all normal inbox items
         loop {
             // We need to refresh every time because of how ids change.
@@ -36,11 +99,11 @@ impl CliMetaCommand for Inbox {
             let cols = vec![Field::AnnotatedDescription];
 
             if let Some(task) = tasks.into_iter().next() {
-                let mut table = TaskTable::new(&cols).description_color(Color::DarkYellow);
+                let mut table = TaskTable::new(&cols).description_color(theme().highlight);
                 table.add_row(&task);
                 println!("\n\nThe next item is:\n");
                 println!("{}\n", table);
-                inbox_task(task.clone())?;
+                inbox_task_saving(config, &mut prompter, task.clone(), &session)?;
             } else {
                 println!("{}", style("Your inbox is empty").yellow());
                 break;
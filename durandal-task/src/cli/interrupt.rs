@@ -3,8 +3,10 @@ use clap::Args;
 use console::style;
 use durandal_core::CliMetaCommand;
 
+use super::print_feedback;
 use crate::{
     config::Config,
+    prompt::Terminal,
     task::{active_tasks, Processable},
     workflow::add_to_project,
 };
@@ -19,7 +21,7 @@ pub struct Interrupt;
 impl CliMetaCommand for Interrupt {
     type Meta = Config;
 
-    fn run(&self, _config: &Self::Meta) -> Result<()> {
+    fn run(&self, config: &Self::Meta) -> Result<()> {
         // 1. stop any started tasks
         // 2. create a new task
         // 3. start the new task
@@ -32,7 +34,7 @@ impl CliMetaCommand for Interrupt {
                     resume = Some(task);
                 }
 
-                task.stop()?;
+                print_feedback(&task.stop()?);
                 println!(
                     "{}",
                     style(format!(
@@ -46,14 +48,14 @@ impl CliMetaCommand for Interrupt {
         }
 
         // lets just have the project and tags signal interrupt
-        let task = add_to_project("interrupt".into())?;
+        let task = add_to_project(config, &mut Terminal::new(), "interrupt".into())?;
         task.add_tags(&["interrupt"])?;
 
         if let Some(resume) = resume {
             task.set_resumable(resume)?;
         }
 
-        task.begin()?;
+        print_feedback(&task.begin()?);
 
         println!(
             "{}",
@@ -1,17 +1,27 @@
+use std::path::PathBuf;
+
 use anyhow::{bail, Context, Result};
 use clap::{Parser, Subcommand};
+use console::style;
 use dialoguer::theme::ColorfulTheme;
 use dialoguer::Select;
 use durandal_core::CliMetaDispatch;
 
 use crate::{
     config::{default_location, Config},
+    dates::{self, DateStyle},
+    hook::HookEvent,
     iou_client::IouClient,
+    output::Format,
+    parser::TaskOutput,
+    theme,
 };
 
 use self::annotate::Annotate;
+use self::capture::Capture;
 use self::current::Current;
 use self::done::Done;
+use self::hook::Hook;
 use self::inbox::Inbox;
 use self::interrupt::Interrupt;
 use self::new::New;
@@ -19,16 +29,21 @@ use self::next::Next;
 use self::open::Open;
 use self::projects::Projects;
 use self::replan::Replan;
+use self::report::Report;
+use self::reports::Reports;
 use self::requests::Requests;
 use self::rfc_util::RFCUtil;
 use self::scrum::Scrum;
 use self::stop::Stop;
 use self::table::Table;
+use self::waiting::Waiting;
 use self::weekly::Weekly;
 
 mod annotate;
+mod capture;
 mod current;
 mod done;
+mod hook;
 mod inbox;
 mod interrupt;
 mod new;
@@ -36,11 +51,14 @@ mod next;
 mod open;
 mod projects;
 mod replan;
+mod report;
+mod reports;
 mod requests;
 mod rfc_util;
 mod scrum;
 mod stop;
 mod table;
+mod waiting;
 mod weekly;
 
 #[derive(Parser)]
@@ -50,6 +68,19 @@ pub(crate) struct Cli {
     #[clap(short = 'C', long)]
     pub config: Option<String>,
 
+    /// Output format for current, next, report, scrum, requests and waiting. Anything other
+    /// than text only lists tasks, without prompting.
+    #[clap(long, global = true, arg_enum, default_value = "text")]
+    pub format: Format,
+
+    /// How to show dates, overriding the config.
+    #[clap(long, global = true, arg_enum, value_name = "STYLE")]
+    pub dates: Option<DateStyle>,
+
+    /// Write the listing to a file instead of stdout.
+    #[clap(short, long, global = true, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
     #[clap(subcommand)]
     pub command: Commands,
 }
@@ -57,26 +88,60 @@ pub(crate) struct Cli {
 impl Cli {
     pub fn run() -> Result<()> {
         let app = Self::parse();
+        let mut config = load_config(app.config)?;
+        config.format = app.format;
+        config.output = app.output;
+        if let Some(dates) = app.dates {
+            config.dates = dates;
+        }
+        dates::configure(config.dates);
 
-        let config_file = match app.config {
-            Some(path) => path,
-            None => default_location().with_context(|| "Could not load default config location")?,
-        };
+        app.command.run(&config)
+    }
 
-        let config = Config::new(&config_file)?;
+    /// Taskwarrior invokes hooks with its own arguments, so there's nothing
+    /// for us to parse when running as one.
+    pub fn run_hook(event: HookEvent) -> Result<()> {
+        let config = load_config(None)?;
 
-        app.command.run(&config)
+        hook::run_hook(event, &config)
     }
 }
 
+fn load_config(path: Option<String>) -> Result<Config> {
+    let config_file = match path {
+        Some(path) => path,
+        None => default_location().with_context(|| "Could not load default config location")?,
+    };
+
+    let config = Config::new(&config_file)?;
+
+    theme::configure(&config.theme)?;
+
+    #[cfg(feature = "taskchampion")]
+    if let Some(ref tc) = config.taskchampion {
+        let udas: Vec<_> = config
+            .udas
+            .iter()
+            .cloned()
+            .chain(crate::task_table::UDA::builtins())
+            .collect();
+        crate::replica::configure(tc, &udas)?;
+    }
+
+    Ok(config)
+}
+
 #[derive(Subcommand, CliMetaDispatch)]
 #[cli_meta(Config)]
 pub(crate) enum Commands {
     #[clap(visible_alias = "comment")]
     Annotate(Annotate),
+    Capture(Capture),
     Current(Current),
     #[clap(visible_alias = "finish")]
     Done(Done),
+    Hook(Hook),
     Inbox(Inbox),
     #[clap(visible_alias = "int")]
     Interrupt(Interrupt),
@@ -87,6 +152,8 @@ pub(crate) enum Commands {
     #[clap(visible_alias = "proj")]
     Projects(Projects),
     Replan(Replan),
+    Report(Report),
+    Reports(Reports),
     #[clap(visible_alias = "req")]
     Requests(Requests),
     #[clap(name = "rfc_util")]
@@ -94,6 +161,7 @@ pub(crate) enum Commands {
     Scrum(Scrum),
     Stop(Stop),
     Table(Table),
+    Waiting(Waiting),
     Weekly(Weekly),
 }
 
@@ -117,3 +185,11 @@ fn make_iou_client(config: &Config) -> Result<IouClient> {
         Ok(IouClient::new(config.iou.servers.clone()))
     }
 }
+
+/// Show hook feedback and warnings from a task command, which would otherwise
+/// be swallowed.
+fn print_feedback(output: &TaskOutput) {
+    for line in output.feedback() {
+        println!("{}", style(line).yellow());
+    }
+}
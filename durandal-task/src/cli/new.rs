@@ -1,17 +1,117 @@
-use crate::{config::Config, workflow::new_task};
-use anyhow::Result;
+use crate::{
+    config::Config,
+    headless,
+    prompt::Terminal,
+    task::TaskBuilder,
+    task_table::UDA,
+    workflow::{new_task, Outcome},
+};
+use anyhow::{anyhow, Result};
 use clap::Args;
 use durandal_core::CliMetaCommand;
+use uuid::Uuid;
 
 /// Convenience for adding a new deferred task.
+///
+/// Anything not covered by the prompts can be set with the options below.
 #[derive(Args)]
-pub struct New;
+pub struct New {
+    /// The priority of the task.
+    #[clap(short, long)]
+    priority: Option<String>,
+
+    /// When the task becomes ready (any valid 'scheduled:' value).
+    #[clap(long)]
+    scheduled: Option<String>,
+
+    /// When a recurring task stops recurring (any valid 'until:' value).
+    #[clap(long)]
+    until: Option<String>,
+
+    /// How often the task recurs, like 'weekly'. Requires a due date.
+    #[clap(long)]
+    recur: Option<String>,
+
+    /// The uuid of a task this task depends on. May be repeated.
+    #[clap(long = "depends")]
+    depends: Vec<Uuid>,
+
+    /// An annotation to add to the task. May be repeated.
+    #[clap(short, long = "annotation")]
+    annotations: Vec<String>,
+
+    /// A UDA to set, in the form KEY=VALUE. May be repeated.
+    #[clap(long = "uda", parse(try_from_str = parse_uda))]
+    udas: Vec<(String, String)>,
+
+    /// Answer the prompts from a TOML/YAML answers file ('-' for stdin)
+    /// instead of the terminal, creating tasks until the answers run out.
+    /// The options above apply to every task. Prints a JSON report of what
+    /// was created.
+    #[clap(long, value_name = "FILE")]
+    answers: Option<String>,
+}
+
+fn parse_uda(s: &str) -> Result<(String, String)> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| anyhow!("Expected KEY=VALUE but got '{}'", s))
+}
 
 impl CliMetaCommand for New {
     type Meta = Config;
 
-    fn run(&self, _config: &Self::Meta) -> Result<()> {
-        new_task()?;
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        if let Some(ref path) = self.answers {
+            return headless::run(path, |prompter, done| {
+                while prompter.remaining() > 0 {
+                    let task = new_task(config, prompter, self.builder(config)?)?;
+                    done.push(Outcome::new("created", &task));
+                }
+
+                Ok(())
+            });
+        }
+
+        new_task(config, &mut Terminal::new(), self.builder(config)?)?;
         Ok(())
     }
 }
+
+impl New {
+    fn builder(&self, config: &Config) -> Result<TaskBuilder> {
+        let mut builder = TaskBuilder::new(&config.categories);
+
+        if let Some(ref priority) = self.priority {
+            builder.with_priority(priority);
+        }
+
+        if let Some(ref scheduled) = self.scheduled {
+            builder.with_scheduled(scheduled);
+        }
+
+        if let Some(ref until) = self.until {
+            builder.with_until(until);
+        }
+
+        if let Some(ref recur) = self.recur {
+            builder.with_recurrence(recur);
+        }
+
+        for uuid in &self.depends {
+            builder.with_dependency(*uuid);
+        }
+
+        for annotation in &self.annotations {
+            builder.with_annotation(annotation);
+        }
+
+        // declared numeric UDAs are stored as numbers, anything else as given
+        for (key, value) in &self.udas {
+            let uda = config.uda(key).unwrap_or_else(|| UDA::new(key.as_str()));
+            builder.with_uda(key, uda.parse_value(value)?);
+        }
+
+        Ok(builder)
+    }
+}
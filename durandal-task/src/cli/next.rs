@@ -1,40 +1,76 @@
 use anyhow::Result;
 use clap::Args;
-use comfy_table::Color;
 use console::style;
 use dialoguer::{theme::ColorfulTheme, Select};
 use durandal_core::CliMetaCommand;
 
+use super::print_feedback;
 use crate::{
     config::Config,
+    output::{Format, View},
     task::{active_tasks, load_tasks, Processable},
-    task_table::{display_table, Field},
+    task_table::{sort_tasks, Field, TaskTable},
+    theme::theme,
 };
 
 /// Find something to work on.
+///
+/// With a format other than text, lists the active and next tasks instead.
 #[derive(Args)]
-pub struct Next;
+pub struct Next {
+    #[clap(flatten)]
+    view: View,
+}
 
 impl CliMetaCommand for Next {
     type Meta = Config;
 
-    fn run(&self, _config: &Self::Meta) -> Result<()> {
+    fn run(&self, config: &Self::Meta) -> Result<()> {
         let active = active_tasks()?;
+        let cols = vec![Field::ID, Field::Project, Field::AnnotatedDescription];
+        let next_task_filter = "+next -ACTIVE status:pending";
+
+        if config.format != Format::Text {
+            let mut output = self.view.output(config)?;
+            output.section(
+                "active",
+                Some("Active tasks:"),
+                &active,
+                &cols,
+                theme().active,
+            )?;
+            output.section(
+                "next",
+                Some("Next tasks:"),
+                &load_tasks(next_task_filter)?,
+                &cols,
+                theme().highlight,
+            )?;
+            return output.finish();
+        }
+
         if !active.is_empty() {
-            let cols = vec![Field::ID, Field::Project, Field::AnnotatedDescription];
             // we already have tasks in progress
             println!(
                 "{}",
                 style("Cannot start a task when other task(s) are active:").red()
             );
-            display_table(&active, &cols, Color::Magenta);
+            let mut table = TaskTable::new(&cols)
+                .description_color(theme().active)
+                .sort_by(&self.view.sort)
+                .group_by(self.view.group_by);
+            for task in &active {
+                table.add_row(task);
+            }
+            println!("{}", table);
 
             // TODO: this probably shouldn't be 0 - MCL - 2022-03-20
             return Ok(());
         }
 
-        let next_task_filter = "+next -ACTIVE status:pending";
-        let next_tasks = load_tasks(next_task_filter)?;
+        // choices can't be grouped, but they can at least be in order
+        let mut next_tasks = load_tasks(next_task_filter)?;
+        sort_tasks(&mut next_tasks, &self.view.sort);
         let mut choices: Vec<_> = next_tasks
             .iter()
             .map(|t| {
@@ -58,7 +94,7 @@ impl CliMetaCommand for Next {
         // if we don't match anything, we don't care, because it's the nothing
         // option
         if let Some(task) = next_tasks.get(choice) {
-            task.begin()?;
+            print_feedback(&task.begin()?);
             println!(
                 "{}",
                 style(format!("started {}", task.id().unwrap_or_default())).green()
@@ -6,7 +6,7 @@ use console::style;
 use dialoguer::{theme::ColorfulTheme, Select};
 use durandal_core::CliMetaCommand;
 use linkify::LinkFinder;
-use task_hookrs::{task::Task, uda::UDAValue};
+use task_hookrs::task::Task;
 
 use crate::{cli::make_iou_client, config::Config, task::active_task, task_table::UDA};
 
@@ -53,15 +53,11 @@ impl CliMetaCommand for Open {
 
 fn get_links(task: &Task) -> HashSet<String> {
     let mut links = HashSet::new();
-    let udas = task.uda();
 
-    // TODO: figure out where to actually store UDAs like jiraurl - MCL - 2022-03-17
-    if let Some(UDAValue::Str(url)) = udas.get("jiraurl") {
-        links.insert(url.clone());
-    }
-
-    if let Some(UDAValue::Str(url)) = udas.get(UDA::GithubUrl.uda_key()) {
-        links.insert(url.clone());
+    for uda in [UDA::jira_url(), UDA::github_url()] {
+        if let Some(url) = uda.get_str(task) {
+            links.insert(url.to_string());
+        }
     }
 
     // now find links in annotations
@@ -1,26 +1,59 @@
-use crate::{config::Config, task, workflow::set_next_task};
+use std::collections::HashMap;
+
+use crate::{
+    config::Config,
+    headless,
+    project::{needs_next_action, Project, ProjectTree},
+    prompt::Terminal,
+    task::{self, load_tasks, Processable},
+    workflow::set_next_task,
+};
 use anyhow::Result;
 use clap::Args;
 use console::style;
 use durandal_core::CliMetaCommand;
+use task_hookrs::task::Task;
 
 /// Ensure projects have next actions.
+///
+/// Projects are visited parents first. A parent only needs a next action of
+/// its own if it has tasks directly in it, not just in its subprojects.
+/// Projects whose policy sets `skip_next_action` are left alone.
 #[derive(Args)]
-pub struct Projects;
+pub struct Projects {
+    /// Only show the project tree and which projects need a next action.
+    #[clap(short, long)]
+    tree: bool,
+
+    /// Answer the prompts from a TOML/YAML answers file ('-' for stdin)
+    /// instead of the terminal. Prints a JSON report of the next actions set.
+    #[clap(long, value_name = "FILE", conflicts_with = "tree")]
+    answers: Option<String>,
+}
 
 impl CliMetaCommand for Projects {
     type Meta = Config;
 
     fn run(&self, config: &Self::Meta) -> Result<()> {
-        let projects = task::projects_excluding(&config.excluded_projects)?;
+        let tree = ProjectTree::new(task::projects_excluding(&config.excluded_projects)?);
 
-        for project in &projects {
-            // ignore the empty project
-            if project.len() < 1 {
-                continue;
-            }
+        if self.tree {
+            return print_tree(config, &tree);
+        }
 
-            set_next_task(project.clone())?;
+        if let Some(ref path) = self.answers {
+            return headless::run(path, |prompter, done| {
+                for project in tree.projects() {
+                    done.extend(set_next_task(config, prompter, project.clone())?);
+                }
+
+                Ok(())
+            });
+        }
+
+        let mut prompter = Terminal::new();
+        for project in tree.projects() {
+            set_next_task(config, &mut prompter, project.clone())?;
         }
 
         println!("{}", style("No remaining projects").yellow());
@@ -28,3 +61,38 @@ impl CliMetaCommand for Projects {
         Ok(())
     }
 }
+
+fn print_tree(config: &Config, tree: &ProjectTree) -> Result<()> {
+    let mut by_project: HashMap<Project, Vec<Task>> = HashMap::new();
+    for task in load_tasks("status:pending")? {
+        if let Some(project) = task.project() {
+            by_project
+                .entry(project.as_str().into())
+                .or_default()
+                .push(task);
+        }
+    }
+
+    for (project, label) in tree.labels() {
+        let policy = config.policy(&project);
+        let own = by_project.get(&project).map(Vec::as_slice).unwrap_or(&[]);
+        let total: usize = by_project
+            .iter()
+            .filter(|(p, _)| project.contains(p))
+            .map(|(_, tasks)| tasks.len())
+            .sum();
+
+        let status = match own.iter().find(|t| t.is_next()) {
+            Some(next) => style(format!("next: {}", next.description())).green(),
+            None if policy.skips_next_action() => {
+                style("next action not required".to_string()).dim()
+            }
+            None if needs_next_action(own) => style("needs a next action".to_string()).yellow(),
+            None => style("subprojects only".to_string()).dim(),
+        };
+
+        println!("{} ({}/{}) {}", label, own.len(), total, status);
+    }
+
+    Ok(())
+}
@@ -1,9 +1,14 @@
 use anyhow::Result;
 use clap::Args;
-use dialoguer::{theme::ColorfulTheme, Select};
 use durandal_core::CliMetaCommand;
 
-use crate::{config::Config, task, workflow::force_next_task};
+use crate::{
+    config::Config,
+    project::ProjectTree,
+    prompt::{Prompter, Terminal},
+    task,
+    workflow::force_next_task,
+};
 
 /// Replan a project by maybe changing the task that is next.
 #[derive(Args)]
@@ -13,19 +18,17 @@ impl CliMetaCommand for Replan {
     type Meta = Config;
 
     fn run(&self, config: &Self::Meta) -> Result<()> {
-        let projects = task::projects_excluding(&config.excluded_projects)?;
+        let tree = ProjectTree::new(task::projects_excluding(&config.excluded_projects)?);
+        let (projects, labels): (Vec<_>, Vec<_>) = tree.labels().into_iter().unzip();
 
-        let choice = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Which project to replan?")
-            .items(&projects)
-            .default(0)
-            .interact()?;
+        let mut prompter = Terminal::new();
+        let choice = prompter.select("Which project to replan?", &labels, 0)?;
 
         if let Some(project) = projects.get(choice) {
             // if the selected project already has a next task, we need to
             // remove the next tag
 
-            force_next_task(project.clone())?;
+            force_next_task(config, &mut prompter, project.clone())?;
         }
 
         Ok(())
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Local;
+use clap::Args;
+use durandal_core::CliMetaCommand;
+use handlebars::Handlebars;
+
+use crate::{config::Config, output::Output, task::load_tasks, task_table::sort_tasks};
+
+/// Show one of the reports defined in config.
+///
+/// Reports live in `[reports.<name>]` sections, see `reports` for the ones
+/// that are available.
+#[derive(Args)]
+pub struct Report {
+    /// The name of the report.
+    name: String,
+
+    /// A variable for the report's filter template, in the form KEY=VALUE.
+    /// May be repeated.
+    #[clap(short, long = "var", parse(try_from_str = parse_var))]
+    vars: Vec<(String, String)>,
+
+    /// Show at most this many tasks, overriding the report's limit.
+    #[clap(short, long)]
+    limit: Option<usize>,
+}
+
+fn parse_var(s: &str) -> Result<(String, String)> {
+    s.split_once('=')
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .ok_or_else(|| anyhow!("Expected KEY=VALUE but got '{}'", s))
+}
+
+impl CliMetaCommand for Report {
+    type Meta = Config;
+
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        let report = config
+            .reports
+            .get(&self.name)
+            .ok_or_else(|| anyhow!("No report named '{}'", self.name))?;
+
+        let today = Local::now().format("%F").to_string();
+        let mut vars: HashMap<&str, &str> = HashMap::new();
+        vars.insert("today", &today);
+        for (key, value) in &self.vars {
+            vars.insert(key, value);
+        }
+
+        let filter = Handlebars::new()
+            .render_template(&report.filter, &vars)
+            .with_context(|| format!("Could not render the filter for report {}", self.name))?;
+
+        let mut tasks = load_tasks(&filter)
+            .with_context(|| format!("Could not fetch tasks for report {}", self.name))?;
+        sort_tasks(&mut tasks, &report.sort);
+
+        if let Some(limit) = self.limit.or(report.limit) {
+            tasks.truncate(limit);
+        }
+
+        let mut output = Output::from_config(config)?.grouped(report.group_by);
+        output.section(&self.name, None, &tasks, &report.columns, report.color())?;
+        output.finish()
+    }
+}
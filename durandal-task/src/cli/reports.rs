@@ -0,0 +1,32 @@
+use anyhow::Result;
+use clap::Args;
+use console::style;
+use durandal_core::CliMetaCommand;
+
+use crate::config::Config;
+
+/// List the reports defined in config.
+#[derive(Args)]
+pub struct Reports;
+
+impl CliMetaCommand for Reports {
+    type Meta = Config;
+
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        if config.reports.is_empty() {
+            println!("{}", style("No reports configured").yellow());
+            return Ok(());
+        }
+
+        let mut names: Vec<_> = config.reports.keys().collect();
+        names.sort();
+
+        for name in names {
+            let report = &config.reports[name];
+            println!("{}  {}", style(name).cyan(), report.description);
+            println!("    {}", style(&report.filter).dim());
+        }
+
+        Ok(())
+    }
+}
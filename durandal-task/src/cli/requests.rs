@@ -1,4 +1,12 @@
-use crate::{config::Config, task::load_tasks, workflow::process_pr};
+use crate::{
+    config::Config,
+    output::{Format, Output},
+    prompt::Terminal,
+    task::load_tasks,
+    task_table::Field,
+    theme::theme,
+    workflow::process_pr,
+};
 use anyhow::{Context, Result};
 use clap::Args;
 use durandal_core::CliMetaCommand;
@@ -6,6 +14,8 @@ use durandal_core::CliMetaCommand;
 use super::make_iou_client;
 
 /// Process all requests.
+///
+/// With a format other than text, lists the requests instead.
 #[derive(Args)]
 pub struct Requests;
 
@@ -13,13 +23,21 @@ impl CliMetaCommand for Requests {
     type Meta = Config;
 
     fn run(&self, config: &Self::Meta) -> Result<()> {
-        let iou_client = make_iou_client(&config)?;
-
         let prs =
             load_tasks(&config.requests.filter).with_context(|| "Could not fetch pull requests")?;
 
+        if config.format != Format::Text {
+            let cols = vec![Field::ID, Field::AnnotatedDescription];
+            let mut output = Output::from_config(config)?;
+            output.section("requests", None, &prs, &cols, theme().highlight)?;
+            return output.finish();
+        }
+
+        let iou_client = make_iou_client(&config)?;
+
+        let mut prompter = Terminal::new();
         for task in &prs {
-            process_pr(task.clone(), &iou_client)?;
+            process_pr(&mut prompter, task.clone(), &iou_client)?;
         }
 
         Ok(())
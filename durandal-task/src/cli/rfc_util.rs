@@ -1,4 +1,4 @@
-use crate::task::{ActionCategory, Processable};
+use crate::task::Processable;
 use anyhow::{bail, Context, Result};
 use clap::Args;
 use console::style;
@@ -8,6 +8,7 @@ use linkify::LinkFinder;
 use crate::{
     config::Config,
     task::{load_tasks, TaskBuilder},
+    task_table::UDA,
 };
 
 /// Create rnr tasks for RFC tickets if RFC links are available
@@ -25,9 +26,7 @@ impl CliMetaCommand for RFCUtil {
         let eligible: Vec<_> = rfcs
             .iter()
             .filter_map(|rfc| {
-                if let Some(task_hookrs::uda::UDAValue::Str(desc)) =
-                    rfc.uda().get("jiradescription")
-                {
+                if let Some(desc) = UDA::jira_description().get_str(rfc) {
                     if let Some(link) = finder
                         .links(desc)
                         .find(|link| link.as_str().contains("notion"))
@@ -43,33 +42,32 @@ impl CliMetaCommand for RFCUtil {
             println!("\n{}", style("Eligible RFCs detected").yellow());
 
             for (rfc, notion_link) in eligible {
-                let mut builder = TaskBuilder::new();
-                let description = match rfc.uda().get("jirasummary") {
-                    Some(task_hookrs::uda::UDAValue::Str(summary)) => {
-                        format!("RFC Review: {}", summary)
-                    }
-                    _ => bail!("Failed to get jira summary from: {:?}", rfc.id()),
+                let project = config.rfcs.rnr_task_project.clone().into();
+                let mut builder = TaskBuilder::new(&config.categories);
+                let description = match UDA::jira_summary().get_str(rfc) {
+                    Some(summary) => format!("RFC Review: {}", summary),
+                    None => bail!("Failed to get jira summary from: {:?}", rfc.id()),
+                };
+
+                let jira_link = match UDA::jira_url().get_str(rfc) {
+                    Some(link) => link,
+                    None => bail!("Failed to get jira url from: {:?}", rfc.id()),
                 };
 
-                let newtask = builder
+                // the rnr task is annotated with the relevant links
+                builder
                     .with_description(&description)
-                    .with_contexts(&[ActionCategory::Computer, ActionCategory::Work])
+                    .with_context_tags(&config.rfcs.contexts)
                     .with_tags(["rnr"])
-                    .with_project(config.rfcs.rnr_task_project.clone().into())
+                    .with_policy(&config.policy(&project))
+                    .with_project(project)
+                    .with_annotation(jira_link)
+                    .with_annotation(notion_link)
                     .build()?;
 
                 // remove the rfc_inbox tag from the original task
                 rfc.remove_tags(&["rfc_inbox"])?;
 
-                // proceed to annotate the rnr task with relevant links
-                let jira_link = match rfc.uda().get("jiraurl") {
-                    Some(task_hookrs::uda::UDAValue::Str(link)) => link,
-                    _ => bail!("Failed to get jira url from: {:?}", rfc.id()),
-                };
-
-                newtask.annotate(jira_link)?;
-                newtask.annotate(notion_link)?;
-
                 println!(
                     "{}",
                     style(format!("Added rnr task for '{}'", description)).green()
@@ -1,17 +1,22 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 use anyhow::{Context, Result};
 use chrono::prelude::*;
 use clap::Args;
-use comfy_table::Color;
 use console::style;
 use durandal_core::CliMetaCommand;
 use handlebars::Handlebars;
+use serde_json::json;
+use task_hookrs::task::Task;
 
 use crate::{
     config::Config,
+    dates::format_date,
+    output::{Format, View},
+    project::{last_activity, Project, ProjectTree, ReviewCadence},
     task::load_tasks,
-    task_table::{display_unique_table, Field},
+    task_table::Field,
+    theme::{paint, theme},
 };
 
 /// Daily scrum summary.
@@ -23,6 +28,9 @@ pub struct Scrum {
     /// appropriate number of days.
     #[clap(short, long)]
     days: Option<u64>,
+
+    #[clap(flatten)]
+    view: View,
 }
 
 impl CliMetaCommand for Scrum {
@@ -72,14 +80,24 @@ impl CliMetaCommand for Scrum {
         let followup_tasks =
             load_tasks(&followup_filter).with_context(|| "Could not fetch follow-up tasks")?;
 
+        let pending_tasks =
+            load_tasks("status:pending").with_context(|| "Could not fetch pending tasks")?;
+
         let motd = format!(
             "Today is {}. Last scrum should have been {}\n",
             today.format("%A, %F"),
             lower_bound
         );
-        println!("{}", style(motd).bold());
-
-        let mut seen_uuids = HashSet::new();
+        let mut output = self.view.output(config)?;
+        let theme = theme();
+        match output.format() {
+            Format::Text => output.line(style(motd).bold())?,
+            Format::Markdown | Format::Slack => output.line(motd)?,
+            Format::Json => {
+                output.value("today", json!(today.format("%F").to_string()));
+                output.value("since", json!(lower_bound));
+            }
+        }
 
         let standard_cols = vec![
             Field::ID,
@@ -89,67 +107,168 @@ impl CliMetaCommand for Scrum {
         ];
 
         // Tasks finished yesterday (or since Friday if Monday scrum)
-        println!("{}", style("Tasks completed since last scrum:").cyan());
-        display_unique_table(
+        output.section(
+            "completed",
+            Some("Tasks completed since last scrum:"),
             &completed_tasks,
             // showing the id or next for these would be pointless
-            &vec![Field::Project, Field::Description],
-            Color::Green,
-            &mut seen_uuids,
-        );
+            &[Field::Project, Field::Description],
+            theme.completed,
+        )?;
 
         // Tasks currently in progress
-        println!("\n{}", style("In-progress tasks:").cyan());
-        display_unique_table(&started_tasks, &standard_cols, Color::Blue, &mut seen_uuids);
+        output.section(
+            "in_progress",
+            Some("In-progress tasks:"),
+            &started_tasks,
+            &standard_cols,
+            theme.active,
+        )?;
 
         // Tasks due in the next 7 days or overdue
-        println!("\n{}", style("Tasks due soon:").cyan());
-        display_unique_table(
+        output.section(
+            "due",
+            Some("Tasks due soon:"),
             &due_tasks,
-            &vec![
+            &[
                 Field::ID,
                 Field::Due,
                 Field::Project,
                 Field::Next,
                 Field::AnnotatedDescription,
             ],
-            Color::Red,
-            &mut seen_uuids,
-        );
+            theme.urgent,
+        )?;
 
         // Tasks waiting for others
-        println!(
-            "\n{}",
-            style("Waiting tasks for the next five days:").cyan()
-        );
-        display_unique_table(
+        output.section(
+            "waiting",
+            Some("Waiting tasks for the next five days:"),
             &followup_tasks,
-            &vec![
+            &[
                 Field::ID,
                 Field::Waiting,
                 Field::Project,
                 Field::AnnotatedDescription,
             ],
-            Color::Magenta,
-            &mut seen_uuids,
-        );
+            theme.waiting,
+        )?;
 
         // Tasks modified yesterday (or since Friday if Monday scrum)
         // this needs to be last since it's possible that it would catch tasks that
         // fit better in other sections, and, because we're only showing unique
         // tasks across any report, we don't want to not show the duplicate here
         // instead of elsewhere
-        println!(
-            "\n{}",
-            style("Other tasks modified since last scrum:").cyan()
-        );
-        display_unique_table(
+        output.section(
+            "modified",
+            Some("Other tasks modified since last scrum:"),
             &modified_tasks,
             &standard_cols,
-            Color::DarkYellow,
-            &mut seen_uuids,
-        );
+            theme.highlight,
+        )?;
 
-        Ok(())
+        // Projects that haven't seen any activity within their review cadence
+        // finishing or touching tasks counts as activity too
+        let recent: Vec<_> = completed_tasks
+            .iter()
+            .chain(modified_tasks.iter())
+            .cloned()
+            .collect();
+        let reviews = due_for_review(config, &pending_tasks, &recent, Utc::now().naive_utc());
+        match output.format() {
+            Format::Text if !reviews.is_empty() => {
+                output.line(format!(
+                    "\n{}",
+                    paint(theme.header, "Projects due for review:")
+                ))?;
+                for (project, cadence, last) in reviews {
+                    output.line(format!(
+                        "  {} ({}, last activity {})",
+                        paint(theme.warning, project),
+                        cadence,
+                        format_date(last)
+                    ))?;
+                }
+            }
+            Format::Markdown if !reviews.is_empty() => {
+                output.line("\n## Projects due for review\n")?;
+                for (project, cadence, last) in reviews {
+                    output.line(format!(
+                        "- {} ({}, last activity {})",
+                        project,
+                        cadence,
+                        format_date(last)
+                    ))?;
+                }
+            }
+            Format::Slack if !reviews.is_empty() => {
+                output.line("\n*Projects due for review*")?;
+                for (project, cadence, last) in reviews {
+                    output.line(format!(
+                        "• {} ({}, last activity {})",
+                        project,
+                        cadence,
+                        format_date(last)
+                    ))?;
+                }
+            }
+            Format::Json => {
+                let reviews: Vec<_> = reviews
+                    .into_iter()
+                    .map(|(project, cadence, last)| {
+                        json!({
+                            "project": project.to_string(),
+                            "review": cadence.to_string(),
+                            "last_activity": last.format("%F").to_string(),
+                        })
+                    })
+                    .collect();
+                output.value("due_for_review", json!(reviews));
+            }
+            _ => {}
+        }
+
+        output.finish()
     }
 }
+
+/// Projects with a review cadence and no activity in their subtree within it,
+/// going by the `pending` tasks and any `recent` ones no longer pending.
+fn due_for_review(
+    config: &Config,
+    pending: &[Task],
+    recent: &[Task],
+    now: NaiveDateTime,
+) -> Vec<(Project, ReviewCadence, NaiveDateTime)> {
+    let tree = ProjectTree::new(
+        pending
+            .iter()
+            .filter_map(|t| t.project())
+            .filter(|p| !config.is_excluded(p))
+            .map(|p| Project::from(p.as_str())),
+    );
+
+    tree.projects()
+        .into_iter()
+        .filter_map(|project| {
+            let cadence = config.policy(project).review?;
+            let subtree: Vec<_> = pending
+                .iter()
+                .chain(recent.iter())
+                .filter(|t| {
+                    t.project()
+                        .map(|p| project.contains(&p.as_str().into()))
+                        .unwrap_or(false)
+                })
+                .cloned()
+                .collect();
+            let last = last_activity(&subtree)?;
+
+            if cadence.is_due(last, now) {
+                Some((project.clone(), cadence, last))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
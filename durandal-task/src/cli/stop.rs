@@ -3,6 +3,7 @@ use clap::Args;
 use console::style;
 use durandal_core::CliMetaCommand;
 
+use super::print_feedback;
 use crate::{
     config::Config,
     task::{active_tasks, Processable},
@@ -23,7 +24,7 @@ impl CliMetaCommand for Stop {
         }
 
         for task in &active {
-            task.stop()?;
+            print_feedback(&task.stop()?);
             println!(
                 "{}",
                 style(format!(
@@ -0,0 +1,48 @@
+use anyhow::Result;
+use clap::Args;
+use durandal_core::CliMetaCommand;
+
+use crate::{
+    config::Config,
+    output::View,
+    task::load_tasks,
+    task_table::{Column, Field, GroupBy, UDA},
+    theme::theme,
+};
+
+/// Outstanding follow-ups on delegated tasks, by who they're waiting on.
+#[derive(Args)]
+pub struct Waiting {
+    /// Only what's waiting on this person.
+    person: Option<String>,
+
+    #[clap(flatten)]
+    view: View,
+}
+
+impl CliMetaCommand for Waiting {
+    type Meta = Config;
+
+    fn run(&self, config: &Self::Meta) -> Result<()> {
+        let mut filter = String::from("delegatee.any: (status:pending or status:waiting)");
+        if let Some(ref person) = self.person {
+            filter += &format!(" delegatee:{}", shlex::quote(person));
+        }
+
+        let tasks = load_tasks(&filter)?;
+        let cols = vec![
+            Column::Field(Field::ID),
+            Column::Field(Field::Description),
+            Column::Field(Field::Age),
+            Column::Uda(UDA::delegation()),
+            Column::Field(Field::Waiting),
+        ];
+
+        let mut output = self
+            .view
+            .output(config)?
+            .grouped(Some(self.view.group_by.unwrap_or(GroupBy::Delegatee)));
+        output.section("waiting", None, &tasks, &cols, theme().waiting)?;
+        output.finish()
+    }
+}
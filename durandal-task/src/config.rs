@@ -1,9 +1,21 @@
-use std::{collections::HashSet, ffi::OsStr, path::Path};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
 
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use comfy_table::Color;
 use config;
 use serde::Deserialize;
 
+use crate::dates::DateStyle;
+use crate::output::Format;
+use crate::project::{Project, ProjectPattern, ProjectPolicy};
+use crate::task::{ActionCategory, Brainpower, Estimate};
+use crate::task_table::{parse_color, Column, Field, GroupBy, SortKey, UDA};
+use crate::theme::{theme, Theme, ThemeConfig};
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct IouConfig {
     pub servers: Vec<String>,
@@ -13,6 +25,14 @@ pub struct IouConfig {
 pub struct Rfcs {
     pub filter: String,
     pub rnr_task_project: String,
+
+    /// The context tags given to rnr tasks.
+    #[serde(default = "default_rfc_contexts")]
+    pub contexts: Vec<String>,
+}
+
+fn default_rfc_contexts() -> Vec<String> {
+    vec!["@computer".into(), "@work".into()]
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -50,14 +70,310 @@ fn default_waiting() -> String {
     )
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct Inbox {
+    /// How long "Do it" can take before it should have been deferred, the
+    /// two-minute rule.
+    #[serde(default = "default_do_it_minutes")]
+    pub do_it_minutes: u64,
+}
+
+impl Default for Inbox {
+    fn default() -> Self {
+        Self {
+            do_it_minutes: default_do_it_minutes(),
+        }
+    }
+}
+
+fn default_do_it_minutes() -> u64 {
+    2
+}
+
+/// A named report, shown with `report <name>`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomReport {
+    /// Shown by `reports`.
+    #[serde(default)]
+    pub description: String,
+
+    /// A handlebars template, with `today` and anything passed with `--var`
+    /// available to it.
+    pub filter: String,
+
+    #[serde(default = "default_report_columns")]
+    pub columns: Vec<Column>,
+
+    /// Taskwarrior style sort keys, like `due+` or `urgency-`.
+    #[serde(default)]
+    pub sort: Vec<SortKey>,
+
+    /// `project`, `context` or `status`.
+    pub group_by: Option<GroupBy>,
+
+    /// The description color, by name or as `#rrggbb`.
+    pub color: Option<String>,
+
+    pub limit: Option<usize>,
+}
+
+impl CustomReport {
+    pub fn color(&self) -> Color {
+        self.color
+            .as_deref()
+            .and_then(|c| parse_color(c).ok())
+            .unwrap_or(theme().description)
+    }
+}
+
+fn default_report_columns() -> Vec<Column> {
+    vec![
+        Field::ID.into(),
+        Field::Project.into(),
+        Field::AnnotatedDescription.into(),
+    ]
+}
+
+/// The contexts, brainpower levels and estimates tasks are categorized by.
+///
+/// Each list replaces the defaults entirely when specified.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Categories {
+    #[serde(default = "default_contexts")]
+    pub contexts: Vec<ActionCategory>,
+
+    /// The context tags given to follow-up tasks for delegated items.
+    #[serde(default = "default_follow_up_contexts")]
+    pub follow_up_contexts: Vec<String>,
+
+    #[serde(default = "default_brainpower")]
+    pub brainpower: Vec<Brainpower>,
+
+    /// The `uda` of the brainpower level new tasks get by default.
+    #[serde(default = "default_default_brainpower")]
+    pub default_brainpower: String,
+
+    #[serde(default = "default_estimates")]
+    pub estimates: Vec<Estimate>,
+
+    /// The `minutes` of the estimate new tasks get by default.
+    #[serde(default = "default_default_estimate")]
+    pub default_estimate: u64,
+}
+
+impl Categories {
+    /// Catch mistakes that would otherwise only show up halfway through a
+    /// prompt, or as tasks the hooks complain about.
+    pub fn validate(&self) -> Result<()> {
+        if self.contexts.is_empty() {
+            bail!("At least one context must be configured");
+        }
+
+        for tag in self
+            .contexts
+            .iter()
+            .map(|c| c.tag())
+            .chain(self.follow_up_contexts.iter().map(|t| t.as_str()))
+        {
+            if !tag.starts_with('@') {
+                bail!("Context tags must start with '@': {}", tag);
+            }
+        }
+
+        if self.brainpower_index(&self.default_brainpower).is_none() {
+            bail!(
+                "Default brainpower '{}' is not a configured level",
+                self.default_brainpower
+            );
+        }
+
+        if self.estimate_index(self.default_estimate).is_none() {
+            bail!(
+                "Default estimate {} is not a configured estimate",
+                self.default_estimate
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn brainpower_index(&self, uda: &str) -> Option<usize> {
+        self.brainpower.iter().position(|b| b.uda() == uda)
+    }
+
+    pub fn estimate_index(&self, minutes: u64) -> Option<usize> {
+        self.estimates.iter().position(|e| e.minutes == minutes)
+    }
+
+    /// The brainpower level labeled `name`, or stored as it, in any case.
+    pub fn brainpower_named(&self, name: &str) -> Option<&Brainpower> {
+        self.brainpower
+            .iter()
+            .find(|b| b.label.eq_ignore_ascii_case(name) || b.uda.eq_ignore_ascii_case(name))
+    }
+
+    /// The smallest estimate that `minutes` fits in, or the largest one when
+    /// it doesn't fit in any.
+    pub fn estimate_for(&self, minutes: u64) -> Option<&Estimate> {
+        self.estimates
+            .iter()
+            .filter(|e| e.minutes >= minutes)
+            .min_by_key(|e| e.minutes)
+            .or_else(|| self.estimates.iter().max_by_key(|e| e.minutes))
+    }
+}
+
+impl Default for Categories {
+    fn default() -> Self {
+        Self {
+            contexts: default_contexts(),
+            follow_up_contexts: default_follow_up_contexts(),
+            brainpower: default_brainpower(),
+            default_brainpower: default_default_brainpower(),
+            estimates: default_estimates(),
+            default_estimate: default_default_estimate(),
+        }
+    }
+}
+
+fn default_contexts() -> Vec<ActionCategory> {
+    vec![
+        ActionCategory::new("Agenda", "@agenda"),
+        ActionCategory::new("Anywhere", "@anywhere"),
+        ActionCategory::new("Computer", "@computer"),
+        ActionCategory::new("Errands", "@errands"),
+        ActionCategory::new("Home", "@home"),
+        ActionCategory::new("Phone", "@phone"),
+        ActionCategory::new("ReadAndReview", "@rnr"),
+        ActionCategory::new("Work", "@work"),
+    ]
+}
+
+fn default_follow_up_contexts() -> Vec<String> {
+    vec!["@work".into(), "@home".into()]
+}
+
+fn default_brainpower() -> Vec<Brainpower> {
+    vec![
+        Brainpower::new("Low", "L"),
+        Brainpower::new("Medium", "M"),
+        Brainpower::new("High", "H"),
+    ]
+}
+
+fn default_default_brainpower() -> String {
+    String::from("M")
+}
+
+fn default_estimates() -> Vec<Estimate> {
+    vec![
+        Estimate::new("Thirty minutes", 30),
+        Estimate::new("Six hours", 360),
+        Estimate::new("One day", 1440),
+        Estimate::new("Two days", 2880),
+        Estimate::new("More than two days (not well understood)", 9999),
+    ]
+}
+
+fn default_default_estimate() -> u64 {
+    30
+}
+
+/// How the taskwarrior hooks should treat a task without an `@context` tag.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MissingContext {
+    #[default]
+    Warn,
+    Reject,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Hooks {
+    #[serde(default)]
+    pub missing_context: MissingContext,
+
+    /// Where `hook install` should link the hooks.
+    #[serde(default = "default_hooks_dir")]
+    pub directory: String,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            missing_context: MissingContext::default(),
+            directory: default_hooks_dir(),
+        }
+    }
+}
+
+fn default_hooks_dir() -> String {
+    String::from("~/.task/hooks")
+}
+
+/// Settings for reading directly from a Taskwarrior 3 replica.
+///
+/// Only reads are served from the replica. Anything that modifies tasks still
+/// goes through the `task` cli.
+#[cfg(feature = "taskchampion")]
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaskChampion {
+    /// The directory containing `taskchampion.sqlite3`. This should match the
+    /// `data.location` setting in your taskrc.
+    #[serde(default = "default_data_location")]
+    pub data_location: String,
+}
+
+#[cfg(feature = "taskchampion")]
+fn default_data_location() -> String {
+    String::from("~/.task")
+}
+
+fn default_session_file() -> String {
+    String::from("~/.task/durandal-inbox.json")
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Config {
     pub iou: IouConfig,
+    /// Projects to leave out of `projects` and `replan`, see `ProjectPattern`.
+    #[serde(default)]
+    pub excluded_projects: Vec<ProjectPattern>,
+    /// Per-project policies, keyed by project name. Subprojects inherit from
+    /// their parents.
     #[serde(default)]
-    pub excluded_projects: HashSet<String>,
+    pub projects: HashMap<String, ProjectPolicy>,
     pub rfcs: Rfcs,
     pub requests: Requests,
     pub scrum: Scrum,
+    #[serde(default)]
+    pub inbox: Inbox,
+    #[serde(default)]
+    pub categories: Categories,
+    #[serde(default)]
+    pub reports: HashMap<String, CustomReport>,
+    /// UDAs to show as report columns, on top of the built-in github and jira
+    /// ones. Declaring a built-in one again overrides its label and type.
+    #[serde(default)]
+    pub udas: Vec<UDA>,
+    #[serde(default)]
+    pub hooks: Hooks,
+    /// Where `inbox` keeps track of the item it's on, for `inbox --resume`.
+    #[serde(default = "default_session_file")]
+    pub session_file: String,
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// `relative`, `absolute` or `both`.
+    #[serde(default)]
+    pub dates: DateStyle,
+    #[cfg(feature = "taskchampion")]
+    pub taskchampion: Option<TaskChampion>,
+    /// How read commands should print their tasks, from `--format`.
+    #[serde(skip)]
+    pub format: Format,
+    /// Where read commands should write their tasks, from `--output`.
+    #[serde(skip)]
+    pub output: Option<PathBuf>,
 }
 
 pub fn default_location() -> Result<String> {
@@ -76,6 +392,14 @@ pub fn default_location() -> Result<String> {
     }
 }
 
+/// Expand a leading `~/` to the user's home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
 impl Config {
     pub fn new(path: &str) -> Result<Self> {
         if !Path::new(path).exists() {
@@ -84,6 +408,246 @@ impl Config {
         let mut raw = config::Config::default();
         raw.merge(config::File::with_name(path))?;
 
-        Ok(raw.try_into()?)
+        let mut config: Self = raw.try_into()?;
+        config.resolve_udas()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// The declared or built-in UDA with `key`.
+    pub fn uda(&self, key: &str) -> Option<UDA> {
+        self.udas
+            .iter()
+            .cloned()
+            .chain(UDA::builtins())
+            .find(|u| u.key == key)
+    }
+
+    /// Report columns are parsed before the UDAs they name are known, so swap
+    /// each for its declaration.
+    pub(crate) fn resolve_udas(&mut self) -> Result<()> {
+        let udas: Vec<_> = self.udas.iter().cloned().chain(UDA::builtins()).collect();
+
+        for (name, report) in self.reports.iter_mut() {
+            for column in report.columns.iter_mut() {
+                if let Column::Uda(uda) = column {
+                    *uda = udas
+                        .iter()
+                        .find(|u| u.key == uda.key)
+                        .cloned()
+                        .ok_or_else(|| {
+                            anyhow!("Unknown column '{}' in report {}", uda.key, name)
+                        })?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.categories.validate()?;
+        Theme::from_config(&self.theme)?;
+
+        for (name, report) in &self.reports {
+            if let Some(ref color) = report.color {
+                parse_color(color).with_context(|| format!("In report {}", name))?;
+            }
+        }
+
+        for (name, policy) in &self.projects {
+            if let Some(ref contexts) = policy.contexts {
+                if let Some(tag) = contexts.iter().find(|t| !t.starts_with('@')) {
+                    bail!(
+                        "Context tags must start with '@': {} (project {})",
+                        tag,
+                        name
+                    );
+                }
+            }
+
+            if let Some(ref brainpower) = policy.brainpower {
+                if self.categories.brainpower_index(brainpower).is_none() {
+                    bail!(
+                        "Brainpower '{}' for project {} is not a configured level",
+                        brainpower,
+                        name
+                    );
+                }
+            }
+
+            if let Some(estimate) = policy.estimate {
+                if self.categories.estimate_index(estimate).is_none() {
+                    bail!(
+                        "Estimate {} for project {} is not a configured estimate",
+                        estimate,
+                        name
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_excluded(&self, project: &str) -> bool {
+        self.excluded_projects.iter().any(|p| p.matches(project))
+    }
+
+    /// The effective policy for `project`, including inherited settings.
+    pub fn policy(&self, project: &Project) -> ProjectPolicy {
+        ProjectPolicy::resolve(&self.projects, project)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::ReviewCadence;
+    use crate::task_table::UdaType;
+
+    fn categories(toml: &str) -> Categories {
+        let mut raw = config::Config::default();
+        raw.merge(config::File::from_str(toml, config::FileFormat::Toml))
+            .unwrap();
+        raw.try_into().unwrap()
+    }
+
+    #[test]
+    fn default_categories_are_valid() {
+        let defaults = categories("");
+
+        assert!(defaults.validate().is_ok());
+        assert_eq!(defaults.contexts.len(), 8);
+        assert_eq!(defaults.brainpower_index("M"), Some(1));
+        assert_eq!(defaults.estimate_index(9999), Some(4));
+        assert_eq!(defaults.brainpower_named("high").unwrap().uda(), "H");
+        assert_eq!(defaults.brainpower_named("l").unwrap().uda(), "L");
+        assert!(defaults.brainpower_named("huge").is_none());
+        assert_eq!(defaults.estimate_for(20).unwrap().minutes, 30);
+        assert_eq!(defaults.estimate_for(120).unwrap().minutes, 360);
+        assert_eq!(defaults.estimate_for(100_000).unwrap().minutes, 9999);
+    }
+
+    #[test]
+    fn configuring_categories() {
+        let custom = categories(
+            r#"
+            default_estimate = 15
+
+            [[contexts]]
+            label = "Lab"
+            tag = "@lab"
+
+            [[estimates]]
+            label = "Quick"
+            minutes = 15
+            "#,
+        );
+
+        assert!(custom.validate().is_ok());
+        assert_eq!(custom.contexts, vec![ActionCategory::new("Lab", "@lab")]);
+        assert_eq!(custom.brainpower.len(), 3);
+
+        let invalid = categories(
+            r#"
+            [[contexts]]
+            label = "Lab"
+            tag = "lab"
+            "#,
+        );
+
+        assert!(invalid.validate().is_err());
+        assert!(categories("default_brainpower = \"X\"").validate().is_err());
+    }
+
+    #[test]
+    fn configuring_project_policies() {
+        let mut raw = config::Config::default();
+        raw.merge(config::File::from_str(
+            r#"
+            excluded_projects = ["maybe*", "re:.*\\.interrupt"]
+
+            [iou]
+            servers = []
+            [rfcs]
+            filter = ""
+            rnr_task_project = ""
+            [requests]
+            filter = ""
+            [scrum]
+            completed = ""
+            modified = ""
+
+            [projects.work]
+            review = "weekly"
+            contexts = ["@work"]
+
+            [projects."work.oncall"]
+            skip_next_action = true
+            brainpower = "L"
+            "#,
+            config::FileFormat::Toml,
+        ))
+        .unwrap();
+        let config: Config = raw.try_into().unwrap();
+
+        assert!(config.validate().is_ok());
+        assert!(config.is_excluded("maybe.later"));
+        assert!(config.is_excluded("work.interrupt"));
+        assert!(!config.is_excluded("work"));
+
+        let policy = config.policy(&Project::from("work.oncall.pager"));
+        assert!(policy.skips_next_action());
+        assert_eq!(policy.brainpower, Some("L".into()));
+        assert_eq!(policy.contexts, Some(vec![String::from("@work")]));
+        assert_eq!(policy.review, Some(ReviewCadence::Weekly));
+    }
+
+    #[test]
+    fn configuring_reports() {
+        let config = crate::testing::config(
+            r#"
+            [reports.stale]
+            description = "Old tasks"
+            filter = "status:pending entry.before:{{today}}-30d"
+            columns = ["id", "due", "githuburl", "points"]
+            sort = ["due+", "urgency-"]
+            color = "dark_red"
+            limit = 10
+            group_by = "project"
+
+            [[udas]]
+            key = "points"
+            label = "Pts"
+            type = "numeric"
+            "#,
+        );
+
+        let report = &config.reports["stale"];
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            report.columns,
+            vec![
+                Column::Field(Field::ID),
+                Column::Field(Field::Due),
+                Column::Uda(UDA::github_url()),
+                Column::Uda(UDA {
+                    key: "points".into(),
+                    label: Some("Pts".into()),
+                    kind: UdaType::Numeric,
+                }),
+            ]
+        );
+        assert_eq!(report.sort.len(), 2);
+        assert_eq!(report.color(), Color::DarkRed);
+        assert_eq!(report.limit, Some(10));
+        assert_eq!(report.group_by, Some(GroupBy::Project));
+
+        let mut config = config.clone();
+        let report = config.reports.get_mut("stale").unwrap();
+        report.columns.push(Column::Uda(UDA::new("nope")));
+        assert!(config.resolve_udas().is_err());
     }
 }
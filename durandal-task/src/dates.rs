@@ -0,0 +1,110 @@
+//! Showing dates the way people talk about them, like "tomorrow" or "2w ago",
+//! with the absolute date available for anyone who'd rather have it.
+use std::sync::OnceLock;
+
+use chrono::{Local, NaiveDateTime, TimeZone, Utc};
+use clap::ArgEnum;
+use serde::Deserialize;
+
+static STYLE: OnceLock<DateStyle> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ArgEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DateStyle {
+    /// Like `in 3d` or `yesterday`.
+    #[default]
+    Relative,
+    /// Like `2026-10-21 Wed`.
+    Absolute,
+    /// Like `in 3d (2026-10-21 Wed)`.
+    Both,
+}
+
+/// Use `style` for all subsequent dates.
+pub fn configure(style: DateStyle) {
+    // if this was already set, the first configuration wins
+    let _ = STYLE.set(style);
+}
+
+/// The configured style, or relative.
+pub fn style() -> DateStyle {
+    STYLE.get().copied().unwrap_or_default()
+}
+
+/// Taskwarrior dates are in UTC, but "today" is wherever the user is.
+pub fn local(date: NaiveDateTime) -> NaiveDateTime {
+    Utc.from_utc_datetime(&date)
+        .with_timezone(&Local)
+        .naive_local()
+}
+
+/// A taskwarrior date in the configured style.
+pub fn format_date(date: NaiveDateTime) -> String {
+    format_date_at(local(date), Local::now().naive_local(), style())
+}
+
+/// `date` in `style`, relative to `now`. Both are local.
+pub fn format_date_at(date: NaiveDateTime, now: NaiveDateTime, style: DateStyle) -> String {
+    let absolute = date.format("%F %a").to_string();
+
+    match style {
+        DateStyle::Relative => relative(date, now),
+        DateStyle::Absolute => absolute,
+        DateStyle::Both => format!("{} ({})", relative(date, now), absolute),
+    }
+}
+
+/// Days are counted by calendar day, so anything later today is still today.
+fn relative(date: NaiveDateTime, now: NaiveDateTime) -> String {
+    let days = (date.date() - now.date()).num_days();
+
+    let amount = |days: i64| match days {
+        d if d < 14 => format!("{}d", d),
+        d if d < 60 => format!("{}w", d / 7),
+        d if d < 365 => format!("{}mo", d / 30),
+        d => format!("{}y", d / 365),
+    };
+
+    match days {
+        0 => String::from("today"),
+        1 => String::from("tomorrow"),
+        -1 => String::from("yesterday"),
+        d if d > 0 => format!("in {}", amount(d)),
+        d => format!("{} ago", amount(-d)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn formatting_dates() {
+        let at = |m, d, h| {
+            NaiveDate::from_ymd_opt(2026, m, d)
+                .unwrap()
+                .and_hms_opt(h, 0, 0)
+                .unwrap()
+        };
+        let now = at(10, 18, 9);
+        let relative = |date| format_date_at(date, now, DateStyle::Relative);
+
+        assert_eq!(relative(at(10, 18, 23)), "today");
+        assert_eq!(relative(at(10, 19, 0)), "tomorrow");
+        assert_eq!(relative(at(10, 17, 23)), "yesterday");
+        assert_eq!(relative(at(10, 21, 9)), "in 3d");
+        assert_eq!(relative(at(10, 4, 9)), "2w ago");
+        assert_eq!(relative(at(12, 25, 9)), "in 2mo");
+
+        assert_eq!(
+            format_date_at(at(10, 21, 9), now, DateStyle::Absolute),
+            "2026-10-21 Wed"
+        );
+        assert_eq!(
+            format_date_at(at(10, 21, 9), now, DateStyle::Both),
+            "in 3d (2026-10-21 Wed)"
+        );
+    }
+}
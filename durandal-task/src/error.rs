@@ -0,0 +1,50 @@
+use std::process::ExitStatus;
+
+use crate::parser::TaskOutput;
+
+/// Enumerates the ways a task command can go wrong, along with whatever we
+/// could make sense of in its output.
+#[derive(Debug)]
+pub enum TaskError {
+    /// The task command exited unsuccessfully.
+    CommandFailed {
+        args: Vec<String>,
+        status: ExitStatus,
+        output: TaskOutput,
+    },
+
+    /// The task command succeeded, but did not report doing what was asked.
+    Unexpected {
+        args: Vec<String>,
+        output: TaskOutput,
+    },
+}
+
+impl std::error::Error for TaskError {}
+
+impl std::fmt::Display for TaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match *self {
+            TaskError::CommandFailed {
+                ref args,
+                ref status,
+                ref output,
+            } => write!(
+                f,
+                "'task {}' failed ({}):\n{}",
+                args.join(" "),
+                status,
+                output
+            ),
+            TaskError::Unexpected {
+                ref args,
+                ref output,
+            } => write!(
+                f,
+                "'task {}' did not do what was expected:\n{}",
+                args.join(" "),
+                output
+            ),
+        }
+    }
+}
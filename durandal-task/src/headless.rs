@@ -0,0 +1,138 @@
+//! Driving the interactive workflows from a prepared answers document instead
+//! of a terminal, for bulk changes and automation.
+//!
+//! An answers document has a single `answers` list, consumed in order by
+//! whatever prompts the workflow lands on:
+//!
+//! ```yaml
+//! answers:
+//!   - confirm: false
+//!   - input: Migrate the billing cron jobs
+//!   - multi_select: [Computer, Work]
+//!   - select: Low
+//!   - default
+//!   - confirm: false
+//! ```
+use std::io::{self, Read};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use config::FileFormat;
+use serde::{Deserialize, Serialize};
+
+use crate::prompt::{Answer, Scripted};
+use crate::workflow::Outcome;
+
+#[derive(Debug, Deserialize)]
+struct Document {
+    answers: Vec<Answer>,
+}
+
+/// Load answers from a TOML, YAML or JSON file, or from stdin with `-`.
+pub fn load_answers(path: &str) -> Result<Vec<Answer>> {
+    let mut raw = config::Config::default();
+
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+
+        // json is close enough to yaml that we only need to try the two
+        if raw
+            .merge(config::File::from_str(&input, FileFormat::Toml))
+            .is_err()
+        {
+            raw = config::Config::default();
+            raw.merge(config::File::from_str(&input, FileFormat::Yaml))
+                .with_context(|| "Answers on stdin are neither TOML nor YAML")?;
+        }
+    } else {
+        raw.merge(config::File::from(Path::new(path)))
+            .with_context(|| format!("Could not read answers from {}", path))?;
+    }
+
+    let document: Document = raw
+        .try_into()
+        .with_context(|| "Expected an `answers` list")?;
+
+    Ok(document.answers)
+}
+
+/// What a headless run did, printed as JSON once it finishes.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub done: Vec<Outcome>,
+    /// Every prompt that was answered, in order, to make mismatched answers
+    /// easier to track down.
+    pub prompts: Vec<String>,
+    pub unused_answers: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Run `work` with the answers from `path`, then print a report of what was
+/// done. The report is printed even if `work` fails partway through.
+pub fn run<F>(path: &str, work: F) -> Result<()>
+where
+    F: FnOnce(&mut Scripted, &mut Vec<Outcome>) -> Result<()>,
+{
+    let mut prompter = Scripted::new(load_answers(path)?);
+    let mut done = Vec::new();
+
+    let result = work(&mut prompter, &mut done);
+
+    let report = Report {
+        done,
+        prompts: prompter.asked().to_vec(),
+        unused_answers: prompter.remaining(),
+        error: result.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+    println!("{}", serde_json::to_string_pretty(&report)?);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str, format: FileFormat) -> Vec<Answer> {
+        let mut raw = config::Config::default();
+        raw.merge(config::File::from_str(input, format)).unwrap();
+        raw.try_into::<Document>().unwrap().answers
+    }
+
+    #[test]
+    fn answers_documents() {
+        let expected = vec![
+            Answer::Confirm(false),
+            Answer::Select("Defer".into()),
+            Answer::MultiSelect(vec!["Home".into(), 2.into()]),
+            Answer::Input("+1d".into()),
+            Answer::Default,
+        ];
+
+        let yaml = r#"
+answers:
+  - confirm: false
+  - select: Defer
+  - multi_select: [Home, 2]
+  - input: "+1d"
+  - default
+"#;
+        assert_eq!(parse(yaml, FileFormat::Yaml), expected);
+
+        let toml = r#"
+[[answers]]
+confirm = false
+[[answers]]
+select = "Defer"
+[[answers]]
+multi_select = ["Home", 2]
+[[answers]]
+input = "+1d"
+[[answers]]
+default = true
+"#;
+        assert_eq!(parse(toml, FileFormat::Toml), expected);
+    }
+}
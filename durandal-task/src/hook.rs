@@ -0,0 +1,398 @@
+//! Taskwarrior hooks that enforce the conventions `TaskBuilder` follows for
+//! tasks that were created some other way, like plain `task add` or
+//! bugwarrior.
+//!
+//! See https://taskwarrior.org/docs/hooks_guide/ for the protocol. In short,
+//! the task(s) arrive as json lines on stdin, the (possibly modified) task is
+//! written back as a single json line on stdout, followed by any feedback
+//! lines. Exiting non-zero rejects the change.
+use std::ffi::OsStr;
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use task_hookrs::status::TaskStatus;
+use task_hookrs::task::Task;
+use task_hookrs::uda::UDAValue;
+
+use crate::config::{Categories, Config, MissingContext};
+use crate::task::{load_tasks, Processable};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    OnAdd,
+    OnModify,
+}
+
+impl HookEvent {
+    pub fn list() -> Vec<Self> {
+        vec![Self::OnAdd, Self::OnModify]
+    }
+
+    /// The prefix taskwarrior uses to decide when to run a hook file.
+    pub fn prefix(&self) -> &str {
+        match self {
+            Self::OnAdd => "on-add",
+            Self::OnModify => "on-modify",
+        }
+    }
+
+    /// Taskwarrior runs hooks based on their file name, so if we were invoked
+    /// through one of the installed links, figure out which one.
+    pub fn from_program<S: AsRef<OsStr>>(program: S) -> Option<Self> {
+        let name = Path::new(program.as_ref()).file_name()?.to_str()?;
+        Self::list()
+            .into_iter()
+            .find(|event| name.starts_with(event.prefix()))
+    }
+
+    /// Speak the hook protocol for this event, returning the exit code.
+    pub fn run<R: BufRead, W: Write>(
+        &self,
+        config: &Config,
+        input: R,
+        mut output: W,
+    ) -> Result<i32> {
+        let mut lines = input.lines();
+        let mut next_task = || -> Result<Task> {
+            let line = lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Expected a task on stdin"))??;
+            serde_json::from_str(&line).with_context(|| format!("Invalid task json: {}", line))
+        };
+
+        let outcome = match self {
+            Self::OnAdd => on_add(next_task()?, config, &pending_next_tasks)?,
+            Self::OnModify => {
+                let original = next_task()?;
+                on_modify(&original, next_task()?, config, &pending_next_tasks)?
+            }
+        };
+
+        if !outcome.rejected {
+            writeln!(output, "{}", serde_json::to_string(&outcome.task)?)?;
+        }
+
+        for msg in &outcome.feedback {
+            writeln!(output, "{}", msg)?;
+        }
+
+        Ok(if outcome.rejected { 1 } else { 0 })
+    }
+}
+
+/// The result of running a hook over a task.
+#[derive(Debug, Clone)]
+pub struct HookOutcome {
+    pub task: Task,
+    pub feedback: Vec<String>,
+    pub rejected: bool,
+}
+
+impl HookOutcome {
+    fn new(task: Task) -> Self {
+        Self {
+            task,
+            feedback: Vec::new(),
+            rejected: false,
+        }
+    }
+
+    fn reject(&mut self, msg: String) {
+        self.feedback.push(msg);
+        self.rejected = true;
+    }
+}
+
+/// Looks up the other pending `+next` tasks for a project.
+pub type NextTaskLookup = dyn Fn(&str) -> Result<Vec<Task>>;
+
+fn pending_next_tasks(project: &str) -> Result<Vec<Task>> {
+    // hooks=off, since nothing good comes from hooks calling themselves
+    load_tasks(&format!(
+        "rc.hooks=off {} +next status:pending",
+        shlex::quote(&format!("project.is:{}", project))
+    ))
+}
+
+pub fn on_add(task: Task, config: &Config, lookup: &NextTaskLookup) -> Result<HookOutcome> {
+    let mut outcome = HookOutcome::new(task);
+
+    fill_defaults(&mut outcome.task, &config.categories);
+
+    if !has_context(&outcome.task) {
+        missing_context(&mut outcome, config.hooks.missing_context);
+    }
+
+    if outcome.task.is_next() {
+        check_single_next(&mut outcome, lookup)?;
+    }
+
+    Ok(outcome)
+}
+
+pub fn on_modify(
+    original: &Task,
+    task: Task,
+    config: &Config,
+    lookup: &NextTaskLookup,
+) -> Result<HookOutcome> {
+    let mut outcome = HookOutcome::new(task);
+
+    // don't bother finished tasks or recurrence templates with any of this,
+    // waiting tasks are still live
+    if matches!(
+        outcome.task.status(),
+        TaskStatus::Completed | TaskStatus::Deleted | TaskStatus::Recurring
+    ) {
+        return Ok(outcome);
+    }
+
+    if !has_context(&outcome.task) {
+        // only removing the last context is worth rejecting, otherwise we'd
+        // block every edit to a task that predates these hooks
+        let policy = if has_context(original) {
+            config.hooks.missing_context
+        } else {
+            MissingContext::Warn
+        };
+        missing_context(&mut outcome, policy);
+    }
+
+    let became_next = outcome.task.is_next()
+        && (!original.is_next() || original.project() != outcome.task.project());
+
+    if became_next {
+        check_single_next(&mut outcome, lookup)?;
+    }
+
+    Ok(outcome)
+}
+
+fn fill_defaults(task: &mut Task, categories: &Categories) {
+    let udas = task.uda_mut();
+
+    udas.entry("brain".into())
+        .or_insert_with(|| UDAValue::Str(categories.default_brainpower.clone()));
+    udas.entry("est".into())
+        .or_insert_with(|| UDAValue::U64(categories.default_estimate));
+}
+
+/// Inbox items haven't been processed yet, so they get a pass.
+fn has_context(task: &Task) -> bool {
+    task.has_tag("in")
+        || task
+            .tags()
+            .map(|tags| tags.iter().any(|t| t.starts_with('@')))
+            .unwrap_or(false)
+}
+
+fn missing_context(outcome: &mut HookOutcome, policy: MissingContext) {
+    let msg = format!("Task '{}' has no @context tag", outcome.task.description());
+
+    match policy {
+        MissingContext::Warn => outcome.feedback.push(format!("Warning: {}", msg)),
+        MissingContext::Reject => outcome.reject(msg),
+    }
+}
+
+fn check_single_next(outcome: &mut HookOutcome, lookup: &NextTaskLookup) -> Result<()> {
+    let project = match outcome.task.project() {
+        Some(project) => project.clone(),
+        None => return Ok(()),
+    };
+
+    let others: Vec<_> = lookup(&project)?
+        .into_iter()
+        .filter(|t| t.uuid() != outcome.task.uuid())
+        .collect();
+
+    if !others.is_empty() {
+        let existing: Vec<_> = others
+            .iter()
+            .map(|t| format!("'{}'", t.description()))
+            .collect();
+        outcome.reject(format!(
+            "Project '{}' already has a next task: {}",
+            project,
+            existing.join(", ")
+        ));
+    }
+
+    Ok(())
+}
+
+/// Link the hooks for every event into `dir`, pointing at `target`.
+pub fn install(dir: &Path, target: &Path, force: bool) -> Result<Vec<String>> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Could not create hooks directory {:?}", dir))?;
+
+    let mut installed = Vec::new();
+
+    for event in HookEvent::list() {
+        let link = dir.join(format!("{}.durandal", event.prefix()));
+
+        if link.symlink_metadata().is_ok() {
+            if std::fs::read_link(&link).ok().as_deref() == Some(target) {
+                continue;
+            }
+
+            if !force {
+                bail!("{:?} already exists, use --force to replace it", link);
+            }
+
+            std::fs::remove_file(&link)
+                .with_context(|| format!("Could not remove existing hook {:?}", link))?;
+        }
+
+        std::os::unix::fs::symlink(target, &link)
+            .with_context(|| format!("Could not link {:?} to {:?}", link, target))?;
+
+        installed.push(link.display().to_string());
+    }
+
+    Ok(installed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(missing_context: MissingContext) -> Config {
+        let mut raw = config::Config::default();
+        raw.merge(config::File::from_str(
+            r#"
+            [iou]
+            servers = []
+            [rfcs]
+            filter = ""
+            rnr_task_project = ""
+            [requests]
+            filter = ""
+            [scrum]
+            completed = ""
+            modified = ""
+            "#,
+            config::FileFormat::Toml,
+        ))
+        .unwrap();
+        let mut config: Config = raw.try_into().unwrap();
+        config.hooks.missing_context = missing_context;
+        config
+    }
+
+    fn task(json: &str) -> Task {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn no_next_tasks(_: &str) -> Result<Vec<Task>> {
+        Ok(Vec::new())
+    }
+
+    #[test]
+    fn detecting_hook_from_program_name() {
+        assert_eq!(
+            HookEvent::from_program("/home/me/.task/hooks/on-add.durandal"),
+            Some(HookEvent::OnAdd)
+        );
+        assert_eq!(
+            HookEvent::from_program("on-modify.durandal"),
+            Some(HookEvent::OnModify)
+        );
+        assert_eq!(HookEvent::from_program("durandal-task"), None);
+    }
+
+    #[test]
+    fn filling_in_defaults() {
+        let added = task(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "description": "a", "tags": ["@work"]}"#,
+        );
+
+        let outcome = on_add(added, &config(MissingContext::Reject), &no_next_tasks).unwrap();
+
+        assert!(!outcome.rejected);
+        assert!(outcome.feedback.is_empty());
+        assert_eq!(
+            outcome.task.uda().get("brain"),
+            Some(&UDAValue::Str("M".into()))
+        );
+        assert_eq!(outcome.task.uda().get("est"), Some(&UDAValue::U64(30)));
+
+        // editing a task that predates the hooks leaves it alone
+        let original = task(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "description": "a", "tags": ["@work"]}"#,
+        );
+        let mut modified = original.clone();
+        *modified.description_mut() = String::from("b");
+
+        let config = config(MissingContext::Reject);
+        let outcome = on_modify(&original, modified, &config, &no_next_tasks).unwrap();
+
+        assert!(outcome.task.uda().is_empty());
+    }
+
+    #[test]
+    fn handling_missing_context() {
+        let added = task(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "description": "a"}"#,
+        );
+
+        let outcome = on_add(added.clone(), &config(MissingContext::Warn), &no_next_tasks).unwrap();
+        assert!(!outcome.rejected);
+        assert_eq!(outcome.feedback.len(), 1);
+
+        let outcome = on_add(added, &config(MissingContext::Reject), &no_next_tasks).unwrap();
+        assert!(outcome.rejected);
+    }
+
+    #[test]
+    fn checking_waiting_tasks() {
+        let original = task(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "waiting",
+                "entry": "20220101T000000Z", "wait": "20300101T000000Z",
+                "description": "a", "tags": ["@work"]}"#,
+        );
+        let mut modified = original.clone();
+        modified.tags_mut().unwrap().clear();
+
+        let config = config(MissingContext::Reject);
+        let outcome = on_modify(&original, modified, &config, &no_next_tasks).unwrap();
+        assert!(outcome.rejected);
+
+        let mut completed = original.clone();
+        completed.tags_mut().unwrap().clear();
+        *completed.status_mut() = TaskStatus::Completed;
+        let outcome = on_modify(&original, completed, &config, &no_next_tasks).unwrap();
+        assert!(!outcome.rejected);
+    }
+
+    #[test]
+    fn enforcing_single_next_task() {
+        let existing = task(
+            r#"{"uuid": "7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21", "status": "pending",
+                "entry": "20220101T000000Z", "description": "old", "project": "work",
+                "tags": ["next", "@work"]}"#,
+        );
+        let lookup = move |_: &str| Ok(vec![existing.clone()]);
+
+        let original = task(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "description": "new", "project": "work",
+                "tags": ["@work"]}"#,
+        );
+        let mut modified = original.clone();
+        modified.tags_mut().unwrap().push("next".into());
+
+        let config = config(MissingContext::Warn);
+
+        let outcome = on_modify(&original, modified.clone(), &config, &lookup).unwrap();
+        assert!(outcome.rejected);
+
+        // already being next means some other edit, which is fine
+        let outcome = on_modify(&modified, modified.clone(), &config, &lookup).unwrap();
+        assert!(!outcome.rejected);
+    }
+}
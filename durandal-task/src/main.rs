@@ -1,11 +1,32 @@
 mod cli;
 mod config;
+mod dates;
+mod error;
+mod headless;
+mod hook;
 mod iou_client;
+mod output;
 mod parser;
+mod project;
+mod prompt;
+#[cfg(feature = "taskchampion")]
+mod replica;
 mod task;
 mod task_table;
+#[cfg(test)]
+mod testing;
+mod theme;
 mod workflow;
 
 fn main() -> Result<(), anyhow::Error> {
+    // when linked into the taskwarrior hooks directory, we're invoked as the
+    // hook itself
+    if let Some(event) = std::env::args_os()
+        .next()
+        .and_then(hook::HookEvent::from_program)
+    {
+        return cli::Cli::run_hook(event);
+    }
+
     cli::Cli::run()
 }
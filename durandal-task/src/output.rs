@@ -0,0 +1,314 @@
+//! Rendering the task listings of read-only commands as terminal tables,
+//! markdown, slack messages or json.
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use anyhow::{Context, Result};
+use clap::{ArgEnum, Args};
+use comfy_table::Color;
+use console::style;
+use serde_json::{Map, Value};
+use task_hookrs::task::Task;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::task_table::{group_tasks, sort_tasks, GroupBy, SortKey, TaskAttr, TaskTable};
+use crate::theme::{paint, theme};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    Markdown,
+    /// Slack's mrkdwn, as bullet lists since it has no tables.
+    Slack,
+}
+
+/// How read commands order and group their tasks.
+#[derive(Debug, Clone, Default, Args)]
+pub struct View {
+    /// Sort by a taskwarrior style key, like `due+` or `urgency-`, on any of
+    /// description, due, entry, estimate, id, modified, project, scheduled,
+    /// urgency or wait. May be repeated, earlier keys take precedence.
+    #[clap(long = "sort", value_name = "KEY", parse(try_from_str))]
+    pub sort: Vec<SortKey>,
+
+    /// Split the tasks into groups, each with a header and count. Json output
+    /// is only sorted.
+    #[clap(long, arg_enum, value_name = "GROUP")]
+    pub group_by: Option<GroupBy>,
+}
+
+impl View {
+    pub fn output(&self, config: &Config) -> Result<Output> {
+        Ok(Output::from_config(config)?
+            .sorted(&self.sort)
+            .grouped(self.group_by))
+    }
+}
+
+/// A set of task sections, where a task is only ever shown in the first
+/// section it appears in.
+///
+/// Text, markdown and slack are written as each section is added, json is
+/// written as a single object keyed by section once everything has been added.
+pub struct Output {
+    format: Format,
+    out: Box<dyn Write>,
+    seen: HashSet<Uuid>,
+    json: Map<String, Value>,
+    sections: usize,
+    sort: Vec<SortKey>,
+    group: Option<GroupBy>,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            out: Box::new(io::stdout()),
+            seen: HashSet::new(),
+            json: Map::new(),
+            sections: 0,
+            sort: Vec::new(),
+            group: None,
+        }
+    }
+
+    /// In the format from `--format`, written to the file from `--output` if
+    /// there is one.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut output = Self::new(config.format);
+
+        if let Some(ref path) = config.output {
+            let file = File::create(path)
+                .with_context(|| format!("Could not write to {}", path.display()))?;
+            output.out = Box::new(BufWriter::new(file));
+
+            // nobody wants escape codes in a file
+            console::set_colors_enabled(false);
+        }
+
+        Ok(output)
+    }
+
+    pub fn sorted(mut self, keys: &[SortKey]) -> Self {
+        self.sort = keys.to_vec();
+        self
+    }
+
+    pub fn grouped(mut self, group: Option<GroupBy>) -> Self {
+        self.group = group;
+        self
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Add a section of tasks, skipping any already shown in an earlier one.
+    pub fn section<C: TaskAttr>(
+        &mut self,
+        key: &str,
+        title: Option<&str>,
+        tasks: &[Task],
+        columns: &[C],
+        color: Color,
+    ) -> Result<()> {
+        let mut tasks = tasks.to_vec();
+        sort_tasks(&mut tasks, &self.sort);
+
+        let tasks: Vec<_> = tasks
+            .iter()
+            .filter(|t| self.seen.insert(*t.uuid()))
+            .collect();
+
+        let spacing = if self.sections > 0 { "\n" } else { "" };
+
+        match self.format {
+            Format::Text => {
+                if let Some(title) = title {
+                    writeln!(self.out, "{}{}", spacing, paint(theme().header, title))?;
+                }
+
+                if tasks.is_empty() {
+                    writeln!(self.out, "    {}", style("--none--").yellow())?;
+                } else {
+                    let mut table = TaskTable::new(columns)
+                        .description_color(color)
+                        .group_by(self.group);
+                    for task in tasks {
+                        table.add_row(task);
+                    }
+                    writeln!(self.out, "{}", table)?;
+                }
+            }
+            Format::Markdown => {
+                if let Some(title) = title {
+                    writeln!(self.out, "## {}\n", title.trim_end_matches(':'))?;
+                }
+                match self.group {
+                    Some(group) if !tasks.is_empty() => {
+                        for (label, tasks) in group_tasks(&tasks, group) {
+                            writeln!(self.out, "### {} ({})\n", label, tasks.len())?;
+                            writeln!(self.out, "{}", markdown_table(&tasks, columns))?;
+                        }
+                    }
+                    _ => writeln!(self.out, "{}", markdown_table(&tasks, columns))?,
+                }
+            }
+            Format::Slack => {
+                if let Some(title) = title {
+                    writeln!(self.out, "{}*{}*", spacing, title.trim_end_matches(':'))?;
+                }
+                match self.group {
+                    Some(group) if !tasks.is_empty() => {
+                        for (label, tasks) in group_tasks(&tasks, group) {
+                            writeln!(self.out, "_{}_ ({})", slack_escape(&label), tasks.len())?;
+                            write!(self.out, "{}", slack_list(&tasks, columns))?;
+                        }
+                    }
+                    _ => write!(self.out, "{}", slack_list(&tasks, columns))?,
+                }
+            }
+            Format::Json => {
+                self.json.insert(key.into(), serde_json::to_value(&tasks)?);
+            }
+        }
+
+        self.sections += 1;
+        Ok(())
+    }
+
+    /// Anything that isn't a list of tasks, only included in json output.
+    pub fn value(&mut self, key: &str, value: Value) {
+        self.json.insert(key.into(), value);
+    }
+
+    /// A line of anything else a command has to say, for the formats other
+    /// than json.
+    pub fn line<D: Display>(&mut self, line: D) -> Result<()> {
+        writeln!(self.out, "{}", line)?;
+        Ok(())
+    }
+
+    /// Write the json object, if that's the format in use.
+    pub fn finish(mut self) -> Result<()> {
+        if self.format == Format::Json {
+            writeln!(self.out, "{}", serde_json::to_string_pretty(&self.json)?)?;
+        }
+
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+fn markdown_table<C: TaskAttr>(tasks: &[&Task], columns: &[C]) -> String {
+    if tasks.is_empty() {
+        return String::from("_none_\n");
+    }
+
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', "<br>");
+    let row = |cells: Vec<String>| format!("| {} |", cells.join(" | "));
+
+    let mut lines = vec![
+        row(columns.iter().map(|c| cell(c.name())).collect()),
+        row(columns.iter().map(|_| String::from("---")).collect()),
+    ];
+
+    for task in tasks {
+        lines.push(row(columns
+            .iter()
+            .map(|c| cell(&c.get_value(task)))
+            .collect()));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn slack_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// A bullet per task with its non-empty columns, and any further lines of a
+/// column, like annotations, as sub-bullets.
+fn slack_list<C: TaskAttr>(tasks: &[&Task], columns: &[C]) -> String {
+    if tasks.is_empty() {
+        return String::from("_none_\n");
+    }
+
+    let mut lines = vec![];
+
+    for task in tasks {
+        let mut values = vec![];
+        let mut extra = vec![];
+
+        for column in columns {
+            let value = column.get_value(task);
+            let mut parts = value.lines().map(|l| slack_escape(l.trim()));
+
+            match parts.next() {
+                Some(first) if !first.is_empty() => values.push(first),
+                _ => {}
+            }
+            extra.extend(parts);
+        }
+
+        lines.push(format!("• {}", values.join(" · ")));
+        lines.extend(extra.iter().map(|l| format!("    ◦ {}", l)));
+    }
+
+    lines.join("\n") + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use task_hookrs::import::import_task;
+
+    use super::*;
+    use crate::dates::format_date;
+    use crate::task_table::Field;
+
+    #[test]
+    fn rendering_markdown() {
+        let task = import_task(
+            r#"{"id":3,"uuid":"0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f00","status":"pending",
+            "entry":"20261001T090000Z","description":"pipes | and\nnewlines","project":"home"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            markdown_table(&[&task], &[Field::ID, Field::Project, Field::Description]),
+            "| ID | Project | Description |\n| --- | --- | --- |\n\
+             | 3 | home | pipes \\| and<br>newlines |\n"
+        );
+        assert_eq!(markdown_table(&[], &[Field::ID]), "_none_\n");
+    }
+
+    #[test]
+    fn rendering_slack() {
+        let task = import_task(
+            r#"{"id":3,"uuid":"0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f00","status":"pending",
+            "entry":"20261001T090000Z","description":"fix <html> & stuff",
+            "annotations":[{"entry":"20261002T090000Z","description":"see the PR"}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            slack_list(
+                &[&task],
+                &[Field::ID, Field::Project, Field::AnnotatedDescription]
+            ),
+            format!(
+                "• 3 · fix &lt;html&gt; &amp; stuff\n    ◦ {} see the PR\n",
+                format_date(**task.annotations().unwrap()[0].entry())
+            )
+        );
+        assert_eq!(slack_list(&[], &[Field::ID]), "_none_\n");
+    }
+}
@@ -1,31 +1,813 @@
-use anyhow::{bail, Result};
-use nom::bytes::complete::tag;
-use nom::character::complete::{char, u64};
-use nom::sequence::delimited;
+use std::fmt;
+
+use anyhow::{anyhow, bail, Result};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{char, multispace1, space1, u64};
+use nom::combinator::{all_consuming, eof, map, opt, peek, recognize, rest, value};
+use nom::multi::separated_list0;
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
 use nom::IResult;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::project::Project;
+use crate::task::TaskBuilder;
+
+/// The things taskwarrior reports having done to tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Annotated,
+    Completed,
+    Deleted,
+    Denotated,
+    Imported,
+    Modified,
+    Started,
+    Stopped,
+}
+
+impl Verb {
+    /// The form used in the per-task lines, like "Modifying task 3 'foo'."
+    fn progressive(&self) -> &str {
+        match self {
+            Self::Annotated => "Annotating",
+            Self::Completed => "Completed",
+            Self::Deleted => "Deleting",
+            Self::Denotated => "Denotating",
+            Self::Imported => "Importing",
+            Self::Modified => "Modifying",
+            Self::Started => "Starting",
+            Self::Stopped => "Stopping",
+        }
+    }
+}
+
+impl fmt::Display for Verb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Annotated => write!(f, "Annotated"),
+            Self::Completed => write!(f, "Completed"),
+            Self::Deleted => write!(f, "Deleted"),
+            Self::Denotated => write!(f, "Denotated"),
+            Self::Imported => write!(f, "Imported"),
+            Self::Modified => write!(f, "Modified"),
+            Self::Started => write!(f, "Started"),
+            Self::Stopped => write!(f, "Stopped"),
+        }
+    }
+}
+
+/// Taskwarrior refers to tasks by id, or, for tasks without one, by a short
+/// uuid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskRef {
+    Id(u64),
+    Uuid(String),
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{}", id),
+            Self::Uuid(uuid) => write!(f, "{}", uuid),
+        }
+    }
+}
+
+/// A task reported as created by `task add`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Created {
+    pub task: TaskRef,
+    /// Set when the created task is a recurrence template.
+    pub recurrence_template: Option<Option<u64>>,
+}
+
+/// A single task an action was applied to, like "Completed task 3 'foo'."
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Action {
+    pub verb: Verb,
+    pub task: TaskRef,
+    pub description: String,
+}
+
+/// Everything we could make sense of in the output of a task command.
+///
+/// Lines we don't recognize are kept as messages rather than being treated as
+/// errors, since hooks and newer taskwarrior versions are free to print
+/// whatever they want.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskOutput {
+    pub created: Vec<Created>,
+    pub actions: Vec<Action>,
+    pub counts: Vec<(Verb, u64)>,
+    pub imported: Vec<Uuid>,
+    /// Tasks `task import` left alone, since they were already up to date.
+    pub skipped: Vec<Uuid>,
+    pub overrides: Vec<String>,
+    /// Unrecognized lines printed before taskwarrior's own output, which is
+    /// where hook feedback ends up.
+    pub hook_messages: Vec<String>,
+    pub warnings: Vec<String>,
+    pub messages: Vec<String>,
+}
+
+impl TaskOutput {
+    /// Parse stdout and stderr of a task command. The order only matters for
+    /// deciding what counts as hook feedback.
+    pub fn parse(stdout: &str, stderr: &str) -> Self {
+        let mut output = Self::default();
+        let mut seen_task_output = false;
+
+        for line in stderr.lines().chain(stdout.lines()) {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_line(line) {
+                Some(Line::Created(created)) => output.created.push(created),
+                Some(Line::Action(action)) => output.actions.push(action),
+                Some(Line::Count(verb, count)) => output.counts.push((verb, count)),
+                Some(Line::Imported(uuid)) => output.imported.push(uuid),
+                Some(Line::Skipped(uuid)) => output.skipped.push(uuid),
+                Some(Line::Override(setting)) => {
+                    output.overrides.push(setting);
+                    continue;
+                }
+                Some(Line::Warning(msg)) => {
+                    output.warnings.push(msg);
+                    continue;
+                }
+                Some(Line::Note(msg)) => output.messages.push(msg),
+                None if seen_task_output => output.messages.push(line.trim().into()),
+                None => output.hook_messages.push(line.trim().into()),
+            }
+
+            seen_task_output = true;
+        }
+
+        output
+    }
+
+    /// The number of tasks reported for `verb`, if taskwarrior reported any.
+    pub fn count(&self, verb: Verb) -> Option<u64> {
+        self.counts
+            .iter()
+            .filter(|(v, _)| *v == verb)
+            .map(|(_, c)| *c)
+            .reduce(|a, b| a + b)
+    }
+
+    /// Things the user probably wants to see: hook feedback and warnings.
+    pub fn feedback(&self) -> Vec<&str> {
+        self.hook_messages
+            .iter()
+            .chain(self.warnings.iter())
+            .map(|s| s.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Display for TaskOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
 
-pub fn new_task_parser(i: &str) -> Result<u64> {
-    let out: IResult<&str, u64> = delimited(tag("Created task "), u64, char('.'))(i);
-    match out {
-        Ok((_, id)) => Ok(id),
-        Err(e) => bail!("Could not parse id from created task: {:?}", e),
+        for msg in self.hook_messages.iter().chain(self.warnings.iter()) {
+            lines.push(msg.clone());
+        }
+
+        for created in &self.created {
+            lines.push(match created.recurrence_template {
+                Some(_) => format!("Created recurrence template {}", created.task),
+                None => format!("Created task {}", created.task),
+            });
+        }
+
+        for action in &self.actions {
+            lines.push(format!(
+                "{} task {} '{}'",
+                action.verb, action.task, action.description
+            ));
+        }
+
+        for (verb, count) in &self.counts {
+            lines.push(format!("{} {} task(s)", verb, count));
+        }
+
+        if !self.imported.is_empty() {
+            lines.push(format!("Imported {} task(s)", self.imported.len()));
+        }
+
+        if !self.skipped.is_empty() {
+            lines.push(format!("Skipped {} task(s)", self.skipped.len()));
+        }
+
+        for msg in &self.messages {
+            lines.push(msg.clone());
+        }
+
+        if !self.overrides.is_empty() {
+            lines.push(format!("Overrides: {}", self.overrides.join(", ")));
+        }
+
+        write!(f, "{}", lines.join("\n"))
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+enum Line {
+    Created(Created),
+    Action(Action),
+    Count(Verb, u64),
+    Imported(Uuid),
+    Skipped(Uuid),
+    Override(String),
+    Warning(String),
+    /// Something taskwarrior itself says, like project completion notes.
+    Note(String),
+}
+
+fn parse_line(line: &str) -> Option<Line> {
+    let out: IResult<&str, Line> = all_consuming(alt((
+        created_parser,
+        action_parser,
+        count_parser,
+        imported_parser,
+        override_parser,
+        warning_parser,
+        note_parser,
+    )))(line.trim());
+
+    out.ok().map(|(_, line)| line)
+}
+
+fn task_ref(i: &str) -> IResult<&str, TaskRef> {
+    alt((
+        map(
+            terminated(u64, peek(alt((tag("."), tag(" "))))),
+            TaskRef::Id,
+        ),
+        map(
+            take_while1(|c: char| c.is_ascii_hexdigit() || c == '-'),
+            |s: &str| TaskRef::Uuid(s.into()),
+        ),
+    ))(i)
+}
+
+/// `Created task 4.` or `Created task 4 (recurrence template).`, with some
+/// versions including the template id as well.
+fn created_parser(i: &str) -> IResult<&str, Line> {
+    map(
+        delimited(
+            tag("Created task "),
+            pair(
+                task_ref,
+                opt(delimited(
+                    tag(" (recurrence template"),
+                    opt(preceded(char(' '), u64)),
+                    char(')'),
+                )),
+            ),
+            char('.'),
+        ),
+        |(task, recurrence_template)| {
+            Line::Created(Created {
+                task,
+                recurrence_template,
+            })
+        },
+    )(i)
+}
+
+/// `Modifying task 3 'the description'.`
+fn action_parser(i: &str) -> IResult<&str, Line> {
+    let (i, verb) = alt((
+        value(Verb::Annotated, tag(Verb::Annotated.progressive())),
+        value(Verb::Completed, tag(Verb::Completed.progressive())),
+        value(Verb::Deleted, tag(Verb::Deleted.progressive())),
+        value(Verb::Denotated, tag(Verb::Denotated.progressive())),
+        value(Verb::Modified, tag(Verb::Modified.progressive())),
+        value(Verb::Started, tag(Verb::Started.progressive())),
+        value(Verb::Stopped, tag(Verb::Stopped.progressive())),
+    ))(i)?;
+    let (i, _) = tuple((opt(tag(" recurring")), tag(" task ")))(i)?;
+    let (i, task) = task_ref(i)?;
+    let (i, _) = tag(" '")(i)?;
+    let (i, quoted) = rest(i)?;
+
+    // the description itself may contain quotes, so only the end counts
+    match quoted.strip_suffix("'.") {
+        Some(description) => Ok((
+            "",
+            Line::Action(Action {
+                verb,
+                task,
+                description: description.into(),
+            }),
+        )),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Tag,
+        ))),
+    }
+}
+
+/// `Modified 2 tasks.`
+fn count_parser(i: &str) -> IResult<&str, Line> {
+    let (i, verb) = alt((
+        value(Verb::Annotated, tag("Annotated")),
+        value(Verb::Completed, tag("Completed")),
+        value(Verb::Deleted, tag("Deleted")),
+        value(Verb::Denotated, tag("Denotated")),
+        value(Verb::Imported, tag("Imported")),
+        value(Verb::Modified, tag("Modified")),
+        value(Verb::Started, tag("Started")),
+        value(Verb::Stopped, tag("Stopped")),
+    ))(i)?;
+
+    map(
+        delimited(
+            char(' '),
+            u64,
+            pair(alt((tag(" tasks"), tag(" task"))), char('.')),
+        ),
+        move |count| Line::Count(verb, count),
+    )(i)
+}
+
+/// The per-task lines from `task import`, like ` add  <uuid> description`.
+fn imported_parser(i: &str) -> IResult<&str, Line> {
+    map(
+        tuple((
+            alt((
+                value(true, tag("add")),
+                value(true, tag("mod")),
+                value(false, tag("skip")),
+            )),
+            space1,
+            take_while1(|c: char| c.is_ascii_hexdigit() || c == '-'),
+            rest,
+        )),
+        |(imported, _, uuid, _): (bool, &str, &str, &str)| (imported, uuid),
+    )(i)
+    .and_then(|(rest, (imported, uuid))| match Uuid::parse_str(uuid) {
+        Ok(uuid) if imported => Ok((rest, Line::Imported(uuid))),
+        Ok(uuid) => Ok((rest, Line::Skipped(uuid))),
+        Err(_) => Err(nom::Err::Error(nom::error::Error::new(
+            i,
+            nom::error::ErrorKind::Verify,
+        ))),
+    })
+}
+
+/// `Configuration override rc.confirmation=off`
+fn override_parser(i: &str) -> IResult<&str, Line> {
+    map(preceded(tag("Configuration override "), rest), |s: &str| {
+        Line::Override(s.into())
+    })(i)
+}
+
+fn warning_parser(i: &str) -> IResult<&str, Line> {
+    map(
+        preceded(alt((tag("Warning: "), tag("warning: "))), rest),
+        |s: &str| Line::Warning(s.into()),
+    )(i)
+}
+
+/// `Importing 'STDIN'`, `The project 'work' has changed.` and the like.
+fn note_parser(i: &str) -> IResult<&str, Line> {
+    map(
+        recognize(tuple((
+            alt((
+                tag("Importing "),
+                tag("The project "),
+                tag("Project "),
+                tag("There are "),
+                tag("There is "),
+                tag("Unblocked "),
+            )),
+            rest,
+        ))),
+        |s: &str| Line::Note(s.into()),
+    )(i)
+}
+
+/// A task written out on a single line, like
+/// `Call Bob about budget @phone due:fri ~30m !high proj:finance`.
+///
+/// `@context`, `+tag`, `proj:`, `due:`, `wait:`, `sched:`, `until:`,
+/// `recur:` and `pri:` mean what they do in taskwarrior, `~30m` (or `h`, `d`)
+/// is an estimate and `!high` a brainpower level. Everything else is the
+/// description.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InlineTask {
+    pub description: String,
+    /// Context tags, including the `@`.
+    pub contexts: Vec<String>,
+    pub tags: Vec<String>,
+    pub project: Option<String>,
+    pub due: Option<String>,
+    pub wait: Option<String>,
+    pub scheduled: Option<String>,
+    pub until: Option<String>,
+    pub recur: Option<String>,
+    pub priority: Option<String>,
+    /// How long the task will take, in minutes.
+    pub minutes: Option<u64>,
+    /// The brainpower level as written, matched against the configured ones
+    /// when building.
+    pub brainpower: Option<String>,
+}
+
+impl InlineTask {
+    pub fn parse(line: &str) -> Result<Self> {
+        let words: IResult<&str, Vec<Word<'_>>> =
+            all_consuming(separated_list0(multispace1, word))(line.trim());
+        let (_, words) = words.map_err(|e| anyhow!("Could not parse '{}': {}", line, e))?;
+
+        let mut task = Self::default();
+        let mut description = Vec::new();
+
+        for word in words {
+            match word {
+                Word::Context(context) => task.contexts.push(context.into()),
+                Word::Tag(tag) => task.tags.push(tag.into()),
+                Word::Attribute(key, value) => {
+                    *match key {
+                        "project" => &mut task.project,
+                        "due" => &mut task.due,
+                        "wait" => &mut task.wait,
+                        "scheduled" => &mut task.scheduled,
+                        "until" => &mut task.until,
+                        "recur" => &mut task.recur,
+                        _ => &mut task.priority,
+                    } = Some(value.into());
+                }
+                Word::Estimate(minutes) => task.minutes = Some(minutes),
+                Word::Brainpower(level) => task.brainpower = Some(level.into()),
+                Word::Plain(word) => description.push(word),
+            }
+        }
+
+        if description.is_empty() {
+            bail!("'{}' has no description", line.trim());
+        }
+        task.description = description.join(" ");
+
+        Ok(task)
+    }
+
+    /// A builder for the task, with the estimate and brainpower mapped onto
+    /// the configured ones. Tasks without a context go to the inbox.
+    pub fn builder(&self, config: &Config) -> Result<TaskBuilder> {
+        let categories = &config.categories;
+        let mut builder = TaskBuilder::new(categories);
+        builder.with_description(&self.description);
+
+        if let Some(ref project) = self.project {
+            let project = Project::from(project.as_str());
+            builder
+                .with_policy(&config.policy(&project))
+                .with_project(project);
+        }
+
+        for context in &self.contexts {
+            if !categories.contexts.iter().any(|c| c.tag() == context) {
+                bail!("Unknown context '{}'", context);
+            }
+        }
+
+        if self.contexts.is_empty() {
+            builder.with_tag("in");
+        } else {
+            builder.with_context_tags(&self.contexts);
+        }
+        builder.with_tags(&self.tags);
+
+        if let Some(ref due) = self.due {
+            builder.with_due(due);
+        }
+        if let Some(ref wait) = self.wait {
+            builder.with_wait(wait);
+        }
+        if let Some(ref scheduled) = self.scheduled {
+            builder.with_scheduled(scheduled);
+        }
+        if let Some(ref until) = self.until {
+            builder.with_until(until);
+        }
+        if let Some(ref recur) = self.recur {
+            builder.with_recurrence(recur);
+        }
+        if let Some(ref priority) = self.priority {
+            builder.with_priority(priority);
+        }
+
+        if let Some(minutes) = self.minutes {
+            let estimate = categories
+                .estimate_for(minutes)
+                .ok_or_else(|| anyhow!("There are no estimates configured"))?;
+            builder.with_estimate(estimate);
+        }
+
+        if let Some(ref level) = self.brainpower {
+            let brainpower = categories
+                .brainpower_named(level)
+                .ok_or_else(|| anyhow!("Unknown brainpower '!{}'", level))?;
+            builder.with_brainpower(brainpower);
+        }
+
+        Ok(builder)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Word<'a> {
+    Context(&'a str),
+    Tag(&'a str),
+    /// The full attribute name, and its value.
+    Attribute(&'static str, &'a str),
+    Estimate(u64),
+    Brainpower(&'a str),
+    Plain(&'a str),
+}
+
+fn not_space(c: char) -> bool {
+    !c.is_whitespace()
+}
+
+/// Anything special has to be a whole word, so `~30mins` or `@` on its own
+/// end up in the description.
+fn word(i: &str) -> IResult<&str, Word<'_>> {
+    alt((
+        terminated(
+            alt((
+                map(
+                    recognize(preceded(char('@'), take_while1(not_space))),
+                    Word::Context,
+                ),
+                map(preceded(char('+'), take_while1(not_space)), Word::Tag),
+                attribute_parser,
+                estimate_parser,
+                map(
+                    preceded(char('!'), take_while1(|c: char| c.is_alphanumeric())),
+                    Word::Brainpower,
+                ),
+            )),
+            peek(alt((multispace1, eof))),
+        ),
+        map(take_while1(not_space), Word::Plain),
+    ))(i)
+}
+
+/// `due:fri`, with the same abbreviations taskwarrior accepts for the common
+/// attributes.
+fn attribute_parser(i: &str) -> IResult<&str, Word<'_>> {
+    let (i, key) = alt((
+        value("project", alt((tag("project:"), tag("proj:")))),
+        value("due", tag("due:")),
+        value("wait", tag("wait:")),
+        value("scheduled", alt((tag("scheduled:"), tag("sched:")))),
+        value("until", tag("until:")),
+        value("recur", tag("recur:")),
+        value("priority", alt((tag("priority:"), tag("pri:")))),
+    ))(i)?;
+
+    map(take_while1(not_space), move |v| Word::Attribute(key, v))(i)
+}
+
+/// `~30m`, `~2h` or `~1d`.
+fn estimate_parser(i: &str) -> IResult<&str, Word<'_>> {
+    map(
+        preceded(
+            char('~'),
+            pair(
+                u64,
+                alt((
+                    value(1, alt((tag("min"), tag("m")))),
+                    value(60, tag("h")),
+                    value(24 * 60, tag("d")),
+                )),
+            ),
+        ),
+        |(amount, unit)| Word::Estimate(amount * unit),
+    )(i)
+}
+
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+    use task_hookrs::status::TaskStatus;
+
     use super::*;
+    use crate::testing::{config, FakeTask};
 
     #[test]
     fn parsing_created_task_id() {
-        let input = "Created task 30.\n";
-        let id = new_task_parser(input).unwrap();
+        let output = TaskOutput::parse("Created task 30.\n", "");
+
+        assert_eq!(
+            output.created,
+            vec![Created {
+                task: TaskRef::Id(30),
+                recurrence_template: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn parsing_recurring_and_hook_output() {
+        let stdout = "on-add: filled in defaults\nCreated task 5 (recurrence template 4).\n";
+        let output = TaskOutput::parse(stdout, "Configuration override rc.confirmation=off\n");
 
-        assert_eq!(id, 30);
+        assert_eq!(output.hook_messages, vec!["on-add: filled in defaults"]);
+        assert_eq!(output.overrides, vec!["rc.confirmation=off"]);
+        assert_eq!(
+            output.created,
+            vec![Created {
+                task: TaskRef::Id(5),
+                recurrence_template: Some(Some(4)),
+            }]
+        );
+
+        let output = TaskOutput::parse("Created task 5 (recurrence template).", "");
+        assert_eq!(output.created[0].recurrence_template, Some(None));
+    }
+
+    #[test]
+    fn parsing_actions_and_counts() {
+        let stdout = "Modifying task 3 'it's quoted'.\nModifying task 2a3b9a6e 'done'.\n\
+            Modified 2 tasks.\nThe project 'work' has changed.\n";
+        let output = TaskOutput::parse(stdout, "");
+
+        assert_eq!(
+            output.actions,
+            vec![
+                Action {
+                    verb: Verb::Modified,
+                    task: TaskRef::Id(3),
+                    description: "it's quoted".into(),
+                },
+                Action {
+                    verb: Verb::Modified,
+                    task: TaskRef::Uuid("2a3b9a6e".into()),
+                    description: "done".into(),
+                },
+            ]
+        );
+        assert_eq!(output.count(Verb::Modified), Some(2));
+        assert_eq!(output.messages, vec!["The project 'work' has changed."]);
+
+        let output = TaskOutput::parse(
+            "Completed task 1 'a'.\nCompleted 1 task.\nWarning: something odd\n",
+            "",
+        );
+        assert_eq!(output.count(Verb::Completed), Some(1));
+        assert_eq!(output.warnings, vec!["something odd"]);
+        assert_eq!(output.count(Verb::Deleted), None);
+    }
+
+    #[test]
+    fn parsing_inline_tasks() {
+        let task =
+            InlineTask::parse("Call Bob about budget @phone due:fri ~30m !high proj:finance")
+                .unwrap();
+
+        assert_eq!(
+            task,
+            InlineTask {
+                description: "Call Bob about budget".into(),
+                contexts: vec!["@phone".into()],
+                project: Some("finance".into()),
+                due: Some("fri".into()),
+                minutes: Some(30),
+                brainpower: Some("high".into()),
+                ..Default::default()
+            }
+        );
+
+        let task =
+            InlineTask::parse("  email bob@example.com  +bills sched:mon ~2h ~30mins ! ").unwrap();
+        assert_eq!(task.description, "email bob@example.com ~30mins !");
+        assert_eq!(task.tags, vec!["bills"]);
+        assert_eq!(task.scheduled, Some("mon".into()));
+        assert_eq!(task.minutes, Some(120));
+
+        assert!(InlineTask::parse("@phone due:fri").is_err());
+        assert!(InlineTask::parse("").is_err());
+    }
+
+    #[test]
+    fn building_inline_tasks() {
+        let _fake = FakeTask::new(&[]);
+        let config = config("");
+
+        let builder = InlineTask::parse("Call Bob @phone ~45m !low proj:finance")
+            .unwrap()
+            .builder(&config)
+            .unwrap();
+        assert_eq!(builder.description(), "Call Bob");
+        assert_eq!(builder.project(), Some(&Project::from("finance")));
+        assert_eq!(builder.brainpower(), "L");
+        assert_eq!(builder.estimate(), 360);
+        assert_eq!(builder.to_json().unwrap()["tags"], json!(["@phone"]));
+
+        // without a context it's something for the inbox
+        let json = InlineTask::parse("buy milk +groceries due:tomorrow")
+            .unwrap()
+            .builder(&config)
+            .unwrap()
+            .to_json()
+            .unwrap();
+        assert_eq!(json["tags"], json!(["in", "groceries"]));
+        assert_eq!(json["due"], json!("20300101T000000Z"));
+        assert_eq!(json["est"], json!(30));
+
+        let invalid = |line| InlineTask::parse(line).unwrap().builder(&config).is_err();
+        assert!(invalid("buy milk @shop"));
+        assert!(invalid("buy milk !huge"));
+    }
+
+    #[test]
+    fn resolving_dates() {
+        let fake = FakeTask::new(&[]);
+
+        let json = InlineTask::parse("call mom wait:3d due:+1w sched:fri")
+            .unwrap()
+            .builder(&config(""))
+            .unwrap()
+            .to_json()
+            .unwrap();
+        assert_eq!(json["wait"], json!("20300101T000000Z"));
+
+        let calcs: Vec<_> = fake
+            .calls()
+            .into_iter()
+            .filter(|c| c.contains("calc"))
+            .collect();
+        assert_eq!(
+            calcs,
+            vec![
+                "rc.verbose=nothing calc now + 3d",
+                "rc.verbose=nothing calc now + 1w",
+                "rc.verbose=nothing calc fri",
+            ]
+        );
+    }
+
+    #[test]
+    fn capturing_recurring_tasks() {
+        let fake = FakeTask::new(&[]);
+
+        let task = InlineTask::parse("water the plants due:sat recur:weekly")
+            .unwrap()
+            .builder(&config(""))
+            .unwrap()
+            .build()
+            .unwrap();
+
+        // the pending instance, not its template
+        assert_eq!(task.status(), &TaskStatus::Pending);
+        assert_eq!(task.id(), Some(2));
+        assert_eq!(task.imask(), Some(&0.0));
+
+        let template = fake
+            .tasks()
+            .into_iter()
+            .find(|t| t["status"] == json!("recurring"))
+            .unwrap();
+        assert_eq!(
+            task.parent(),
+            Some(&Uuid::parse_str(template["uuid"].as_str().unwrap()).unwrap())
+        );
+        assert_eq!(template["mask"], json!("-"));
+        assert_eq!(template["recur"], json!("weekly"));
+    }
+
+    #[test]
+    fn parsing_import() {
+        let stdout =
+            "on-add: ok\nImporting 'STDIN'\n add  2a3b9a6e-6c2c-4bd2-9c7e-0d6b5d3c6c21 Some task\n\
+            Imported 1 tasks.\n";
+        let output = TaskOutput::parse(stdout, "");
 
-        let input = "Created task 4.";
-        let id = new_task_parser(input).unwrap();
+        assert_eq!(
+            output.imported,
+            vec![Uuid::parse_str("2a3b9a6e-6c2c-4bd2-9c7e-0d6b5d3c6c21").unwrap()]
+        );
+        assert_eq!(output.count(Verb::Imported), Some(1));
+        assert_eq!(output.hook_messages, vec!["on-add: ok"]);
+        assert_eq!(output.messages, vec!["Importing 'STDIN'"]);
 
-        assert_eq!(id, 4);
+        let output = TaskOutput::parse(
+            "Importing 'STDIN'\n skip 2a3b9a6e-6c2c-4bd2-9c7e-0d6b5d3c6c21 Some task\n",
+            "",
+        );
+        assert!(output.imported.is_empty());
+        assert_eq!(
+            output.skipped,
+            vec![Uuid::parse_str("2a3b9a6e-6c2c-4bd2-9c7e-0d6b5d3c6c21").unwrap()]
+        );
     }
 }
@@ -0,0 +1,647 @@
+//! Taskwarrior projects are hierarchical, with `work.api.auth` being a
+//! subproject of `work.api`, which in turn is a subproject of `work`.
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+use task_hookrs::task::Task;
+
+use crate::task::{load_tasks, Processable};
+use crate::task_table::brainpower;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Project(String);
+
+impl Project {
+    /// The pending tasks in this project and all of its subprojects.
+    pub fn tasks(&self) -> Result<Vec<Task>> {
+        let filter = format!(
+            "{} status:pending",
+            shlex::quote(&format!("project:{}", self))
+        );
+
+        // `project:` is a left match, so `work` would pick up `workshop` too
+        Ok(load_tasks(&filter)?
+            .into_iter()
+            .filter(|t| {
+                t.project()
+                    .map(|p| self.contains(&Project::from(p.as_str())))
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    /// The pending tasks directly in this project, ignoring subprojects.
+    pub fn own_tasks(&self) -> Result<Vec<Task>> {
+        let filter = format!(
+            "{} status:pending",
+            shlex::quote(&format!("project.is:{}", self))
+        );
+        load_tasks(&filter)
+    }
+
+    /// The last segment of the name, e.g. `auth` for `work.api.auth`.
+    pub fn leaf(&self) -> &str {
+        self.0.rsplit('.').next().unwrap_or_default()
+    }
+
+    /// How many levels deep this project is, with top level projects at 0.
+    pub fn depth(&self) -> usize {
+        self.0.matches('.').count()
+    }
+
+    pub fn parent(&self) -> Option<Project> {
+        self.0
+            .rsplit_once('.')
+            .map(|(parent, _)| Project(parent.to_string()))
+    }
+
+    /// All ancestors, starting from the immediate parent.
+    pub fn ancestors(&self) -> Vec<Project> {
+        let mut ancestors = Vec::new();
+        let mut current = self.parent();
+
+        while let Some(project) = current {
+            current = project.parent();
+            ancestors.push(project);
+        }
+
+        ancestors
+    }
+
+    /// Whether `other` is this project or one of its subprojects.
+    pub fn contains(&self, other: &Project) -> bool {
+        other.0 == self.0
+            || other
+                .0
+                .strip_prefix(&self.0)
+                .map(|rest| rest.starts_with('.'))
+                .unwrap_or(false)
+    }
+
+    /// The direct children of this project among `projects`.
+    pub fn children<'a>(&self, projects: &'a [Project]) -> Vec<&'a Project> {
+        projects
+            .iter()
+            .filter(|p| p.parent().as_ref() == Some(self))
+            .collect()
+    }
+}
+
+impl Deref for Project {
+    type Target = String;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Project {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for Project {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl From<Project> for String {
+    fn from(p: Project) -> Self {
+        p.0
+    }
+}
+
+impl From<String> for Project {
+    fn from(s: String) -> Self {
+        Project(s)
+    }
+}
+
+impl From<&str> for Project {
+    fn from(s: &str) -> Self {
+        s.to_string().into()
+    }
+}
+
+/// Whether a project with `own_tasks` directly in it still needs a next
+/// action of its own.
+///
+/// Subprojects track their own next actions, so a parent that only groups
+/// subprojects never needs one, even when none of its children have one.
+pub fn needs_next_action(own_tasks: &[Task]) -> bool {
+    !own_tasks.is_empty() && !own_tasks.iter().any(|t| t.is_next())
+}
+
+/// A project name pattern, used for excluding projects.
+///
+/// Patterns prefixed with `re:` are regular expressions, everything else is a
+/// glob where `*` matches any run of characters (dots included) and `?` a
+/// single character. Both have to match the whole project name.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ProjectPattern {
+    source: String,
+    regex: Regex,
+}
+
+impl ProjectPattern {
+    pub fn matches(&self, project: &str) -> bool {
+        self.regex.is_match(project)
+    }
+}
+
+impl TryFrom<String> for ProjectPattern {
+    type Error = anyhow::Error;
+
+    fn try_from(source: String) -> Result<Self> {
+        let pattern = match source.strip_prefix("re:") {
+            Some(re) => format!("^(?:{})$", re),
+            None => {
+                let escaped: Vec<_> = source
+                    .chars()
+                    .map(|c| match c {
+                        '*' => String::from(".*"),
+                        '?' => String::from("."),
+                        c => regex::escape(&c.to_string()),
+                    })
+                    .collect();
+                format!("^{}$", escaped.join(""))
+            }
+        };
+
+        let regex = Regex::new(&pattern)
+            .with_context(|| format!("Invalid project pattern '{}'", source))?;
+
+        Ok(Self { source, regex })
+    }
+}
+
+impl fmt::Display for ProjectPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// How often a project should be looked at. A project is due for review once
+/// nothing has happened in it for that long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewCadence {
+    Daily,
+    Weekly,
+    Biweekly,
+    Monthly,
+    Quarterly,
+}
+
+impl ReviewCadence {
+    pub fn days(&self) -> i64 {
+        match self {
+            Self::Daily => 1,
+            Self::Weekly => 7,
+            Self::Biweekly => 14,
+            Self::Monthly => 30,
+            Self::Quarterly => 91,
+        }
+    }
+
+    /// Whether a project last touched at `last_activity` is due for review.
+    pub fn is_due(&self, last_activity: NaiveDateTime, now: NaiveDateTime) -> bool {
+        now - last_activity >= Duration::days(self.days())
+    }
+}
+
+impl fmt::Display for ReviewCadence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Daily => write!(f, "daily"),
+            Self::Weekly => write!(f, "weekly"),
+            Self::Biweekly => write!(f, "biweekly"),
+            Self::Monthly => write!(f, "monthly"),
+            Self::Quarterly => write!(f, "quarterly"),
+        }
+    }
+}
+
+/// Per-project settings. Anything left unset is inherited from the nearest
+/// ancestor that sets it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ProjectPolicy {
+    /// Don't require this project to have a next action.
+    pub skip_next_action: Option<bool>,
+
+    /// The context tags preselected for new tasks.
+    pub contexts: Option<Vec<String>>,
+
+    /// The `uda` of the brainpower level new tasks get by default.
+    pub brainpower: Option<String>,
+
+    /// The `minutes` of the estimate new tasks get by default.
+    pub estimate: Option<u64>,
+
+    pub review: Option<ReviewCadence>,
+}
+
+impl ProjectPolicy {
+    /// The effective policy for `project`, keyed by project name.
+    pub fn resolve(policies: &HashMap<String, ProjectPolicy>, project: &Project) -> Self {
+        let mut resolved = Self::default();
+
+        for policy in std::iter::once(project.clone())
+            .chain(project.ancestors())
+            .filter_map(|p| policies.get(p.as_str()))
+        {
+            resolved.inherit(policy);
+        }
+
+        resolved
+    }
+
+    /// Fill in anything not already set from `other`.
+    fn inherit(&mut self, other: &ProjectPolicy) {
+        if self.skip_next_action.is_none() {
+            self.skip_next_action = other.skip_next_action;
+        }
+        if self.contexts.is_none() {
+            self.contexts = other.contexts.clone();
+        }
+        if self.brainpower.is_none() {
+            self.brainpower = other.brainpower.clone();
+        }
+        if self.estimate.is_none() {
+            self.estimate = other.estimate;
+        }
+        if self.review.is_none() {
+            self.review = other.review;
+        }
+    }
+
+    pub fn skips_next_action(&self) -> bool {
+        self.skip_next_action.unwrap_or(false)
+    }
+
+    /// Fill in the contexts and brainpower left unset from what a project's
+    /// `tasks` have in common: the contexts at least half of them have, and
+    /// the most common brainpower.
+    pub fn with_suggestions(mut self, tasks: &[Task]) -> Self {
+        let mut contexts: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut levels: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for task in tasks {
+            for tag in task.tags().into_iter().flatten() {
+                if tag.starts_with('@') {
+                    *contexts.entry(tag).or_default() += 1;
+                }
+            }
+            if let Some(level) = brainpower(task) {
+                *levels.entry(level).or_default() += 1;
+            }
+        }
+
+        let common: Vec<String> = contexts
+            .into_iter()
+            .filter(|(_, count)| count * 2 >= tasks.len())
+            .map(|(tag, _)| tag.to_string())
+            .collect();
+
+        self.inherit(&ProjectPolicy {
+            contexts: Some(common).filter(|c| !c.is_empty()),
+            brainpower: levels
+                .into_iter()
+                .max_by_key(|(_, count)| *count)
+                .map(|(level, _)| level.to_string()),
+            ..Default::default()
+        });
+        self
+    }
+}
+
+/// The most recent time anything happened to `tasks`.
+pub fn last_activity(tasks: &[Task]) -> Option<NaiveDateTime> {
+    tasks
+        .iter()
+        .map(|t| **t.modified().unwrap_or_else(|| t.entry()))
+        .max()
+}
+
+/// The most recent time anything happened in each project, going by `tasks`.
+pub fn activity(tasks: &[Task]) -> HashMap<Project, NaiveDateTime> {
+    let mut activity = HashMap::new();
+
+    for task in tasks {
+        if let Some(project) = task.project() {
+            let modified = **task.modified().unwrap_or_else(|| task.entry());
+            let latest = activity
+                .entry(Project::from(project.as_str()))
+                .or_insert(modified);
+            *latest = modified.max(*latest);
+        }
+    }
+
+    activity
+}
+
+/// A project along with its subprojects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectNode {
+    pub project: Project,
+    pub children: Vec<ProjectNode>,
+}
+
+/// Projects arranged by hierarchy, sorted by name at every level.
+///
+/// Parents that don't have any tasks of their own are still part of the tree,
+/// since `task _projects` leaves them out.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProjectTree {
+    pub roots: Vec<ProjectNode>,
+}
+
+impl ProjectTree {
+    pub fn new<I: IntoIterator<Item = Project>>(projects: I) -> Self {
+        let mut all = BTreeSet::new();
+
+        for project in projects.into_iter().filter(|p| !p.is_empty()) {
+            all.extend(project.ancestors());
+            all.insert(project);
+        }
+
+        let all: Vec<_> = all.into_iter().collect();
+
+        fn build(project: &Project, all: &[Project]) -> ProjectNode {
+            ProjectNode {
+                project: project.clone(),
+                children: project
+                    .children(all)
+                    .into_iter()
+                    .map(|child| build(child, all))
+                    .collect(),
+            }
+        }
+
+        Self {
+            roots: all
+                .iter()
+                .filter(|p| p.parent().is_none())
+                .map(|p| build(p, &all))
+                .collect(),
+        }
+    }
+
+    /// Put the most recently active projects first at every level, by the
+    /// latest `activity` anywhere under them. Projects without any stay in
+    /// name order after the rest.
+    pub fn by_activity(mut self, activity: &HashMap<Project, NaiveDateTime>) -> Self {
+        fn latest(
+            node: &ProjectNode,
+            activity: &HashMap<Project, NaiveDateTime>,
+        ) -> Option<NaiveDateTime> {
+            node.children
+                .iter()
+                .filter_map(|child| latest(child, activity))
+                .chain(activity.get(&node.project).copied())
+                .max()
+        }
+
+        fn sort(nodes: &mut [ProjectNode], activity: &HashMap<Project, NaiveDateTime>) {
+            for node in nodes.iter_mut() {
+                sort(&mut node.children, activity);
+            }
+            nodes.sort_by_key(|node| Reverse(latest(node, activity)));
+        }
+
+        sort(&mut self.roots, activity);
+        self
+    }
+
+    /// Every project in the tree, parents before their children.
+    pub fn projects(&self) -> Vec<&Project> {
+        fn walk<'a>(node: &'a ProjectNode, out: &mut Vec<&'a Project>) {
+            out.push(&node.project);
+            for child in &node.children {
+                walk(child, out);
+            }
+        }
+
+        let mut out = Vec::new();
+        for root in &self.roots {
+            walk(root, &mut out);
+        }
+        out
+    }
+
+    /// The projects with their leaf names indented by depth, for display.
+    pub fn labels(&self) -> Vec<(Project, String)> {
+        self.projects()
+            .into_iter()
+            .map(|p| (p.clone(), format!("{}{}", "  ".repeat(p.depth()), p.leaf())))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::testing::FakeTask;
+
+    #[test]
+    fn navigating_hierarchy() {
+        let project = Project::from("work.api.auth");
+
+        assert_eq!(project.leaf(), "auth");
+        assert_eq!(project.depth(), 2);
+        assert_eq!(project.parent(), Some(Project::from("work.api")));
+        assert_eq!(
+            project.ancestors(),
+            vec![Project::from("work.api"), Project::from("work")]
+        );
+        assert!(Project::from("work").contains(&project));
+        assert!(!Project::from("wo").contains(&project));
+        assert!(!Project::from("work.ap").contains(&project));
+        assert!(Project::from("work").parent().is_none());
+    }
+
+    #[test]
+    fn loading_subproject_tasks() {
+        let tasks: Vec<_> = ["work", "work.api", "workshop", "home"]
+            .iter()
+            .enumerate()
+            .map(|(i, project)| {
+                json!({
+                    "id": i + 1,
+                    "uuid": format!("00000000-0000-0000-0000-00000000000{}", i + 1),
+                    "status": "pending",
+                    "entry": "20261001T090000Z",
+                    "description": format!("something for {}", project),
+                    "project": project,
+                })
+            })
+            .collect();
+        let _fake = FakeTask::new(&tasks);
+
+        let projects: Vec<_> = Project::from("work")
+            .tasks()
+            .unwrap()
+            .iter()
+            .filter_map(|t| t.project().cloned())
+            .collect();
+
+        assert_eq!(projects, vec!["work", "work.api"]);
+    }
+
+    #[test]
+    fn building_tree() {
+        let tree = ProjectTree::new(
+            ["work.api.auth", "home", "work.web", ""]
+                .iter()
+                .map(|p| Project::from(*p)),
+        );
+
+        let names: Vec<_> = tree.projects().iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            names,
+            vec!["home", "work", "work.api", "work.api.auth", "work.web"]
+        );
+
+        let labels: Vec<_> = tree.labels().into_iter().map(|(_, l)| l).collect();
+        assert_eq!(labels, vec!["home", "work", "  api", "    auth", "  web"]);
+    }
+
+    #[test]
+    fn ordering_by_activity() {
+        let at = |day| {
+            chrono::NaiveDate::from_ymd_opt(2026, 10, day)
+                .unwrap()
+                .and_hms_opt(9, 0, 0)
+                .unwrap()
+        };
+        let activity: HashMap<_, _> = [("home", at(1)), ("work.web", at(3)), ("work.api", at(2))]
+            .into_iter()
+            .map(|(p, at)| (Project::from(p), at))
+            .collect();
+
+        let tree = ProjectTree::new(
+            ["work.api", "home", "work.web", "errands"]
+                .iter()
+                .map(|p| Project::from(*p)),
+        )
+        .by_activity(&activity);
+
+        let names: Vec<_> = tree.projects().iter().map(|p| p.to_string()).collect();
+        assert_eq!(
+            names,
+            vec!["work", "work.web", "work.api", "home", "errands"]
+        );
+    }
+
+    #[test]
+    fn suggesting_from_tasks() {
+        let tasks: Vec<Task> = [
+            r#""tags": ["@computer", "@work"], "brain": "H""#,
+            r#""tags": ["@computer"], "brain": "H""#,
+            r#""tags": ["@phone"], "brain": "L""#,
+        ]
+        .iter()
+        .map(|fields| {
+            serde_json::from_str(&format!(
+                r#"{{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                    "entry": "20220101T000000Z", "description": "a", {}}}"#,
+                fields
+            ))
+            .unwrap()
+        })
+        .collect();
+
+        let suggested = ProjectPolicy::default().with_suggestions(&tasks);
+        assert_eq!(suggested.contexts, Some(vec![String::from("@computer")]));
+        assert_eq!(suggested.brainpower, Some(String::from("H")));
+
+        // configured settings win
+        let configured = ProjectPolicy {
+            brainpower: Some("M".into()),
+            ..Default::default()
+        }
+        .with_suggestions(&tasks);
+        assert_eq!(configured.brainpower, Some(String::from("M")));
+
+        assert_eq!(
+            ProjectPolicy::default().with_suggestions(&[]),
+            ProjectPolicy::default()
+        );
+    }
+
+    #[test]
+    fn matching_patterns() {
+        let exact = ProjectPattern::try_from(String::from("maybe")).unwrap();
+        assert!(exact.matches("maybe"));
+        assert!(!exact.matches("maybe.later"));
+
+        let glob = ProjectPattern::try_from(String::from("maybe*")).unwrap();
+        assert!(glob.matches("maybe"));
+        assert!(glob.matches("maybe.later"));
+        assert!(!glob.matches("work.maybe"));
+
+        let re = ProjectPattern::try_from(String::from("re:(.*\\.)?interrupt(\\..*)?")).unwrap();
+        assert!(re.matches("interrupt"));
+        assert!(re.matches("work.interrupt.pager"));
+        assert!(!re.matches("interrupts"));
+
+        assert!(ProjectPattern::try_from(String::from("re:(")).is_err());
+    }
+
+    #[test]
+    fn inheriting_policies() {
+        let mut policies = HashMap::new();
+        policies.insert(
+            String::from("work"),
+            ProjectPolicy {
+                brainpower: Some("H".into()),
+                review: Some(ReviewCadence::Weekly),
+                ..Default::default()
+            },
+        );
+        policies.insert(
+            String::from("work.api"),
+            ProjectPolicy {
+                brainpower: Some("L".into()),
+                skip_next_action: Some(true),
+                ..Default::default()
+            },
+        );
+
+        let policy = ProjectPolicy::resolve(&policies, &Project::from("work.api.auth"));
+        assert_eq!(policy.brainpower, Some("L".into()));
+        assert_eq!(policy.review, Some(ReviewCadence::Weekly));
+        assert!(policy.skips_next_action());
+
+        let policy = ProjectPolicy::resolve(&policies, &Project::from("home"));
+        assert_eq!(policy, ProjectPolicy::default());
+    }
+
+    #[test]
+    fn parents_without_tasks_need_no_next_action() {
+        let task: Task = serde_json::from_str(
+            r#"{"uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "description": "a", "project": "work"}"#,
+        )
+        .unwrap();
+
+        assert!(!needs_next_action(&[]));
+        assert!(needs_next_action(std::slice::from_ref(&task)));
+
+        let mut next = task;
+        next.set_tags(Some(vec![String::from("next")]));
+        assert!(!needs_next_action(&[next]));
+    }
+}
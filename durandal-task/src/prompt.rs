@@ -0,0 +1,672 @@
+//! Everything the workflows ask or tell the user goes through a `Prompter`, so
+//! the same workflows can be driven from a terminal, a script or a test.
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail, Result};
+use console::{Key, Term};
+use dialoguer::theme::{ColorfulTheme, Theme};
+use dialoguer::{Confirm, Input, Select};
+use serde::Deserialize;
+
+use crate::task_table::format_duration;
+use crate::theme::{paint, theme};
+
+pub trait Prompter {
+    fn confirm(&mut self, prompt: &str, default: bool) -> Result<bool>;
+
+    /// Returns the index of the selected item.
+    fn select(&mut self, prompt: &str, items: &[String], default: usize) -> Result<usize>;
+
+    /// Like `select`, for long lists. Typing narrows the items down to the
+    /// ones whose key, in `keys`, fuzzily matches, best match first.
+    fn fuzzy_select(&mut self, prompt: &str, items: &[String], keys: &[String]) -> Result<usize>;
+
+    /// Returns the indices of the selected items, with `defaults` marking the
+    /// items that start out selected.
+    fn multi_select(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>>;
+
+    fn input(&mut self, prompt: &str, default: Option<&str>) -> Result<String>;
+
+    /// Run a clock, starting from `elapsed`, until the user says they're
+    /// done. Past `threshold` the clock turns into a warning. Returns the
+    /// total time on the clock.
+    fn timer(&mut self, prompt: &str, elapsed: Duration, threshold: Duration) -> Result<Duration>;
+
+    /// Let the user know what just happened.
+    fn tell(&mut self, message: &dyn fmt::Display);
+}
+
+/// Returned by a prompter when the user would rather go back to the previous
+/// question than answer this one. Workflow drivers turn it into a step back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GoBack;
+
+impl fmt::Display for GoBack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Went back from a prompt with nothing to go back to")
+    }
+}
+
+impl std::error::Error for GoBack {}
+
+/// Whether `err` is the user asking to go back, however deeply it's wrapped.
+pub fn is_go_back(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<GoBack>().is_some()
+}
+
+/// Convenience for turning anything displayable into prompt items.
+pub fn items<T: ToString>(choices: &[T]) -> Vec<String> {
+    choices.iter().map(|c| c.to_string()).collect()
+}
+
+/// How well `query` matches `key`, if it does at all. Every character of the
+/// query has to show up in the key in order, ignoring case and whitespace.
+/// Consecutive characters, ones starting a word or project segment and
+/// matching up to the end count for more.
+pub fn fuzzy_score(query: &str, key: &str) -> Option<i64> {
+    let key: Vec<char> = key.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+
+    for c in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (next..key.len()).find(|&i| key[i] == c)?;
+
+        score += 1;
+        if found > 0 && found == next {
+            score += 4;
+        }
+        if found == 0 || !key[found - 1].is_alphanumeric() {
+            score += 2;
+        }
+
+        next = found + 1;
+    }
+
+    // `work` is a better match for `work` than `work.api` is
+    if next == key.len() {
+        score += 2;
+    }
+
+    Some(score)
+}
+
+/// The indices of the `keys` matching `query`, best first. Equally good
+/// matches keep their order.
+pub fn fuzzy_rank(query: &str, keys: &[String]) -> Vec<usize> {
+    let mut scored: Vec<_> = keys
+        .iter()
+        .enumerate()
+        .filter_map(|(i, key)| fuzzy_score(query, key).map(|score| (i, score)))
+        .collect();
+
+    scored.sort_by_key(|(_, score)| Reverse(*score));
+    scored.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Prompts on the terminal via dialoguer.
+///
+/// Escape on a confirm or any select prompt, or `<` as text input, goes back.
+/// Fuzzy and multi selects are drawn here, since this version of dialoguer
+/// doesn't have the one and can't go back from the other.
+#[derive(Default)]
+pub struct Terminal {
+    theme: ColorfulTheme,
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Prompter for Terminal {
+    fn confirm(&mut self, prompt: &str, default: bool) -> Result<bool> {
+        Confirm::with_theme(&self.theme)
+            .with_prompt(prompt)
+            .default(default)
+            .interact_opt()?
+            .ok_or_else(|| GoBack.into())
+    }
+
+    fn select(&mut self, prompt: &str, items: &[String], default: usize) -> Result<usize> {
+        Select::with_theme(&self.theme)
+            .with_prompt(prompt)
+            .items(items)
+            .default(default)
+            .interact_opt()?
+            .ok_or_else(|| GoBack.into())
+    }
+
+    fn fuzzy_select(&mut self, prompt: &str, items: &[String], keys: &[String]) -> Result<usize> {
+        let term = Term::stderr();
+        let page = (term.size().0 as usize).saturating_sub(2).clamp(1, 10);
+        let mut query = String::new();
+        let mut cursor = 0;
+        let mut drawn = 0;
+
+        loop {
+            // the items as given until something is typed, then the keys
+            // that match it
+            let shown: Vec<(usize, &String)> = if query.is_empty() {
+                items.iter().enumerate().collect()
+            } else {
+                fuzzy_rank(&query, keys)
+                    .into_iter()
+                    .map(|i| (i, &keys[i]))
+                    .collect()
+            };
+            cursor = cursor.min(shown.len().saturating_sub(1));
+            let start = (cursor + 1).saturating_sub(page);
+
+            let mut lines = Vec::new();
+            let mut line = String::new();
+            self.theme.format_select_prompt(&mut line, prompt)?;
+            lines.push(format!("{} {}", line, query));
+
+            for (n, (_, text)) in shown.iter().enumerate().skip(start).take(page) {
+                let mut line = String::new();
+                self.theme
+                    .format_select_prompt_item(&mut line, text, n == cursor)?;
+                lines.push(line);
+            }
+
+            term.clear_last_lines(drawn)?;
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            drawn = lines.len();
+
+            match term.read_key()? {
+                Key::Enter if !shown.is_empty() => {
+                    let (choice, text) = shown[cursor];
+                    let mut line = String::new();
+                    self.theme
+                        .format_select_prompt_selection(&mut line, prompt, text)?;
+
+                    term.clear_last_lines(drawn)?;
+                    term.write_line(&line)?;
+                    return Ok(choice);
+                }
+                Key::Escape => {
+                    term.clear_last_lines(drawn)?;
+                    return Err(GoBack.into());
+                }
+                Key::ArrowUp => cursor = cursor.saturating_sub(1),
+                Key::ArrowDown => cursor += 1,
+                Key::Backspace => {
+                    query.pop();
+                    cursor = 0;
+                }
+                Key::Char(c) if !c.is_control() => {
+                    query.push(c);
+                    cursor = 0;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn multi_select(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>> {
+        let term = Term::stderr();
+        let page = (term.size().0 as usize).saturating_sub(2).clamp(1, 10);
+        let mut checked = defaults.to_vec();
+        checked.resize(items.len(), false);
+        let mut cursor: usize = 0;
+        let mut drawn = 0;
+
+        loop {
+            let start = (cursor + 1).saturating_sub(page);
+
+            let mut lines = Vec::new();
+            let mut line = String::new();
+            self.theme.format_multi_select_prompt(&mut line, prompt)?;
+            lines.push(line);
+
+            for (n, text) in items.iter().enumerate().skip(start).take(page) {
+                let mut line = String::new();
+                self.theme.format_multi_select_prompt_item(
+                    &mut line,
+                    text,
+                    checked[n],
+                    n == cursor,
+                )?;
+                lines.push(line);
+            }
+
+            term.clear_last_lines(drawn)?;
+            for line in &lines {
+                term.write_line(line)?;
+            }
+            drawn = lines.len();
+
+            match term.read_key()? {
+                Key::Enter => {
+                    let selected: Vec<_> = (0..items.len()).filter(|n| checked[*n]).collect();
+                    let texts: Vec<_> = selected.iter().map(|n| items[*n].as_str()).collect();
+                    let mut line = String::new();
+                    self.theme
+                        .format_multi_select_prompt_selection(&mut line, prompt, &texts)?;
+
+                    term.clear_last_lines(drawn)?;
+                    term.write_line(&line)?;
+                    return Ok(selected);
+                }
+                Key::Escape => {
+                    term.clear_last_lines(drawn)?;
+                    return Err(GoBack.into());
+                }
+                Key::ArrowUp | Key::Char('k') => cursor = cursor.saturating_sub(1),
+                Key::ArrowDown | Key::Char('j') => {
+                    cursor = (cursor + 1).min(items.len().saturating_sub(1))
+                }
+                Key::Char(' ') if cursor < items.len() => checked[cursor] = !checked[cursor],
+                _ => {}
+            }
+        }
+    }
+
+    fn input(&mut self, prompt: &str, default: Option<&str>) -> Result<String> {
+        let mut input = Input::<String>::with_theme(&self.theme);
+        input.with_prompt(prompt);
+
+        if let Some(default) = default {
+            input.default(default.into());
+        }
+
+        match input.interact_text()? {
+            back if back == "<" => Err(GoBack.into()),
+            value => Ok(value),
+        }
+    }
+
+    fn timer(&mut self, prompt: &str, elapsed: Duration, threshold: Duration) -> Result<Duration> {
+        let term = Term::stderr();
+        let started = Instant::now();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        term.write_line(prompt)?;
+
+        let ticker = {
+            let term = term.clone();
+            let stopped = stopped.clone();
+
+            thread::spawn(move || {
+                while !stopped.load(Ordering::Relaxed) {
+                    let _ = term.clear_line();
+                    let _ = term.write_str(&clock(elapsed + started.elapsed(), threshold));
+                    thread::sleep(Duration::from_millis(250));
+                }
+            })
+        };
+
+        // keys aren't echoed, so the clock keeps its line to itself
+        let key = loop {
+            match term.read_key() {
+                Ok(Key::Enter) => break Ok(()),
+                Ok(_) => continue,
+                Err(err) => break Err(err),
+            }
+        };
+
+        stopped.store(true, Ordering::Relaxed);
+        let _ = ticker.join();
+        term.clear_line()?;
+        key?;
+
+        let total = elapsed + started.elapsed();
+        term.write_line(&format!(
+            "  Took {}",
+            format_duration(total.as_secs() as i64)
+        ))?;
+        Ok(total)
+    }
+
+    fn tell(&mut self, message: &dyn fmt::Display) {
+        println!("{}", message);
+    }
+}
+
+/// A running clock like `  1:05`, with a warning once it's past `threshold`.
+fn clock(elapsed: Duration, threshold: Duration) -> String {
+    let secs = elapsed.as_secs();
+    let time = format!("  {}:{:02}", secs / 60, secs % 60);
+
+    if elapsed <= threshold {
+        return time;
+    }
+
+    let warning = format!(
+        "{}  past {}, consider deferring it",
+        time,
+        format_duration(threshold.as_secs() as i64)
+    );
+    paint(theme().warning, warning).to_string()
+}
+
+/// One of the items offered by a select prompt, either by position or by
+/// its exact label.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Choice {
+    Index(usize),
+    Label(String),
+}
+
+impl Choice {
+    fn resolve(&self, items: &[String]) -> Option<usize> {
+        match self {
+            Self::Index(index) if *index < items.len() => Some(*index),
+            Self::Index(_) => None,
+            Self::Label(label) => items.iter().position(|i| i == label),
+        }
+    }
+}
+
+impl From<usize> for Choice {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for Choice {
+    fn from(label: &str) -> Self {
+        Self::Label(label.into())
+    }
+}
+
+impl fmt::Display for Choice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "#{}", index),
+            Self::Label(label) => write!(f, "'{}'", label),
+        }
+    }
+}
+
+/// A single prepared answer for a `Scripted` prompter.
+///
+/// In an answers document these look like `{ confirm = true }`,
+/// `{ select = "Defer" }`, `{ multi_select = ["Home", 2] }`,
+/// `{ input = "+1d" }`, `{ timer = 90 }`, or just `"default"` or `"back"`.
+/// Projects are picked with a select, by their full name like
+/// `{ select = "work.api" }`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Answer {
+    Confirm(bool),
+    Select(Choice),
+    MultiSelect(Vec<Choice>),
+    Input(String),
+    /// How many seconds a timer runs before the user is done.
+    Timer(u64),
+    /// Accept whatever the prompt would have defaulted to.
+    Default,
+    /// Go back to the previous question instead of answering, whatever the
+    /// prompt.
+    Back,
+}
+
+impl Answer {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Confirm(_) => "confirm",
+            Self::Select(_) => "select",
+            Self::MultiSelect(_) => "multi-select",
+            Self::Input(_) => "input",
+            Self::Timer(_) => "timer",
+            Self::Default => "default",
+            Self::Back => "back",
+        }
+    }
+}
+
+/// Answers prompts from a prepared list, in order.
+///
+/// Running out of answers, or an answer that doesn't fit the prompt it lands
+/// on, is an error rather than a hang waiting on a terminal. Anything the
+/// workflows tell the user goes to stderr.
+#[derive(Debug, Clone, Default)]
+pub struct Scripted {
+    answers: VecDeque<Answer>,
+    asked: Vec<String>,
+}
+
+impl Scripted {
+    pub fn new<I: IntoIterator<Item = Answer>>(answers: I) -> Self {
+        Self {
+            answers: answers.into_iter().collect(),
+            asked: Vec::new(),
+        }
+    }
+
+    /// The prompts asked so far, in order.
+    pub fn asked(&self) -> &[String] {
+        &self.asked
+    }
+
+    /// The answers that were never used.
+    pub fn remaining(&self) -> usize {
+        self.answers.len()
+    }
+
+    fn next(&mut self, prompt: &str, kind: &str) -> Result<Answer> {
+        self.asked.push(prompt.to_string());
+
+        let answer = self.answers.pop_front().ok_or_else(|| {
+            anyhow!(
+                "Ran out of answers at {} prompt {} '{}'",
+                kind,
+                self.asked.len(),
+                prompt
+            )
+        })?;
+
+        if answer == Answer::Back {
+            return Err(GoBack.into());
+        }
+
+        if answer != Answer::Default && answer.kind() != kind {
+            bail!(
+                "Answer {} is a {} answer but landed on {} prompt '{}'",
+                self.asked.len(),
+                answer.kind(),
+                kind,
+                prompt
+            );
+        }
+
+        Ok(answer)
+    }
+
+    fn choose(&self, prompt: &str, choice: &Choice, items: &[String]) -> Result<usize> {
+        choice.resolve(items).ok_or_else(|| {
+            anyhow!(
+                "Answer {} selects {} which is not one of the choices for '{}': {}",
+                self.asked.len(),
+                choice,
+                prompt,
+                items.join(", ")
+            )
+        })
+    }
+}
+
+impl Prompter for Scripted {
+    fn confirm(&mut self, prompt: &str, default: bool) -> Result<bool> {
+        match self.next(prompt, "confirm")? {
+            Answer::Confirm(value) => Ok(value),
+            _ => Ok(default),
+        }
+    }
+
+    fn select(&mut self, prompt: &str, items: &[String], default: usize) -> Result<usize> {
+        match self.next(prompt, "select")? {
+            Answer::Select(choice) => self.choose(prompt, &choice, items),
+            _ => Ok(default),
+        }
+    }
+
+    /// Answered like a select, where a label may also name an item by its key.
+    fn fuzzy_select(&mut self, prompt: &str, items: &[String], keys: &[String]) -> Result<usize> {
+        match self.next(prompt, "select")? {
+            Answer::Select(choice) => match choice.resolve(keys) {
+                Some(index) => Ok(index),
+                None => self.choose(prompt, &choice, items),
+            },
+            _ => Ok(0),
+        }
+    }
+
+    fn multi_select(
+        &mut self,
+        prompt: &str,
+        items: &[String],
+        defaults: &[bool],
+    ) -> Result<Vec<usize>> {
+        match self.next(prompt, "multi-select")? {
+            Answer::MultiSelect(choices) => choices
+                .iter()
+                .map(|c| self.choose(prompt, c, items))
+                .collect(),
+            _ => Ok(defaults
+                .iter()
+                .enumerate()
+                .filter(|(_, selected)| **selected)
+                .map(|(i, _)| i)
+                .collect()),
+        }
+    }
+
+    fn input(&mut self, prompt: &str, default: Option<&str>) -> Result<String> {
+        match self.next(prompt, "input")? {
+            Answer::Input(value) => Ok(value),
+            _ => default.map(String::from).ok_or_else(|| {
+                anyhow!(
+                    "Answer {} accepts the default but '{}' has none",
+                    self.asked.len(),
+                    prompt
+                )
+            }),
+        }
+    }
+
+    /// No time passes by default.
+    fn timer(&mut self, prompt: &str, elapsed: Duration, _: Duration) -> Result<Duration> {
+        match self.next(prompt, "timer")? {
+            Answer::Timer(secs) => Ok(elapsed + Duration::from_secs(secs)),
+            _ => Ok(elapsed),
+        }
+    }
+
+    fn tell(&mut self, message: &dyn fmt::Display) {
+        eprintln!("{}", message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+
+    use super::*;
+
+    #[test]
+    fn scripted_answers() {
+        let mut prompter = Scripted::new([
+            Answer::Confirm(true),
+            Answer::Select("b".into()),
+            Answer::Default,
+            Answer::MultiSelect(vec![1.into(), "a".into()]),
+            Answer::Timer(90),
+        ]);
+        let choices = items(&["a", "b"]);
+
+        assert!(prompter.confirm("first?", false).unwrap());
+        assert_eq!(prompter.select("second?", &choices, 0).unwrap(), 1);
+        assert_eq!(prompter.input("third?", Some("+1d")).unwrap(), "+1d");
+        assert_eq!(
+            prompter
+                .multi_select("fourth?", &choices, &[false, false])
+                .unwrap(),
+            vec![1, 0]
+        );
+        let minute = Duration::from_secs(60);
+        assert_eq!(
+            prompter.timer("fifth?", minute, 2 * minute).unwrap(),
+            Duration::from_secs(150)
+        );
+        assert!(prompter.confirm("sixth?", false).is_err());
+        assert_eq!(
+            prompter.asked(),
+            &["first?", "second?", "third?", "fourth?", "fifth?", "sixth?"]
+        );
+    }
+
+    #[test]
+    fn scripted_mismatches() {
+        let mut prompter = Scripted::new([
+            Answer::Select(0.into()),
+            Answer::Select(5.into()),
+            Answer::Select("c".into()),
+            Answer::Default,
+        ]);
+        let choices = items(&["a", "b"]);
+
+        assert!(prompter.confirm("wrong kind?", true).is_err());
+        assert!(prompter.select("out of range?", &choices, 0).is_err());
+        assert!(prompter.select("unknown label?", &choices, 0).is_err());
+        assert!(prompter.input("no default?", None).is_err());
+    }
+
+    #[test]
+    fn fuzzy_matching() {
+        let keys = items(&["home.garden", "work.api.auth", "work", "groceries"]);
+
+        assert!(fuzzy_score("wrk", "work").is_some());
+        assert!(fuzzy_score("krow", "work").is_none());
+        assert!(fuzzy_score("work api", "work.api.auth") > fuzzy_score("wapi", "work.api.auth"));
+
+        assert_eq!(fuzzy_rank("work", &keys), vec![2, 1]);
+        assert_eq!(fuzzy_rank("ga", &keys), vec![0]);
+        assert_eq!(fuzzy_rank("", &keys), vec![0, 1, 2, 3]);
+
+        let mut prompter = Scripted::new([
+            Answer::Select("work.api.auth".into()),
+            Answer::Select("    auth".into()),
+            Answer::Default,
+        ]);
+        let labels = items(&["home", "  garden", "work", "  api", "    auth"]);
+        let keys = items(&["home", "home.garden", "work", "work.api", "work.api.auth"]);
+
+        assert_eq!(prompter.fuzzy_select("a?", &labels, &keys).unwrap(), 4);
+        assert_eq!(prompter.fuzzy_select("b?", &labels, &keys).unwrap(), 4);
+        assert_eq!(prompter.fuzzy_select("c?", &labels, &keys).unwrap(), 0);
+    }
+
+    #[test]
+    fn scripted_going_back() {
+        let mut prompter = Scripted::new([Answer::Back, Answer::Back]);
+
+        assert!(is_go_back(&prompter.confirm("first?", true).unwrap_err()));
+        let err = prompter
+            .multi_select("second?", &items(&["a"]), &[true])
+            .context("wrapped")
+            .unwrap_err();
+        assert!(is_go_back(&err));
+    }
+}
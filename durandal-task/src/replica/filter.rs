@@ -0,0 +1,722 @@
+//! A small evaluator for the subset of taskwarrior filter syntax that the
+//! configured reports actually use.
+//!
+//! Anything we don't understand makes [Filter::parse] return `None`, so the
+//! caller can fall back to letting taskwarrior evaluate the filter. Being
+//! conservative here is the whole point: a wrong answer is much worse than a
+//! slow one.
+use std::collections::HashSet;
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{alpha1, char, digit1, one_of, u64 as parse_u64};
+use nom::combinator::{all_consuming, map, map_res, opt, recognize, rest, value, verify};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{pair, preceded, separated_pair, tuple};
+use nom::IResult;
+use task_hookrs::date::Date;
+use task_hookrs::status::TaskStatus;
+use task_hookrs::task::Task;
+use task_hookrs::uda::UDAValue;
+
+/// The UDAs durandal itself sets on tasks.
+const OWN_UDAS: &[&str] = &["brain", "est"];
+
+/// Number of days ahead a task is considered +DUE (taskwarrior's `rc.due`).
+const DUE_DAYS: i64 = 7;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    All,
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Tag(String, bool),
+    Virtual(VirtualTag, bool),
+    Attribute(Attribute),
+    Ids(Vec<u64>),
+    Uuid(String),
+}
+
+impl Filter {
+    /// Attributes other than the core ones are only understood when they're
+    /// among `udas`, since taskwarrior would treat anything else as an error
+    /// or as something we don't evaluate, like `tags:` or `limit:`.
+    pub fn parse(input: &str, udas: &[String]) -> Option<Self> {
+        let words = shlex::split(input)?;
+        let mut tokens = Vec::new();
+
+        for word in &words {
+            tokenize(word, &mut tokens)?;
+        }
+
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.expr()?;
+
+        if parser.pos != parser.tokens.len() || !filter.knows_udas(udas) {
+            return None;
+        }
+
+        Some(filter)
+    }
+
+    fn knows_udas(&self, udas: &[String]) -> bool {
+        match self {
+            Self::And(filters) | Self::Or(filters) => filters.iter().all(|f| f.knows_udas(udas)),
+            Self::Attribute(Attribute::Text(TextAttr::Uda(key), _, _)) => {
+                OWN_UDAS.contains(&key.as_str()) || udas.contains(key)
+            }
+            _ => true,
+        }
+    }
+
+    pub fn matches(&self, task: &Task, ctx: &EvalContext) -> bool {
+        match self {
+            Self::All => true,
+            Self::And(filters) => filters.iter().all(|f| f.matches(task, ctx)),
+            Self::Or(filters) => filters.iter().any(|f| f.matches(task, ctx)),
+            Self::Tag(name, present) => {
+                task.tags().map(|t| t.contains(name)).unwrap_or(false) == *present
+            }
+            Self::Virtual(tag, present) => tag.matches(task, ctx) == *present,
+            Self::Attribute(attr) => attr.matches(task, ctx),
+            Self::Ids(ids) => task.id().map(|id| ids.contains(&id)).unwrap_or(false),
+            Self::Uuid(prefix) => task.uuid().to_string().starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// Precomputed state needed to evaluate filters that depend on more than the
+/// task being matched, like the current time or dependencies.
+#[derive(Debug, Clone)]
+pub struct EvalContext {
+    now: NaiveDateTime,
+    start_of_today: NaiveDateTime,
+    pending: HashSet<String>,
+    depended_on: HashSet<String>,
+}
+
+impl EvalContext {
+    pub fn new(tasks: &[Task]) -> Self {
+        let mut pending = HashSet::new();
+        let mut depended_on = HashSet::new();
+
+        for task in tasks.iter().filter(|t| is_pending(t)) {
+            pending.insert(task.uuid().to_string());
+            if let Some(deps) = task.depends() {
+                depended_on.extend(deps.iter().map(|d| d.to_string()));
+            }
+        }
+
+        Self {
+            now: Utc::now().naive_utc(),
+            start_of_today: local_midnight(Local::now().date_naive()),
+            pending,
+            depended_on,
+        }
+    }
+
+    pub fn is_blocked(&self, task: &Task) -> bool {
+        task.depends()
+            .map(|deps| deps.iter().any(|d| self.pending.contains(&d.to_string())))
+            .unwrap_or(false)
+    }
+
+    pub fn is_blocking(&self, task: &Task) -> bool {
+        is_pending(task) && self.depended_on.contains(&task.uuid().to_string())
+    }
+}
+
+/// Taskwarrior 3 no longer stores a distinct waiting status, so both count.
+pub fn is_pending(task: &Task) -> bool {
+    matches!(task.status(), TaskStatus::Pending | TaskStatus::Waiting)
+}
+
+fn is_waiting(task: &Task, ctx: &EvalContext) -> bool {
+    is_pending(task) && task.wait().map(|w| **w > ctx.now).unwrap_or(false)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VirtualTag {
+    Active,
+    Annotated,
+    Blocked,
+    Blocking,
+    Completed,
+    Deleted,
+    Due,
+    Overdue,
+    Pending,
+    Project,
+    Ready,
+    Recurring,
+    Scheduled,
+    Tagged,
+    Today,
+    Unblocked,
+    Waiting,
+}
+
+impl VirtualTag {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ACTIVE" => Self::Active,
+            "ANNOTATED" => Self::Annotated,
+            "BLOCKED" => Self::Blocked,
+            "BLOCKING" => Self::Blocking,
+            "COMPLETED" => Self::Completed,
+            "DELETED" => Self::Deleted,
+            "DUE" => Self::Due,
+            "OVERDUE" => Self::Overdue,
+            "PENDING" => Self::Pending,
+            "PROJECT" => Self::Project,
+            "READY" => Self::Ready,
+            "RECURRING" => Self::Recurring,
+            "SCHEDULED" => Self::Scheduled,
+            "TAGGED" => Self::Tagged,
+            "TODAY" => Self::Today,
+            "UNBLOCKED" => Self::Unblocked,
+            "WAITING" => Self::Waiting,
+            _ => return None,
+        })
+    }
+
+    fn matches(&self, task: &Task, ctx: &EvalContext) -> bool {
+        let due = task.due().map(|d| **d);
+        match self {
+            Self::Active => is_pending(task) && task.start().is_some(),
+            Self::Annotated => task.annotations().map(|a| !a.is_empty()).unwrap_or(false),
+            Self::Blocked => ctx.is_blocked(task),
+            Self::Blocking => ctx.is_blocking(task),
+            Self::Completed => task.status() == &TaskStatus::Completed,
+            Self::Deleted => task.status() == &TaskStatus::Deleted,
+            Self::Due => {
+                is_pending(task)
+                    && due
+                        .map(|d| d <= ctx.now + Duration::days(DUE_DAYS))
+                        .unwrap_or(false)
+            }
+            Self::Overdue => is_pending(task) && due.map(|d| d < ctx.now).unwrap_or(false),
+            Self::Pending => is_pending(task),
+            Self::Project => task.project().is_some(),
+            Self::Ready => {
+                is_pending(task)
+                    && !is_waiting(task, ctx)
+                    && !ctx.is_blocked(task)
+                    && task.scheduled().map(|s| **s <= ctx.now).unwrap_or(true)
+            }
+            Self::Recurring => task.status() == &TaskStatus::Recurring,
+            Self::Scheduled => task.scheduled().is_some(),
+            Self::Tagged => task.tags().map(|t| !t.is_empty()).unwrap_or(false),
+            Self::Today => due
+                .map(|d| d >= ctx.start_of_today && d < ctx.start_of_today + Duration::days(1))
+                .unwrap_or(false),
+            Self::Unblocked => !ctx.is_blocked(task),
+            Self::Waiting => is_waiting(task, ctx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Default,
+    Before,
+    After,
+    By,
+    Is,
+    Isnt,
+    Has,
+    Hasnt,
+    StartsWith,
+    EndsWith,
+    None,
+    Any,
+}
+
+impl Modifier {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "before" | "under" | "below" => Self::Before,
+            "after" | "over" | "above" => Self::After,
+            "by" => Self::By,
+            "is" | "equals" => Self::Is,
+            "isnt" | "not" => Self::Isnt,
+            "has" | "contains" => Self::Has,
+            "hasnt" => Self::Hasnt,
+            "startswith" | "left" => Self::StartsWith,
+            "endswith" | "right" => Self::EndsWith,
+            "none" => Self::None,
+            "any" => Self::Any,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Date(DateAttr, Modifier, Option<NaiveDateTime>),
+    Status(Vec<TaskStatus>, bool),
+    Text(TextAttr, Modifier, String),
+}
+
+impl Attribute {
+    fn new(name: &str, modifier: Modifier, value: &str) -> Option<Self> {
+        if let Some(attr) = DateAttr::from_name(name) {
+            return match modifier {
+                Modifier::None | Modifier::Any => Some(Self::Date(attr, modifier, None)),
+                Modifier::Before | Modifier::After | Modifier::By => {
+                    Some(Self::Date(attr, modifier, Some(parse_date(value)?)))
+                }
+                // equality on dates has day-level semantics in taskwarrior
+                // that aren't worth reproducing
+                _ => None,
+            };
+        }
+
+        if name == "status" {
+            let statuses = match value.to_lowercase().as_str() {
+                "pending" => vec![TaskStatus::Pending, TaskStatus::Waiting],
+                "waiting" => vec![TaskStatus::Waiting],
+                "completed" => vec![TaskStatus::Completed],
+                "deleted" => vec![TaskStatus::Deleted],
+                "recurring" => vec![TaskStatus::Recurring],
+                _ => return None,
+            };
+
+            return match modifier {
+                Modifier::Default | Modifier::Is => Some(Self::Status(statuses, true)),
+                Modifier::Isnt => Some(Self::Status(statuses, false)),
+                _ => None,
+            };
+        }
+
+        let attr = TextAttr::from_name(name);
+        match modifier {
+            Modifier::Before | Modifier::After | Modifier::By => None,
+            // an empty value is how taskwarrior spells "none"
+            Modifier::Default if value.is_empty() => {
+                Some(Self::Text(attr, Modifier::None, String::new()))
+            }
+            _ => Some(Self::Text(attr, modifier, value.to_string())),
+        }
+    }
+
+    fn matches(&self, task: &Task, ctx: &EvalContext) -> bool {
+        match self {
+            Self::Date(attr, modifier, value) => {
+                let date = attr.get(task);
+                match (modifier, date, value) {
+                    (Modifier::None, date, _) => date.is_none(),
+                    (Modifier::Any, date, _) => date.is_some(),
+                    (Modifier::Before, Some(date), Some(value)) => **date < *value,
+                    (Modifier::After, Some(date), Some(value)) => **date > *value,
+                    (Modifier::By, Some(date), Some(value)) => **date <= *value,
+                    _ => false,
+                }
+            }
+            Self::Status(statuses, expected) => {
+                let status = if is_waiting(task, ctx) {
+                    &TaskStatus::Waiting
+                } else {
+                    task.status()
+                };
+                // asking for pending includes waiting tasks, but asking for
+                // waiting should not include everything pending
+                let found = statuses.contains(status)
+                    || (statuses.contains(&TaskStatus::Pending) && status == &TaskStatus::Waiting);
+                found == *expected
+            }
+            Self::Text(attr, modifier, value) => {
+                let actual = attr.get(task);
+                match (modifier, actual) {
+                    (Modifier::None, actual) => actual.map(|a| a.is_empty()).unwrap_or(true),
+                    (Modifier::Any, actual) => actual.map(|a| !a.is_empty()).unwrap_or(false),
+                    (Modifier::Is, Some(actual)) => actual == *value,
+                    (Modifier::Isnt, actual) => actual.as_deref() != Some(value.as_str()),
+                    (Modifier::Has, Some(actual)) => actual.contains(value.as_str()),
+                    (Modifier::Hasnt, actual) => {
+                        !actual.map(|a| a.contains(value.as_str())).unwrap_or(false)
+                    }
+                    // taskwarrior's default for text is a left match
+                    (Modifier::Default | Modifier::StartsWith, Some(actual)) => {
+                        actual.starts_with(value.as_str())
+                    }
+                    (Modifier::EndsWith, Some(actual)) => actual.ends_with(value.as_str()),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateAttr {
+    Due,
+    End,
+    Entry,
+    Modified,
+    Scheduled,
+    Start,
+    Until,
+    Wait,
+}
+
+impl DateAttr {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "due" => Self::Due,
+            "end" => Self::End,
+            "entry" => Self::Entry,
+            "modified" | "mod" => Self::Modified,
+            "scheduled" | "sched" => Self::Scheduled,
+            "start" => Self::Start,
+            "until" => Self::Until,
+            "wait" => Self::Wait,
+            _ => return None,
+        })
+    }
+
+    fn get<'a>(&self, task: &'a Task) -> Option<&'a Date> {
+        match self {
+            Self::Due => task.due(),
+            Self::End => task.end(),
+            Self::Entry => Some(task.entry()),
+            Self::Modified => task.modified(),
+            Self::Scheduled => task.scheduled(),
+            Self::Start => task.start(),
+            Self::Until => task.until(),
+            Self::Wait => task.wait(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TextAttr {
+    Description,
+    Priority,
+    Project,
+    Uda(String),
+}
+
+impl TextAttr {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "description" | "desc" => Self::Description,
+            "priority" | "pri" => Self::Priority,
+            "project" | "proj" | "pro" => Self::Project,
+            other => Self::Uda(other.to_string()),
+        }
+    }
+
+    fn get(&self, task: &Task) -> Option<String> {
+        match self {
+            Self::Description => Some(task.description().clone()),
+            Self::Priority => task.priority().cloned(),
+            Self::Project => task.project().cloned(),
+            Self::Uda(key) => task.uda().get(key).map(|v| match v {
+                UDAValue::Str(s) => s.clone(),
+                UDAValue::U64(n) => n.to_string(),
+                UDAValue::F64(n) => n.to_string(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Term(Filter),
+}
+
+/// Split a shell word into parens and a term, since taskwarrior allows things
+/// like `(+DUE` and `+OVERDUE)`.
+fn tokenize(word: &str, tokens: &mut Vec<Token>) -> Option<()> {
+    let mut word = word.trim();
+    let mut closing = 0;
+
+    while let Some(rest) = word.strip_prefix('(') {
+        tokens.push(Token::Open);
+        word = rest;
+    }
+
+    while let Some(rest) = word.strip_suffix(')') {
+        closing += 1;
+        word = rest;
+    }
+
+    match word {
+        "" => {}
+        "and" => tokens.push(Token::And),
+        "or" => tokens.push(Token::Or),
+        // overrides only change how taskwarrior behaves, not what matches
+        w if w.starts_with("rc.") || w.starts_with("rc:") => {}
+        w => tokens.push(Token::Term(term(w)?)),
+    }
+
+    for _ in 0..closing {
+        tokens.push(Token::Close);
+    }
+
+    Some(())
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn expr(&mut self) -> Option<Filter> {
+        let mut terms = vec![self.conjunction()?];
+
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            terms.push(self.conjunction()?);
+        }
+
+        Some(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Filter::Or(terms)
+        })
+    }
+
+    /// Terms next to each other are implicitly and-ed together.
+    fn conjunction(&mut self) -> Option<Filter> {
+        let mut terms = Vec::new();
+
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                }
+                Some(Token::Open) => {
+                    self.pos += 1;
+                    terms.push(self.expr()?);
+                    if self.peek() != Some(&Token::Close) {
+                        return None;
+                    }
+                    self.pos += 1;
+                }
+                Some(Token::Term(filter)) => {
+                    terms.push(filter.clone());
+                    self.pos += 1;
+                }
+            }
+        }
+
+        Some(match terms.len() {
+            0 => Filter::All,
+            1 => terms.remove(0),
+            _ => Filter::And(terms),
+        })
+    }
+}
+
+fn term(word: &str) -> Option<Filter> {
+    alt((
+        all_consuming(tag_term),
+        all_consuming(id_term),
+        all_consuming(uuid_term),
+        all_consuming(attribute_term),
+    ))(word)
+    .ok()
+    .and_then(|(_, filter)| filter)
+}
+
+fn tag_term(i: &str) -> IResult<&str, Option<Filter>> {
+    map(pair(one_of("+-"), rest), |(sign, name): (char, &str)| {
+        let present = sign == '+';
+        if name.is_empty() {
+            None
+        } else if name.chars().all(|c| c.is_ascii_uppercase()) {
+            // unknown virtual tags can't be evaluated, so bail entirely
+            VirtualTag::from_name(name).map(|t| Filter::Virtual(t, present))
+        } else {
+            Some(Filter::Tag(name.to_string(), present))
+        }
+    })(i)
+}
+
+fn id_term(i: &str) -> IResult<&str, Option<Filter>> {
+    map(separated_list1(char(','), parse_u64), |ids| {
+        Some(Filter::Ids(ids))
+    })(i)
+}
+
+fn uuid_term(i: &str) -> IResult<&str, Option<Filter>> {
+    map(
+        verify(
+            take_while1(|c: char| c.is_ascii_hexdigit() || c == '-'),
+            |s: &str| s.len() >= 8 && s.chars().take(8).all(|c| c.is_ascii_hexdigit()),
+        ),
+        |s: &str| Some(Filter::Uuid(s.to_lowercase())),
+    )(i)
+}
+
+fn attribute_term(i: &str) -> IResult<&str, Option<Filter>> {
+    map(
+        separated_pair(
+            pair(
+                recognize(pair(
+                    alpha1,
+                    many0(alt((take_while1(|c: char| c.is_alphanumeric()), tag("_")))),
+                )),
+                opt(preceded(char('.'), alpha1)),
+            ),
+            char(':'),
+            rest,
+        ),
+        |((name, modifier), value): ((&str, Option<&str>), &str)| {
+            let modifier = match modifier {
+                Some(m) => Modifier::from_name(m)?,
+                None => Modifier::Default,
+            };
+            Attribute::new(name, modifier, value).map(Filter::Attribute)
+        },
+    )(i)
+}
+
+/// Parse the handful of date expressions we support into a utc timestamp,
+/// matching how task_hookrs represents dates.
+fn parse_date(input: &str) -> Option<NaiveDateTime> {
+    all_consuming(pair(date_base, many0(date_offset)))(input)
+        .ok()
+        .map(|(_, (base, offsets))| offsets.into_iter().fold(base, |acc, o| acc + o))
+}
+
+fn date_base(i: &str) -> IResult<&str, NaiveDateTime> {
+    let today = Local::now().date_naive();
+    alt((
+        value(Utc::now().naive_utc(), tag("now")),
+        value(local_midnight(today), alt((tag("today"), tag("sod")))),
+        value(
+            local_midnight(today + Duration::days(1)) - Duration::seconds(1),
+            tag("eod"),
+        ),
+        value(local_midnight(today - Duration::days(1)), tag("yesterday")),
+        value(local_midnight(today + Duration::days(1)), tag("tomorrow")),
+        map_res(
+            recognize(tuple((digit1, char('-'), digit1, char('-'), digit1))),
+            |s: &str| NaiveDate::parse_from_str(s, "%F").map(local_midnight),
+        ),
+    ))(i)
+}
+
+fn date_offset(i: &str) -> IResult<&str, Duration> {
+    map(
+        tuple((one_of("+-"), parse_u64, one_of("dwh"))),
+        |(sign, amount, unit)| {
+            let amount = amount as i64 * if sign == '-' { -1 } else { 1 };
+            match unit {
+                'w' => Duration::weeks(amount),
+                'h' => Duration::hours(amount),
+                _ => Duration::days(amount),
+            }
+        },
+    )(i)
+}
+
+fn local_midnight(date: NaiveDate) -> NaiveDateTime {
+    let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is always valid");
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .map(|d| d.naive_utc())
+        .unwrap_or(midnight)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Option<Filter> {
+        Filter::parse(input, &[String::from("delegatee")])
+    }
+
+    fn task(json: &str) -> Task {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn parsing_supported_filters() {
+        assert_eq!(parse(""), Some(Filter::All));
+        assert_eq!(
+            parse("+next -in"),
+            Some(Filter::And(vec![
+                Filter::Tag("next".into(), true),
+                Filter::Tag("in".into(), false),
+            ]))
+        );
+        assert_eq!(
+            parse("+ACTIVE"),
+            Some(Filter::Virtual(VirtualTag::Active, true))
+        );
+        assert_eq!(parse("4"), Some(Filter::Ids(vec![4])));
+        assert!(parse("-in +@work status:Pending and (+DUE or +OVERDUE)").is_some());
+        assert!(parse(
+            "+@work -@home and ((+WAITING and wait.before:today+5d) or (+tickle and status:Pending))"
+        )
+        .is_some());
+        assert!(parse("rc.hooks=off project.is:work +next").is_some());
+    }
+
+    #[test]
+    fn rejecting_unsupported_filters() {
+        // unknown virtual tags
+        assert_eq!(parse("+LATEST"), None);
+        // bare words are description searches
+        assert_eq!(parse("groceries"), None);
+        // date equality
+        assert_eq!(parse("due:today"), None);
+        // unknown date expressions
+        assert_eq!(parse("end.after:monday"), None);
+        // unbalanced
+        assert_eq!(parse("(+next"), None);
+        assert_eq!(parse("xor"), None);
+        // attributes that aren't core ones or known UDAs
+        assert_eq!(parse("tags:next"), None);
+        assert_eq!(parse("limit:5 +next"), None);
+        assert_eq!(parse("(+next or urgency.over:5)"), None);
+        assert!(parse("delegatee.any: est.none:").is_some());
+    }
+
+    #[test]
+    fn matching_tasks() {
+        let active = task(
+            r#"{"id": 1, "uuid": "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", "status": "pending",
+                "entry": "20220101T000000Z", "start": "20220102T000000Z",
+                "description": "a", "project": "work.api", "tags": ["next"]}"#,
+        );
+        let done = task(
+            r#"{"uuid": "7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21", "status": "completed",
+                "entry": "20220101T000000Z", "end": "20220103T000000Z",
+                "description": "b", "project": "work"}"#,
+        );
+        let tasks = vec![active.clone(), done.clone()];
+        let ctx = EvalContext::new(&tasks);
+
+        let check = |filter: &str, task: &Task| parse(filter).unwrap().matches(task, &ctx);
+
+        assert!(check("+ACTIVE", &active));
+        assert!(!check("+ACTIVE", &done));
+        assert!(check("proj:work", &active));
+        assert!(!check("project.is:work", &active));
+        assert!(check("project.is:work", &done));
+        assert!(check("status:pending +next", &active));
+        assert!(check("status:completed end.after:2022-01-02", &done));
+        assert!(!check("status:completed end.after:2022-01-04", &done));
+        assert!(check("+next or status:completed", &done));
+        assert!(check("1", &active));
+        assert!(check("7a8b5c4e", &done));
+        assert!(check("brain:", &active));
+    }
+}
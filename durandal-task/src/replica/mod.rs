@@ -0,0 +1,439 @@
+//! Read-only access to a Taskwarrior 3 (TaskChampion) sqlite replica.
+//!
+//! Spawning `task export` for every read adds up, especially for things like a
+//! shell prompt. When configured, reads are answered directly from the replica
+//! instead, falling back to the cli for any filter we don't know how to
+//! evaluate ourselves. Writes are never performed against the replica.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use anyhow::{Context, Result};
+use chrono::{NaiveDateTime, Utc};
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{json, Map, Value};
+use task_hookrs::date::TASKWARRIOR_DATETIME_TEMPLATE;
+use task_hookrs::task::Task;
+
+use crate::config::{expand_home, TaskChampion};
+use crate::task_table::{UdaType, UDA};
+
+use self::filter::Filter;
+
+mod filter;
+
+const REPLICA_FILE: &str = "taskchampion.sqlite3";
+
+static REPLICA: OnceLock<Replica> = OnceLock::new();
+
+/// Use the replica described by `config` for all subsequent reads, knowing
+/// about `udas` for filtering and reading numeric values.
+pub fn configure(config: &TaskChampion, udas: &[UDA]) -> Result<()> {
+    let mut replica = Replica::new(expand_home(&config.data_location).join(REPLICA_FILE));
+    replica.udas = udas.iter().map(|u| u.key.clone()).collect();
+    replica.numeric = udas
+        .iter()
+        .filter(|u| u.kind == UdaType::Numeric)
+        .map(|u| u.key.clone())
+        .collect();
+
+    if !replica.path.exists() {
+        anyhow::bail!(
+            "Configured taskchampion replica does not exist: {:?}",
+            replica.path
+        );
+    }
+
+    // if this was already set, the first configuration wins
+    let _ = REPLICA.set(replica);
+    Ok(())
+}
+
+/// The configured replica, if any.
+pub fn replica() -> Option<&'static Replica> {
+    REPLICA.get()
+}
+
+#[derive(Debug, Clone)]
+pub struct Replica {
+    path: PathBuf,
+    /// The UDAs filters may refer to.
+    udas: Vec<String>,
+    /// UDAs declared numeric in the config. Taskchampion stores everything as
+    /// strings.
+    numeric: Vec<String>,
+}
+
+impl Replica {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            udas: Vec::new(),
+            numeric: Vec::new(),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        Connection::open_with_flags(
+            &self.path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )
+        .with_context(|| format!("Could not open taskchampion replica {:?}", self.path))
+    }
+
+    /// Load the tasks matching `filter`.
+    ///
+    /// Returns `None` if the filter uses syntax we can't evaluate, in which
+    /// case the caller is expected to ask the cli instead.
+    pub fn load_tasks(&self, filter: &str) -> Result<Option<Vec<Task>>> {
+        let filter = match Filter::parse(filter, &self.udas) {
+            Some(filter) => filter,
+            None => return Ok(None),
+        };
+
+        let tasks = self.all_tasks()?;
+        let ctx = filter::EvalContext::new(&tasks);
+
+        Ok(Some(
+            tasks
+                .iter()
+                .filter(|t| filter.matches(t, &ctx))
+                .cloned()
+                .collect(),
+        ))
+    }
+
+    /// The distinct projects of all pending tasks, like `task _projects`.
+    pub fn projects(&self) -> Result<Vec<String>> {
+        let mut projects: Vec<String> = self
+            .all_tasks()?
+            .iter()
+            .filter(|t| filter::is_pending(t))
+            .filter_map(|t| t.project().cloned())
+            .collect();
+        projects.sort();
+        projects.dedup();
+
+        Ok(projects)
+    }
+
+    fn all_tasks(&self) -> Result<Vec<Task>> {
+        let conn = self.connect()?;
+
+        let mut ids = HashMap::new();
+        let mut stmt = conn.prepare("SELECT id, uuid FROM working_set")?;
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (id, uuid) = row?;
+            ids.insert(uuid, id);
+        }
+
+        let mut tasks = Vec::new();
+        let mut stmt = conn.prepare("SELECT uuid, data FROM tasks")?;
+        for row in stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })? {
+            let (uuid, data) = row?;
+            let data: HashMap<String, String> = serde_json::from_str(&data)
+                .with_context(|| format!("Malformed task data in replica for {}", uuid))?;
+
+            tasks.push(
+                to_task(&uuid, ids.get(&uuid).copied(), &data, &self.numeric)
+                    .with_context(|| format!("Could not read task {} from replica", uuid))?,
+            );
+        }
+
+        set_urgency(&mut tasks, Utc::now().naive_utc());
+        Ok(tasks)
+    }
+}
+
+/// Fill in the urgency taskwarrior would have exported, which the replica
+/// doesn't store. This uses taskwarrior's default coefficients, any
+/// `urgency.*` settings in the taskrc aren't taken into account.
+fn set_urgency(tasks: &mut [Task], now: NaiveDateTime) {
+    let ctx = filter::EvalContext::new(tasks);
+
+    for task in tasks.iter_mut() {
+        let urgency = urgency(task, now, ctx.is_blocking(task), ctx.is_blocked(task));
+        task.set_urgency(Some(urgency));
+    }
+}
+
+fn urgency(task: &Task, now: NaiveDateTime, blocking: bool, blocked: bool) -> f64 {
+    // 0.8 for one, 0.9 for two and 1.0 for more, like taskwarrior
+    let count = |n: usize| match n {
+        0 => 0.0,
+        1 => 0.8,
+        2 => 0.9,
+        _ => 1.0,
+    };
+    let tags = task.tags().cloned().unwrap_or_default();
+    let mut urgency = 0.0;
+
+    if tags.iter().any(|t| t == "next") {
+        urgency += 15.0;
+    }
+
+    if let Some(due) = task.due() {
+        let overdue = (now - **due).num_seconds() as f64 / 86400.0;
+        urgency += 12.0
+            * match overdue {
+                d if d >= 7.0 => 1.0,
+                d if d >= -14.0 => (d + 14.0) * 0.8 / 21.0 + 0.2,
+                _ => 0.2,
+            };
+    }
+
+    urgency += match task.priority().map(|p| p.as_str()) {
+        Some("H") => 6.0,
+        Some("M") => 3.9,
+        Some("L") => 1.8,
+        _ => 0.0,
+    };
+
+    if task.start().is_some() {
+        urgency += 4.0;
+    }
+    if task.scheduled().map(|s| **s < now).unwrap_or(false) {
+        urgency += 5.0;
+    }
+
+    let age = (now - **task.entry()).num_seconds() as f64 / 86400.0;
+    urgency += 2.0 * (age / 365.0).clamp(0.0, 1.0);
+
+    urgency += count(task.annotations().map(|a| a.len()).unwrap_or(0));
+    urgency += count(tags.len());
+    if task.project().is_some() {
+        urgency += 1.0;
+    }
+
+    if blocking {
+        urgency += 8.0;
+    }
+    if blocked {
+        urgency -= 5.0;
+    }
+    if task.wait().map(|w| **w > now).unwrap_or(false) {
+        urgency -= 3.0;
+    }
+
+    urgency
+}
+
+/// Convert the key/value representation taskchampion uses into the same json
+/// `task export` would produce, then let `task_hookrs` do the rest.
+fn to_task(
+    uuid: &str,
+    id: Option<u64>,
+    data: &HashMap<String, String>,
+    numeric: &[String],
+) -> Result<Task> {
+    let mut obj = Map::new();
+    let mut tags = Vec::new();
+    let mut depends = Vec::new();
+    let mut annotations = Vec::new();
+
+    obj.insert("uuid".into(), json!(uuid));
+    if let Some(id) = id {
+        obj.insert("id".into(), json!(id));
+    }
+
+    for (key, value) in data {
+        if let Some(tag) = key.strip_prefix("tag_") {
+            tags.push(tag.to_string());
+        } else if let Some(dep) = key.strip_prefix("dep_") {
+            depends.push(dep.to_string());
+        } else if let Some(entry) = key.strip_prefix("annotation_") {
+            annotations.push((entry.parse::<i64>()?, value.clone()));
+        } else if is_date_key(key) {
+            obj.insert(key.clone(), json!(format_timestamp(value)?));
+        } else if is_numeric_key(key) || numeric.contains(key) {
+            obj.insert(key.clone(), to_number(value));
+        } else {
+            // everything else, including string UDAs, is carried over as is
+            obj.insert(key.clone(), json!(value));
+        }
+    }
+
+    // taskchampion does not store a separate waiting status, but task_hookrs
+    // still requires one to be present
+    obj.entry("status").or_insert_with(|| json!("pending"));
+
+    if !tags.is_empty() {
+        tags.sort();
+        obj.insert("tags".into(), json!(tags));
+    }
+
+    if !depends.is_empty() {
+        depends.sort();
+        obj.insert("depends".into(), json!(depends));
+    }
+
+    if !annotations.is_empty() {
+        annotations.sort();
+        let annotations = annotations
+            .into_iter()
+            .map(|(entry, description)| {
+                Ok(json!({
+                    "entry": format_timestamp(&entry.to_string())?,
+                    "description": description,
+                }))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        obj.insert("annotations".into(), Value::Array(annotations));
+    }
+
+    Ok(serde_json::from_value(Value::Object(obj))?)
+}
+
+fn is_date_key(key: &str) -> bool {
+    matches!(
+        key,
+        "entry" | "modified" | "start" | "end" | "due" | "wait" | "scheduled" | "until"
+    )
+}
+
+/// `imask` is a number to `task_hookrs`, and `est` is written as one.
+fn is_numeric_key(key: &str) -> bool {
+    matches!(key, "imask" | "est")
+}
+
+/// Whole numbers stay whole, so they come out as `UDAValue::U64`. Anything
+/// that isn't a number at all is kept as the string it was.
+fn to_number(value: &str) -> Value {
+    if let Ok(n) = value.parse::<u64>() {
+        json!(n)
+    } else if let Ok(n) = value.parse::<f64>() {
+        json!(n)
+    } else {
+        json!(value)
+    }
+}
+
+fn format_timestamp(value: &str) -> Result<String> {
+    let secs: i64 = value
+        .parse()
+        .with_context(|| format!("Invalid timestamp {}", value))?;
+    let date = NaiveDateTime::from_timestamp_opt(secs, 0)
+        .ok_or_else(|| anyhow::anyhow!("Timestamp out of range {}", value))?;
+
+    Ok(date.format(TASKWARRIOR_DATETIME_TEMPLATE).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use task_hookrs::status::TaskStatus;
+    use task_hookrs::uda::UDAValue;
+
+    use super::*;
+
+    #[test]
+    fn converting_replica_data() {
+        let data: HashMap<String, String> = serde_json::from_str(
+            r#"{
+                "status": "pending",
+                "description": "write the thing",
+                "entry": "1650000000",
+                "start": "1650000100",
+                "project": "work.api",
+                "tag_next": "",
+                "tag_@work": "",
+                "annotation_1650000200": "second",
+                "annotation_1650000050": "first",
+                "dep_7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21": "",
+                "brain": "M"
+            }"#,
+        )
+        .unwrap();
+
+        let task = to_task("1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10", Some(3), &data, &[]).unwrap();
+
+        assert_eq!(task.id(), Some(3));
+        assert_eq!(task.status(), &TaskStatus::Pending);
+        assert_eq!(task.project(), Some(&String::from("work.api")));
+        assert_eq!(
+            task.tags(),
+            Some(&vec![String::from("@work"), String::from("next")])
+        );
+        assert!(task.start().is_some());
+        assert_eq!(task.depends().map(|d| d.len()), Some(1));
+        assert_eq!(task.uda().get("brain"), Some(&UDAValue::Str("M".into())));
+
+        let annotations: Vec<_> = task
+            .annotations()
+            .unwrap()
+            .iter()
+            .map(|a| a.description().clone())
+            .collect();
+        assert_eq!(annotations, vec!["first", "second"]);
+    }
+
+    #[test]
+    fn converting_recurring_children() {
+        let data: HashMap<String, String> = serde_json::from_str(
+            r#"{
+                "status": "pending",
+                "description": "water the plants",
+                "entry": "1650000000",
+                "due": "1650600000",
+                "recur": "weekly",
+                "imask": "3",
+                "parent": "7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21",
+                "est": "30",
+                "points": "2.5",
+                "brain": "L"
+            }"#,
+        )
+        .unwrap();
+
+        let numeric = vec![String::from("points")];
+        let task = to_task(
+            "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10",
+            None,
+            &data,
+            &numeric,
+        )
+        .unwrap();
+
+        assert_eq!(task.imask(), Some(&3.0));
+        assert_eq!(task.recur(), Some(&String::from("weekly")));
+        assert_eq!(
+            task.parent().map(|p| p.to_string()),
+            Some(String::from("7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21"))
+        );
+        assert_eq!(task.uda().get("est"), Some(&UDAValue::U64(30)));
+        assert_eq!(task.uda().get("points"), Some(&UDAValue::F64(2.5)));
+        assert_eq!(task.uda().get("brain"), Some(&UDAValue::Str("L".into())));
+    }
+
+    #[test]
+    fn computing_urgency() {
+        let task = |uuid: &str, fields: &str| -> Task {
+            let data: HashMap<String, String> = serde_json::from_str(&format!(
+                r#"{{"status": "pending", "description": "a", "entry": "1650000000"{}}}"#,
+                fields
+            ))
+            .unwrap();
+            to_task(uuid, None, &data, &[]).unwrap()
+        };
+        let blocker = "7a8b5c4e-0b6f-4bd4-8a1d-1f0e9b9f3c21";
+        let mut tasks = vec![
+            task(blocker, r#", "tag_next": "", "priority": "H""#),
+            task(
+                "1e3c7a1b-6a2d-4e41-9b79-4f7a2a9d1c10",
+                &format!(r#", "dep_{}": "", "project": "work""#, blocker),
+            ),
+        ];
+
+        // a year later, so age counts fully
+        let now = NaiveDateTime::from_timestamp_opt(1650000000 + 400 * 86400, 0).unwrap();
+        set_urgency(&mut tasks, now);
+
+        // next, high priority, age, one tag and blocking another task
+        assert_eq!(tasks[0].urgency(), Some(&(15.0 + 6.0 + 2.0 + 0.8 + 8.0)));
+        // age and project, less being blocked
+        assert_eq!(tasks[1].urgency(), Some(&(2.0 + 1.0 - 5.0)));
+    }
+}
@@ -1,65 +1,109 @@
-use std::collections::HashSet;
 use std::ffi::OsStr;
 use std::fmt::{self, Debug};
-use std::ops::{Deref, DerefMut};
-use std::process::Command;
+use std::io::Write;
+use std::process::{Command, Stdio};
 
 use anyhow::{bail, Context, Result};
+use chrono::{Duration, Local, NaiveDateTime, TimeZone, Utc};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use task_hookrs::date::TASKWARRIOR_DATETIME_TEMPLATE;
 use task_hookrs::import::import;
 use task_hookrs::status::TaskStatus;
 use task_hookrs::task::Task;
+use task_hookrs::uda::{UDAValue, UDA};
+use uuid::Uuid;
 
-use crate::parser::new_task_parser;
+use crate::config::Categories;
+use crate::dates;
+use crate::error::TaskError;
+use crate::parser::{TaskOutput, Verb};
+use crate::project::{Project, ProjectPattern, ProjectPolicy};
 
 /// This trait is just a convenient way to add some functionality to the Task
 /// objects provided by `task_hookrs`
+///
+/// Everything that modifies a task returns what taskwarrior reported doing,
+/// including any feedback from hooks.
 pub trait Processable {
-    fn execute<I, S>(&self, args: I) -> Result<()>
+    fn execute<I, S>(&self, args: I) -> Result<TaskOutput>
     where
         I: IntoIterator<Item = S> + Debug + Clone,
         S: AsRef<OsStr>;
 
-    fn finish(&self) -> Result<()> {
+    fn finish(&self) -> Result<TaskOutput> {
         self.execute(["done"])
+            .and_then(|output| expect_count(output, "done", Verb::Completed))
             .with_context(|| "Could not finish task")
     }
 
-    fn delete(&self) -> Result<()> {
+    fn delete(&self) -> Result<TaskOutput> {
         self.execute(["delete"])
+            .and_then(|output| expect_count(output, "delete", Verb::Deleted))
             .with_context(|| "Could not delete task")
     }
 
-    fn annotate(&self, msg: &str) -> Result<()> {
+    fn annotate(&self, msg: &str) -> Result<TaskOutput> {
         self.execute(["annotate", msg])
             .with_context(|| "Could not annotate task")
     }
 
-    fn begin(&self) -> Result<()> {
+    fn begin(&self) -> Result<TaskOutput> {
         self.execute(["start"])
             .with_context(|| "Could not start task")
     }
 
-    fn stop(&self) -> Result<()> {
+    fn stop(&self) -> Result<TaskOutput> {
         self.execute(["stop"])
             .with_context(|| "Could not stop task")
     }
 
-    fn tickle(&self, wait: &str) -> Result<()> {
+    fn tickle(&self, wait: &str) -> Result<TaskOutput> {
         self.execute(["modify", "+tickle", &format!("wait:{}", wait)])
             .with_context(|| "Could not add task to tickler")
     }
 
-    fn someday(&self) -> Result<()> {
-        self.execute(["modify", "-in", "-@home", "-@work", "proj:maybe"])
+    /// Move the task to the maybe project, out of the inbox and any of the
+    /// configured contexts.
+    fn someday(&self, categories: &Categories) -> Result<TaskOutput> {
+        let mut args = without_contexts(categories);
+        args.push("proj:maybe".into());
+        self.execute(args)
             .with_context(|| "Could not move task to maybe")
     }
 
-    fn reference(&self) -> Result<()> {
-        self.execute(["modify", "-in", "-@home", "-@work", "+reference"])
+    /// Keep the task for reference, out of the inbox and any of the
+    /// configured contexts.
+    fn reference(&self, categories: &Categories) -> Result<TaskOutput> {
+        let mut args = without_contexts(categories);
+        args.push("+reference".into());
+        self.execute(args)
             .with_context(|| "Could not move task to be referenced")
     }
 
-    fn add_tags(&self, tags: &[&str]) -> Result<()> {
+    /// Move an inbox item to `project`, taking it out of the inbox.
+    fn move_to(&self, project: &Project) -> Result<TaskOutput> {
+        self.execute([
+            "modify".into(),
+            format!("project:{}", project),
+            "-in".into(),
+        ])
+        .with_context(|| format!("Could not move task to {}", project))
+    }
+
+    /// Give an inbox item the context tags `contexts`, taking it out of the
+    /// inbox.
+    fn categorize(&self, contexts: &[&str]) -> Result<TaskOutput> {
+        let mut args = vec!["modify".into()];
+        for context in contexts.iter() {
+            args.push(format!("+{}", context));
+        }
+        args.push("-in".into());
+        self.execute(args)
+            .with_context(|| format!("Could not add contexts {:?}", contexts))
+    }
+
+    fn add_tags(&self, tags: &[&str]) -> Result<TaskOutput> {
         let mut args = vec!["modify".into()];
         for tag in tags.iter() {
             args.push(format!("+{}", tag));
@@ -68,7 +112,7 @@ pub trait Processable {
             .with_context(|| format!("Could not add tags {:?}", tags))
     }
 
-    fn remove_tags(&self, tags: &[&str]) -> Result<()> {
+    fn remove_tags(&self, tags: &[&str]) -> Result<TaskOutput> {
         let mut args = vec!["modify".into()];
         for tag in tags.iter() {
             args.push(format!("-{}", tag));
@@ -77,18 +121,27 @@ pub trait Processable {
             .with_context(|| format!("Could not remove tags {:?}", tags))
     }
 
-    fn set_resumable(&self, task: &Task) -> Result<()> {
+    fn set_resumable(&self, task: &Task) -> Result<TaskOutput> {
         self.annotate(&format!("DTR:{}", task.uuid().to_string()))
             .with_context(|| "could not annotate task")
     }
 
     fn task_to_resume(&self) -> Result<Option<Task>>;
 
-    /// Creates a follow-up task annotated with the description from the current
-    /// task.
+    /// Turns the task into a follow-up reminder with `msg` as its description,
+    /// annotated with the old description along with any additional
+    /// `annotations` and set with `udas`, like who it's waiting on.
+    /// Everything else about the task, like its uuid and links, is kept.
     ///
     /// `wait` can be any date format that taskwarrior accepts.
-    fn follow_up(&self, msg: &str, wait: &str) -> Result<Task>;
+    fn follow_up(
+        &self,
+        categories: &Categories,
+        msg: &str,
+        wait: &str,
+        annotations: &[&str],
+        udas: &[(&str, &str)],
+    ) -> Result<Task>;
 
     fn has_tag(&self, tag: &str) -> bool;
 
@@ -97,30 +150,56 @@ pub trait Processable {
     fn annotated_description(&self) -> String;
 }
 
+/// Taskwarrior happily exits successfully after e.g. completing zero tasks, so
+/// make sure it actually did something.
+fn expect_count(output: TaskOutput, command: &str, verb: Verb) -> Result<TaskOutput> {
+    if output.count(verb) == Some(0) {
+        return Err(TaskError::Unexpected {
+            args: vec![command.into()],
+            output,
+        }
+        .into());
+    }
+
+    Ok(output)
+}
+
 impl Processable for Task {
-    fn execute<I, S>(&self, args: I) -> Result<()>
+    fn execute<I, S>(&self, args: I) -> Result<TaskOutput>
     where
         I: IntoIterator<Item = S> + Debug + Clone,
         S: AsRef<OsStr>,
     {
         if let Some(id) = self.id() {
-            let output = Command::new("task")
+            let output = task_command()
                 .arg("rc.confirmation=off")
                 .arg(format!("{}", id))
                 .args(args.clone())
                 .output()
                 .with_context(|| format!("Failed to execute command for task {}", id))?;
 
+            let parsed = TaskOutput::parse(
+                &String::from_utf8_lossy(&output.stdout),
+                &String::from_utf8_lossy(&output.stderr),
+            );
+
             if !output.status.success() {
-                bail!(
-                    "Command with args {:?} for task {} did not succeed with output {:?}",
+                let args = std::iter::once(id.to_string())
+                    .chain(
+                        args.into_iter()
+                            .map(|a| a.as_ref().to_string_lossy().into_owned()),
+                    )
+                    .collect();
+
+                return Err(TaskError::CommandFailed {
                     args,
-                    id,
-                    output
-                );
+                    status: output.status,
+                    output: parsed,
+                }
+                .into());
             }
 
-            Ok(())
+            Ok(parsed)
         } else {
             bail!("Task did not have an id! {:?}", self)
         }
@@ -144,18 +223,33 @@ impl Processable for Task {
         Ok(None)
     }
 
-    fn follow_up(&self, msg: &str, wait: &str) -> Result<Task> {
-        let task = TaskBuilder::new()
+    fn follow_up(
+        &self,
+        categories: &Categories,
+        msg: &str,
+        wait: &str,
+        annotations: &[&str],
+        udas: &[(&str, &str)],
+    ) -> Result<Task> {
+        let mut builder = TaskBuilder::new(categories);
+        builder
             .with_tags(["in", "tickle"])
             .with_wait(wait)
-            .with_contexts(&[ActionCategory::Work, ActionCategory::Home])
+            .with_context_tags(&categories.follow_up_contexts)
             .with_description(msg)
-            .build()
-            .with_context(|| "Failed to create follow-up task")?;
+            .with_annotation(format!("follow up from {}", self.description()));
+
+        for annotation in annotations {
+            builder.with_annotation(annotation);
+        }
 
-        task.annotate(&format!("follow up from {}", self.description()))?;
+        for (key, value) in udas {
+            builder.with_uda(key, UDAValue::Str(value.to_string()));
+        }
 
-        Ok(task)
+        builder
+            .modify(self)
+            .with_context(|| "Failed to turn task into a follow-up")
     }
 
     fn has_tag(&self, tag: &str) -> bool {
@@ -181,18 +275,25 @@ impl Processable for Task {
         let mut s = self.description().clone();
         if let Some(annotations) = self.annotations() {
             for a in annotations {
-                s += &format!("\n    {} {}", a.entry().format("%F"), a.description());
+                s += &format!(
+                    "\n    {} {}",
+                    dates::format_date(**a.entry()),
+                    a.description()
+                );
             }
         }
         s
     }
 }
 
-fn load_task(id: u64) -> Result<Task> {
-    load_tasks(&format!("{}", id))?
-        .into_iter()
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("Could not find task with id {}", id))
+/// The start of a `modify` that takes a task out of the inbox and all of the
+/// configured contexts.
+fn without_contexts(categories: &Categories) -> Vec<String> {
+    let mut args = vec!["modify".to_string(), "-in".into()];
+    for context in &categories.contexts {
+        args.push(format!("-{}", context.tag()));
+    }
+    args
 }
 
 fn load_task_from_uuid(uuid: &str) -> Result<Task> {
@@ -202,8 +303,23 @@ fn load_task_from_uuid(uuid: &str) -> Result<Task> {
         .ok_or_else(|| anyhow::anyhow!("Could not find task with uuid {}", uuid))
 }
 
+/// Load the tasks matching `filter`.
+///
+/// If a taskchampion replica is configured and understands the filter, it is
+/// used instead of `task export`.
 pub fn load_tasks(filter: &str) -> Result<Vec<Task>> {
-    let mut task = Command::new("task");
+    #[cfg(feature = "taskchampion")]
+    if let Some(replica) = crate::replica::replica() {
+        if let Some(tasks) = replica.load_tasks(filter)? {
+            return Ok(tasks);
+        }
+    }
+
+    export_tasks(filter)
+}
+
+fn export_tasks(filter: &str) -> Result<Vec<Task>> {
+    let mut task = task_command();
     task.arg("rc.json.array=on");
     task.arg("rc.confirmation=off");
 
@@ -230,71 +346,75 @@ pub fn load_tasks(filter: &str) -> Result<Vec<Task>> {
     })
 }
 
-pub fn active_tasks() -> Result<Vec<Task>> {
-    let current_task_filter = "+ACTIVE";
+/// Everyone tasks have been delegated to, most recently delegated to first.
+pub fn delegatees() -> Result<Vec<String>> {
+    let mut tasks = load_tasks("delegatee.any:")?;
+    tasks.sort_by_key(|t| std::cmp::Reverse(t.modified().map(|d| **d)));
 
-    load_tasks(&current_task_filter).with_context(|| "Could not fetch current tasks")
-}
+    let mut delegatees: Vec<String> = Vec::new();
+    for task in &tasks {
+        if let Some(UDAValue::Str(name)) = task.uda().get("delegatee") {
+            if !name.is_empty() && !delegatees.contains(name) {
+                delegatees.push(name.clone());
+            }
+        }
+    }
 
-pub fn active_task() -> Result<Option<Task>> {
-    Ok(active_tasks()?.first().cloned())
+    Ok(delegatees)
 }
 
-#[derive(Debug, Clone, Default, PartialEq)]
-pub struct Project(String);
+/// Make sure taskwarrior knows about the UDAs `names`, which it would
+/// otherwise add to the description of the tasks they're set on.
+pub fn require_udas(names: &[&str]) -> Result<()> {
+    let output = task_command()
+        .arg("_udas")
+        .output()
+        .with_context(|| "Attempting to read UDAs")?;
 
-impl Project {
-    pub fn tasks(&self) -> Result<Vec<Task>> {
-        let filter = format!("proj:{} status:pending", self);
-        load_tasks(&filter)
+    if !output.status.success() {
+        bail!("Could not list UDAs: {:?}", output);
     }
-}
-
-impl Deref for Project {
-    type Target = String;
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+    let out = String::from_utf8(output.stdout)?;
+    let declared: Vec<_> = out.lines().map(str::trim).collect();
+    let missing: Vec<_> = names.iter().filter(|n| !declared.contains(n)).collect();
+
+    if !missing.is_empty() {
+        let lines: Vec<_> = missing
+            .iter()
+            .map(|n| format!("uda.{}.type=string", n))
+            .collect();
+        bail!(
+            "Declare these UDAs in your taskrc first:\n{}",
+            lines.join("\n")
+        );
     }
-}
 
-impl DerefMut for Project {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
+    Ok(())
 }
 
-impl fmt::Display for Project {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        std::fmt::Display::fmt(&self.0, f)
-    }
-}
+pub fn active_tasks() -> Result<Vec<Task>> {
+    let current_task_filter = "+ACTIVE";
 
-impl From<Project> for String {
-    fn from(p: Project) -> Self {
-        p.0
-    }
+    load_tasks(&current_task_filter).with_context(|| "Could not fetch current tasks")
 }
 
-impl From<String> for Project {
-    fn from(s: String) -> Self {
-        Project(s)
-    }
+pub fn active_task() -> Result<Option<Task>> {
+    Ok(active_tasks()?.first().cloned())
 }
 
-impl From<&str> for Project {
-    fn from(s: &str) -> Self {
-        s.to_string().into()
+pub fn projects_excluding(exclude: &[ProjectPattern]) -> Result<Vec<Project>> {
+    #[cfg(feature = "taskchampion")]
+    if let Some(replica) = crate::replica::replica() {
+        return Ok(replica
+            .projects()?
+            .into_iter()
+            .filter(|s| !exclude.iter().any(|p| p.matches(s)))
+            .map(Project::from)
+            .collect());
     }
-}
-
-pub fn projects() -> Result<Vec<Project>> {
-    let excluding = HashSet::new();
-    projects_excluding(&excluding)
-}
 
-pub fn projects_excluding(exclude: &HashSet<String>) -> Result<Vec<Project>> {
-    let output = Command::new("task")
+    let output = task_command()
         .arg("_projects")
         .output()
         .with_context(|| "Attempting to read projects")?;
@@ -309,8 +429,8 @@ pub fn projects_excluding(exclude: &HashSet<String>) -> Result<Vec<Project>> {
         .split("\n")
         .into_iter()
         // exclude these special projects
-        .filter(|s| !exclude.contains(*s))
-        .map(|s| Project(s.to_string()))
+        .filter(|s| !exclude.iter().any(|p| p.matches(s)))
+        .map(Project::from)
         .collect())
 }
 
@@ -343,30 +463,93 @@ impl TaskClient {
 /// So I'm aware that the task-hookrs lib provides a builder, but since I'm
 /// going to be interacting via the command line, I'm going to just make my
 /// own.
+///
+/// Tasks are created in a single `task import` with a uuid generated up front,
+/// so everything, annotations included, lands at once.
 #[derive(Debug, Clone)]
 pub struct TaskBuilder {
+    uuid: Uuid,
     description: String,
-    context: Vec<ActionCategory>,
+    context: Vec<String>,
     tags: Vec<String>,
     project: Option<Project>,
     wait: Option<String>,
     due: Option<String>,
-    estimate: Estimate,
-    brainpower: Brainpower,
+    scheduled: Option<String>,
+    until: Option<String>,
+    recur: Option<String>,
+    priority: Option<String>,
+    depends: Vec<Uuid>,
+    annotations: Vec<String>,
+    udas: UDA,
+    /// Only set when chosen, otherwise a new task gets the default and an
+    /// existing one keeps what it has.
+    estimate: Option<u64>,
+    brainpower: Option<String>,
+    default_estimate: u64,
+    default_brainpower: String,
 }
 
 impl TaskBuilder {
-    pub fn new() -> Self {
+    /// A builder with the default brainpower and estimate from `categories`.
+    pub fn new(categories: &Categories) -> Self {
         Self {
+            uuid: Uuid::new_v4(),
             description: String::new(),
             context: Vec::new(),
             tags: Vec::new(),
             project: None,
             wait: None,
             due: None,
-            estimate: Estimate::Small,
-            brainpower: Brainpower::Medium,
+            scheduled: None,
+            until: None,
+            recur: None,
+            priority: None,
+            depends: Vec::new(),
+            annotations: Vec::new(),
+            udas: UDA::new(),
+            estimate: None,
+            brainpower: None,
+            default_estimate: categories.default_estimate,
+            default_brainpower: categories.default_brainpower.clone(),
+        }
+    }
+
+    /// A builder starting from what `task` already has: its description,
+    /// project, contexts, brainpower, estimate and dates.
+    pub fn from_task(categories: &Categories, task: &Task) -> Self {
+        let mut builder = Self::new(categories);
+        builder.with_description(task.description());
+
+        if let Some(project) = task.project() {
+            builder.with_project(Project::from(project.as_str()));
+        }
+
+        builder.with_context_tags(
+            task.tags()
+                .into_iter()
+                .flatten()
+                .filter(|t| t.starts_with('@')),
+        );
+
+        if let Some(UDAValue::Str(brain)) = task.uda().get("brain") {
+            builder.brainpower = Some(brain.clone());
         }
+        builder.estimate = match task.uda().get("est") {
+            Some(UDAValue::U64(est)) => Some(*est),
+            Some(UDAValue::F64(est)) => Some(*est as u64),
+            Some(UDAValue::Str(est)) => est.parse().ok(),
+            None => None,
+        };
+
+        if let Some(due) = task.due() {
+            builder.with_due(format_date(due));
+        }
+        if let Some(wait) = task.wait() {
+            builder.with_wait(format_date(wait));
+        }
+
+        builder
     }
 
     pub fn with_description<S: AsRef<str>>(&mut self, desc: S) -> &mut Self {
@@ -375,7 +558,17 @@ impl TaskBuilder {
     }
 
     pub fn with_contexts(&mut self, contexts: &[ActionCategory]) -> &mut Self {
-        self.context.extend_from_slice(contexts);
+        self.with_context_tags(contexts.iter().map(|c| c.tag()))
+    }
+
+    /// Like `with_contexts`, for when only the tags (e.g. `@work`) are known.
+    pub fn with_context_tags<I, S>(&mut self, tags: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.context
+            .extend(tags.into_iter().map(|t| t.as_ref().to_string()));
         self
     }
 
@@ -400,197 +593,492 @@ impl TaskBuilder {
         self
     }
 
+    /// `wait` can be any date format that taskwarrior accepts.
     pub fn with_wait<S: AsRef<str>>(&mut self, wait: S) -> &mut Self {
         self.wait = Some(wait.as_ref().into());
         self
     }
 
-    pub fn with_due<S: AsRef<str>>(&mut self, wait: S) -> &mut Self {
-        self.due = Some(wait.as_ref().into());
+    /// `due` can be any date format that taskwarrior accepts.
+    pub fn with_due<S: AsRef<str>>(&mut self, due: S) -> &mut Self {
+        self.due = Some(due.as_ref().into());
+        self
+    }
+
+    /// `scheduled` can be any date format that taskwarrior accepts.
+    pub fn with_scheduled<S: AsRef<str>>(&mut self, scheduled: S) -> &mut Self {
+        self.scheduled = Some(scheduled.as_ref().into());
+        self
+    }
+
+    /// `until` can be any date format that taskwarrior accepts.
+    pub fn with_until<S: AsRef<str>>(&mut self, until: S) -> &mut Self {
+        self.until = Some(until.as_ref().into());
+        self
+    }
+
+    /// Make this a recurring task, like `weekly` or `3d`. Taskwarrior requires
+    /// recurring tasks to also have a due date.
+    pub fn with_recurrence<S: AsRef<str>>(&mut self, recur: S) -> &mut Self {
+        self.recur = Some(recur.as_ref().into());
+        self
+    }
+
+    pub fn with_priority<S: AsRef<str>>(&mut self, priority: S) -> &mut Self {
+        self.priority = Some(priority.as_ref().into());
+        self
+    }
+
+    pub fn with_dependency(&mut self, uuid: Uuid) -> &mut Self {
+        self.depends.push(uuid);
+        self
+    }
+
+    pub fn with_annotation<S: AsRef<str>>(&mut self, annotation: S) -> &mut Self {
+        self.annotations.push(annotation.as_ref().into());
+        self
+    }
+
+    /// Set an arbitrary UDA. This will replace the `brain` and `est` values
+    /// if used for those keys.
+    pub fn with_uda<S: AsRef<str>>(&mut self, key: S, value: UDAValue) -> &mut Self {
+        self.udas.insert(key.as_ref().into(), value);
+        self
+    }
+
+    /// Use the default brainpower and estimate from a project's policy.
+    pub fn with_policy(&mut self, policy: &ProjectPolicy) -> &mut Self {
+        if let Some(ref brainpower) = policy.brainpower {
+            self.default_brainpower = brainpower.clone();
+        }
+        if let Some(estimate) = policy.estimate {
+            self.default_estimate = estimate;
+        }
+        self
+    }
+
+    /// Drop the contexts set so far, to choose them again.
+    pub fn clear_contexts(&mut self) -> &mut Self {
+        self.context.clear();
         self
     }
 
-    pub fn with_estimate(&mut self, estimate: Estimate) -> &mut Self {
-        self.estimate = estimate;
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    /// The context tags the task will get.
+    pub fn contexts(&self) -> &[String] {
+        &self.context
+    }
+
+    /// The due date expression the task will get.
+    pub fn due(&self) -> Option<&str> {
+        self.due.as_deref()
+    }
+
+    /// The `uda` of the brainpower level the task will get.
+    pub fn brainpower(&self) -> &str {
+        self.brainpower
+            .as_deref()
+            .unwrap_or(&self.default_brainpower)
+    }
+
+    /// The estimate, in minutes, the task will get.
+    pub fn estimate(&self) -> u64 {
+        self.estimate.unwrap_or(self.default_estimate)
+    }
+
+    pub fn with_estimate(&mut self, estimate: &Estimate) -> &mut Self {
+        self.estimate = Some(estimate.into());
         self
     }
 
-    pub fn with_brainpower(&mut self, brainpower: Brainpower) -> &mut Self {
-        self.brainpower = brainpower;
+    pub fn with_brainpower(&mut self, brainpower: &Brainpower) -> &mut Self {
+        self.brainpower = Some(brainpower.uda().into());
         self
     }
 
     pub fn build(&self) -> Result<Task> {
-        let output = Command::new("task")
-            .args(self.args())
-            .output()
+        let records = self.to_import()?;
+        let mut json = String::new();
+        for record in &records {
+            json.push_str(&serde_json::to_string(record)?);
+            json.push('\n');
+        }
+
+        let mut child = task_command()
+            .arg("rc.confirmation=off")
+            .arg("import")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
             .with_context(|| "Attempting to create a task via TaskBuilder")?;
 
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("Could not write to task import"))?
+            .write_all(json.as_bytes())?;
+
+        let output = child.wait_with_output()?;
+        let parsed = TaskOutput::parse(
+            &String::from_utf8_lossy(&output.stdout),
+            &String::from_utf8_lossy(&output.stderr),
+        );
+        let args = vec!["import".to_string()];
+
         if !output.status.success() {
-            bail!("Failed to create task: {:?}", output);
+            return Err(TaskError::CommandFailed {
+                args,
+                status: output.status,
+                output: parsed,
+            })
+            .with_context(|| "Failed to create task");
+        }
+
+        if parsed.imported.len() != records.len() || !parsed.imported.contains(&self.uuid) {
+            return Err(TaskError::Unexpected {
+                args,
+                output: parsed,
+            })
+            .with_context(|| format!("Expected to import {} task(s)", records.len()));
         }
 
-        let out = String::from_utf8(output.clone().stdout)?;
-        let id = new_task_parser(&out)
-            .with_context(|| format!("Attempting to read newly created command id {:?}", output))?;
-        load_task(id)
+        load_task_from_uuid(&self.uuid.to_string())
     }
 
-    pub fn args(&self) -> Vec<String> {
-        let mut args = vec![
-            "add".into(),
-            format!("brain:{}", self.brainpower.uda()),
-            format!("est:{}", u64::from(self.estimate)),
-        ];
+    /// Apply everything set on the builder to an existing `task` rather than
+    /// creating a new one, so it keeps its uuid, entry date, annotations and
+    /// UDAs.
+    pub fn modify(&self, task: &Task) -> Result<Task> {
+        let mut args = vec![String::from("modify")];
+
+        if !self.description.is_empty() {
+            args.push(format!("description:{}", self.description));
+        }
 
         if let Some(ref proj) = self.project {
-            args.push(format!("proj:{}", proj));
+            args.push(format!("project:{}", proj));
+        }
+
+        args.extend(
+            self.context
+                .iter()
+                .chain(self.tags.iter())
+                .map(|t| format!("+{}", t)),
+        );
+
+        // contexts that were chosen replace the ones the task had
+        if !self.context.is_empty() {
+            args.extend(
+                task.tags()
+                    .into_iter()
+                    .flatten()
+                    .filter(|t| t.starts_with('@') && !self.context.contains(t))
+                    .map(|t| format!("-{}", t)),
+            );
         }
 
-        for c in &self.context {
-            args.push(format!("+{}", c.tag()))
+        for (key, value) in [
+            ("wait", &self.wait),
+            ("due", &self.due),
+            ("scheduled", &self.scheduled),
+            ("until", &self.until),
+            ("recur", &self.recur),
+            ("priority", &self.priority),
+        ] {
+            if let Some(value) = value {
+                args.push(format!("{}:{}", key, value));
+            }
         }
 
-        for t in &self.tags {
-            args.push(format!("+{}", t))
+        if !self.depends.is_empty() {
+            let depends: Vec<_> = self.depends.iter().map(|d| d.to_string()).collect();
+            args.push(format!("depends:{}", depends.join(",")));
         }
 
-        if let Some(ref wait) = self.wait {
-            args.push(format!("wait:{}", wait));
+        if let Some(ref brainpower) = self.brainpower {
+            args.push(format!("brain:{}", brainpower));
+        }
+        if let Some(estimate) = self.estimate {
+            args.push(format!("est:{}", estimate));
         }
 
-        if let Some(ref due) = self.due {
-            args.push(format!("due:{}", due));
+        // later values win, same as in the json
+        for (key, value) in &self.udas {
+            let value = match value {
+                UDAValue::Str(s) => s.clone(),
+                UDAValue::U64(n) => n.to_string(),
+                UDAValue::F64(n) => n.to_string(),
+            };
+            args.push(format!("{}:{}", key, value));
         }
 
-        args.push(self.description.clone());
+        task.execute(args)
+            .with_context(|| format!("Failed to modify task {}", task.uuid()))?;
+
+        for annotation in &self.annotations {
+            task.annotate(annotation)?;
+        }
 
-        args
+        load_task_from_uuid(&task.uuid().to_string())
+    }
+
+    /// The task as the json `task import` expects.
+    ///
+    /// Import does not understand date expressions like `+1d` or `fri`, so
+    /// those are resolved by taskwarrior ahead of time.
+    pub fn to_json(&self) -> Result<Value> {
+        let now = Utc::now().naive_utc();
+        let mut obj = Map::new();
+
+        obj.insert("uuid".into(), json!(self.uuid.to_string()));
+        obj.insert("description".into(), json!(self.description));
+        obj.insert("entry".into(), json!(format_date(&now)));
+        obj.insert("brain".into(), json!(self.brainpower()));
+        obj.insert("est".into(), json!(self.estimate()));
+
+        if let Some(ref proj) = self.project {
+            obj.insert("project".into(), json!(proj.to_string()));
+        }
+
+        let tags: Vec<_> = self
+            .context
+            .iter()
+            .cloned()
+            .chain(self.tags.iter().cloned())
+            .collect();
+        if !tags.is_empty() {
+            obj.insert("tags".into(), json!(tags));
+        }
+
+        for (key, expr) in [
+            ("wait", &self.wait),
+            ("due", &self.due),
+            ("scheduled", &self.scheduled),
+            ("until", &self.until),
+        ] {
+            if let Some(expr) = expr {
+                obj.insert(key.into(), json!(format_date(&resolve_date(expr)?)));
+            }
+        }
+
+        if let Some(ref recur) = self.recur {
+            if self.due.is_none() {
+                bail!("Recurring tasks require a due date");
+            }
+            obj.insert("recur".into(), json!(recur));
+        }
+        obj.insert("status".into(), json!("pending"));
+
+        if let Some(ref priority) = self.priority {
+            obj.insert("priority".into(), json!(priority));
+        }
+
+        if !self.depends.is_empty() {
+            let depends: Vec<_> = self.depends.iter().map(|d| d.to_string()).collect();
+            obj.insert("depends".into(), json!(depends));
+        }
+
+        if !self.annotations.is_empty() {
+            // taskwarrior keys annotations by their entry time, so they each
+            // need a distinct one
+            let annotations: Vec<_> = self
+                .annotations
+                .iter()
+                .enumerate()
+                .map(|(i, a)| {
+                    json!({
+                        "entry": format_date(&(now + Duration::seconds(i as i64))),
+                        "description": a,
+                    })
+                })
+                .collect();
+            obj.insert("annotations".into(), json!(annotations));
+        }
+
+        for (key, value) in &self.udas {
+            obj.insert(key.clone(), serde_json::to_value(value)?);
+        }
+
+        Ok(Value::Object(obj))
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum ActionCategory {
-    Agenda,
-    Anywhere,
-    Computer,
-    Errands,
-    Home,
-    Phone,
-    ReadAndReview,
-    Work,
+impl TaskBuilder {
+    /// The records to import: the task itself, and for a recurring task the
+    /// template it's the first instance of, so `build` still hands back a
+    /// pending task.
+    fn to_import(&self) -> Result<Vec<Value>> {
+        let mut task = self.to_json()?;
+
+        if self.recur.is_none() {
+            return Ok(vec![task]);
+        }
+
+        // the template's mask tracks which instances exist, each instance
+        // knows its place in it through imask
+        let parent = Uuid::new_v4().to_string();
+        let mut template = task.clone();
+        template["uuid"] = json!(parent);
+        template["status"] = json!("recurring");
+        template["mask"] = json!("-");
+        task["parent"] = json!(parent);
+        task["imask"] = json!(0);
+
+        Ok(vec![template, task])
+    }
+}
+
+/// A `task` invocation. Tests swap in a fake binary.
+fn task_command() -> Command {
+    #[cfg(test)]
+    if let Some(bin) = crate::testing::task_binary() {
+        return Command::new(bin);
+    }
+
+    Command::new("task")
+}
+
+fn format_date(date: &NaiveDateTime) -> String {
+    date.format(TASKWARRIOR_DATETIME_TEMPLATE).to_string()
+}
+
+/// Have taskwarrior evaluate a date expression, returning the result in utc.
+///
+/// Like taskwarrior, a bare duration like `+1d` is taken relative to now.
+pub fn resolve_date(expr: &str) -> Result<NaiveDateTime> {
+    let mut args = vec!["rc.verbose=nothing".to_string(), "calc".into()];
+
+    let trimmed = expr.trim();
+
+    if let Some(duration) = trimmed.strip_prefix('+') {
+        args.extend(["now".into(), "+".into(), duration.into()]);
+    } else if let Some(duration) = trimmed.strip_prefix('-') {
+        args.extend(["now".into(), "-".into(), duration.into()]);
+    } else if is_duration(trimmed) {
+        // calc would answer with the duration itself
+        args.extend(["now".into(), "+".into(), trimmed.into()]);
+    } else {
+        args.push(trimmed.into());
+    }
+
+    let output = task_command()
+        .args(&args)
+        .output()
+        .with_context(|| format!("Attempting to evaluate date '{}'", expr))?;
+
+    if !output.status.success() {
+        bail!("Could not evaluate date '{}': {:?}", expr, output);
+    }
+
+    let out = String::from_utf8(output.stdout)?;
+    let local = NaiveDateTime::parse_from_str(out.trim(), "%Y-%m-%dT%H:%M:%S")
+        .with_context(|| format!("'{}' is not a valid date ({})", expr, out.trim()))?;
+
+    Ok(Local
+        .from_local_datetime(&local)
+        .earliest()
+        .map(|d| d.naive_utc())
+        .unwrap_or(local))
+}
+
+/// Whether `expr` is a duration like `3d` or `2weeks`, rather than a date.
+fn is_duration(expr: &str) -> bool {
+    const UNITS: &[&str] = &[
+        "s", "sec", "secs", "second", "seconds", "min", "mins", "minute", "minutes", "h", "hr",
+        "hrs", "hour", "hours", "d", "day", "days", "w", "wk", "wks", "week", "weeks", "mo", "mos",
+        "month", "months", "q", "qtr", "qtrs", "quarter", "quarters", "y", "yr", "yrs", "year",
+        "years",
+    ];
+
+    let unit = expr.trim_start_matches(|c: char| c.is_ascii_digit());
+    unit.len() < expr.len() && UNITS.contains(&unit)
+}
+
+/// A context a task can be done in, like `@work` or `@phone`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ActionCategory {
+    pub label: String,
+    pub tag: String,
 }
 
 impl ActionCategory {
-    pub fn list() -> Vec<Self> {
-        vec![
-            Self::Agenda,
-            Self::Anywhere,
-            Self::Computer,
-            Self::Errands,
-            Self::Home,
-            Self::Phone,
-            Self::ReadAndReview,
-            Self::Work,
-        ]
+    pub fn new<S: Into<String>>(label: S, tag: S) -> Self {
+        Self {
+            label: label.into(),
+            tag: tag.into(),
+        }
     }
 
     pub fn tag(&self) -> &str {
-        match self {
-            Self::Agenda => "@agenda",
-            Self::Anywhere => "@anywhere",
-            Self::Computer => "@computer",
-            Self::Errands => "@errands",
-            Self::Home => "@home",
-            Self::Phone => "@phone",
-            Self::ReadAndReview => "@rnr",
-            Self::Work => "@work",
-        }
+        &self.tag
     }
 }
 
 impl fmt::Display for ActionCategory {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Agenda => write!(f, "Agenda"),
-            Self::Anywhere => write!(f, "Anywhere"),
-            Self::Computer => write!(f, "Computer"),
-            Self::Errands => write!(f, "Errands"),
-            Self::Home => write!(f, "Home"),
-            Self::Phone => write!(f, "Phone"),
-            Self::ReadAndReview => write!(f, "ReadAndReview"),
-            Self::Work => write!(f, "Work"),
-        }
+        write!(f, "{}", self.label)
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum Brainpower {
-    Low,
-    Medium,
-    High,
+/// How much focus a task needs, stored in the `brain` UDA.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Brainpower {
+    pub label: String,
+    pub uda: String,
 }
 
 impl Brainpower {
-    pub fn list() -> Vec<Self> {
-        vec![Self::Low, Self::Medium, Self::High]
+    pub fn new<S: Into<String>>(label: S, uda: S) -> Self {
+        Self {
+            label: label.into(),
+            uda: uda.into(),
+        }
     }
 
     pub fn uda(&self) -> &str {
-        match self {
-            Self::Low => "L",
-            Self::Medium => "M",
-            Self::High => "H",
-        }
+        &self.uda
     }
 }
 
 impl fmt::Display for Brainpower {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Low => write!(f, "Low"),
-            Self::Medium => write!(f, "Medium"),
-            Self::High => write!(f, "High"),
-        }
+        write!(f, "{}", self.label)
     }
 }
 
-#[repr(u64)]
-#[derive(Debug, Clone, Copy)]
-pub enum Estimate {
-    Small = 30,
-    Medium = 360,
-    Large = 1440,
-    XLarge = 2880,
-    Unknown = 9999,
+/// A rough bucket for how long a task will take, stored in minutes in the
+/// `est` UDA.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Estimate {
+    pub label: String,
+    pub minutes: u64,
 }
 
 impl Estimate {
-    pub fn list() -> Vec<Self> {
-        vec![
-            Self::Small,
-            Self::Medium,
-            Self::Large,
-            Self::XLarge,
-            Self::Unknown,
-        ]
+    pub fn new<S: Into<String>>(label: S, minutes: u64) -> Self {
+        Self {
+            label: label.into(),
+            minutes,
+        }
     }
 }
 
 impl fmt::Display for Estimate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Small => write!(f, "Thirty minutes"),
-            Self::Medium => write!(f, "Six hours"),
-            Self::Large => write!(f, "One day"),
-            Self::XLarge => write!(f, "Two days"),
-            Self::Unknown => write!(f, "More than two days (not well understood)"),
-        }
+        write!(f, "{}", self.label)
     }
 }
 
-impl From<Estimate> for u64 {
-    fn from(e: Estimate) -> Self {
-        e as u64
+impl From<&Estimate> for u64 {
+    fn from(e: &Estimate) -> Self {
+        e.minutes
     }
 }
@@ -1,29 +1,44 @@
-use std::{collections::HashSet, fmt};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    rc::Rc,
+    str::FromStr,
+};
 
-use chrono::Local;
+use anyhow::{bail, Context, Error, Result};
+use chrono::{Duration, Local, NaiveDateTime};
+use clap::ArgEnum;
 use comfy_table::{presets::NOTHING, Cell, Color, ContentArrangement, Table};
-use console::style;
+use serde::Deserialize;
 use task_hookrs::{task::Task, uda::UDAValue};
+use uuid::Uuid;
 
-use crate::task::Processable;
+use crate::dates::{format_date, local};
+use crate::task::{load_tasks, Processable};
+use crate::theme::{colors_enabled, paint, theme};
 
+/// Rows are kept in the order they're added unless sort keys are given, and
+/// are only laid out once the table is displayed.
 #[derive(Debug)]
-pub struct TaskTable<'a> {
-    columns: &'a Vec<Field>,
+pub struct TaskTable<'a, C: TaskAttr = Field> {
+    columns: &'a [C],
     desc_color: Color,
-    table: Table,
+    sort: Vec<SortKey>,
+    group: Option<GroupBy>,
+    rows: Vec<Task>,
 }
 
-impl<'a> TaskTable<'a> {
-    pub fn new(columns: &'a Vec<Field>) -> Self {
-        let mut table = Table::new();
-        table.load_preset(NOTHING);
-        table.set_content_arrangement(ContentArrangement::Dynamic);
-
+impl<'a, C: TaskAttr> TaskTable<'a, C> {
+    pub fn new(columns: &'a [C]) -> Self {
         Self {
             columns,
-            desc_color: Color::White,
-            table,
+            desc_color: theme().description,
+            sort: Vec::new(),
+            group: None,
+            rows: Vec::new(),
         }
     }
 
@@ -32,20 +47,63 @@ impl<'a> TaskTable<'a> {
         self
     }
 
+    pub fn sort_by(mut self, keys: &[SortKey]) -> Self {
+        self.sort = keys.to_vec();
+        self
+    }
+
+    /// Split the rows into groups, each under a header with its count.
+    pub fn group_by(mut self, group: Option<GroupBy>) -> Self {
+        self.group = group;
+        self
+    }
+
     pub fn add_row(&mut self, task: &Task) {
-        let mut row = Vec::new();
-        for col in self.columns {
-            row.push(
-                Cell::new(col.get_value(task)).fg(col.get_color(task).unwrap_or(self.desc_color)),
-            )
+        self.rows.push(task.clone());
+    }
+
+    fn table(&self, tasks: &[&Task]) -> Table {
+        let mut table = Table::new();
+        table.load_preset(NOTHING);
+        table.set_content_arrangement(ContentArrangement::Dynamic);
+
+        for task in tasks {
+            let mut row = Vec::new();
+            for col in self.columns {
+                let mut cell = Cell::new(col.get_value(task));
+                if colors_enabled() {
+                    cell = cell.fg(col.get_color(task).unwrap_or(self.desc_color));
+                }
+                row.push(cell)
+            }
+            table.add_row(row);
         }
-        self.table.add_row(row);
+
+        table
     }
 }
 
-impl<'a> fmt::Display for TaskTable<'a> {
+impl<'a, C: TaskAttr> fmt::Display for TaskTable<'a, C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.table)
+        let mut rows = self.rows.clone();
+        sort_tasks(&mut rows, &self.sort);
+        let rows: Vec<_> = rows.iter().collect();
+
+        let group = match self.group {
+            Some(group) => group,
+            None => return write!(f, "{}", self.table(&rows)),
+        };
+
+        for (i, (label, tasks)) in group_tasks(&rows, group).into_iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            let header = format!("{} ({})", label, tasks.len());
+            writeln!(f, "  {}", paint(theme().header, header))?;
+            write!(f, "{}", self.table(&tasks))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -75,12 +133,12 @@ impl<'a> TaskDetail<'a> {
     }
 
     pub fn custom_row(&mut self, label: &str, value: &str) -> &mut Self {
-        self.table.add_row(vec![
-            Cell::new(label)
-                .fg(Color::DarkGrey)
-                .set_alignment(comfy_table::CellAlignment::Right),
-            Cell::new(value),
-        ]);
+        let mut label = Cell::new(label).set_alignment(comfy_table::CellAlignment::Right);
+        if colors_enabled() {
+            label = label.fg(theme().muted);
+        }
+
+        self.table.add_row(vec![label, Cell::new(value)]);
         self
     }
 
@@ -98,9 +156,14 @@ impl<'a> fmt::Display for TaskDetail<'a> {
 pub trait TaskAttr {
     fn name(&self) -> &str;
     fn get_value(&self, task: &Task) -> String;
+
+    /// `None` means the column takes the table's description color.
+    fn get_color(&self, _task: &Task) -> Option<Color> {
+        Some(theme().muted)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Field {
     Description,
     AnnotatedDescription,
@@ -110,10 +173,51 @@ pub enum Field {
     Next,
     Project,
     Waiting,
+    Tags,
+    Urgency,
+    Estimate,
+    Brainpower,
+    Age,
+    Modified,
+    Scheduled,
+    Priority,
+    /// Whether the task is blocked by, or blocking, other pending tasks.
+    Depends,
+    ShortUuid,
+}
+
+impl FromStr for Field {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "description" => Self::Description,
+            "annotated_description" => Self::AnnotatedDescription,
+            "annotations" => Self::Annotations,
+            "due" => Self::Due,
+            "id" => Self::ID,
+            "next" => Self::Next,
+            "project" => Self::Project,
+            "wait" | "waiting" => Self::Waiting,
+            "tags" => Self::Tags,
+            "urgency" => Self::Urgency,
+            "est" | "estimate" => Self::Estimate,
+            "brain" | "brainpower" => Self::Brainpower,
+            "age" | "entry.age" => Self::Age,
+            "modified" => Self::Modified,
+            "scheduled" => Self::Scheduled,
+            "priority" => Self::Priority,
+            "depends" | "blocked" | "blocking" => Self::Depends,
+            "uuid" | "uuid.short" => Self::ShortUuid,
+            _ => bail!("Unknown field '{}'", s),
+        })
+    }
 }
 
-impl Field {
-    pub fn get_color(&self, task: &Task) -> Option<Color> {
+impl TaskAttr for Field {
+    fn get_color(&self, task: &Task) -> Option<Color> {
+        let theme = theme();
+
         match self {
             Self::Description => None,
             Self::AnnotatedDescription => None,
@@ -125,21 +229,51 @@ impl Field {
                 // won't matter anyway
                 let today = Local::now().naive_local().date();
                 Some(match task.due() {
-                    Some(ref date) if date.date() <= today => Color::DarkRed,
-                    _ => Color::DarkGrey,
+                    Some(date) if local(**date).date() <= today => theme.overdue,
+                    _ => theme.muted,
                 })
             }
-            Self::Next => Some(Color::Red),
+            Self::Next => Some(theme.next),
             Self::Waiting => Some(match task.wait() {
-                Some(_) => Color::DarkGrey,
-                None => Color::Red,
+                Some(_) => theme.muted,
+                None => theme.next,
+            }),
+            Self::Tags => Some(theme.tags),
+            Self::Urgency => Some(match task.urgency() {
+                Some(u) if *u >= 10.0 => theme.urgent,
+                Some(u) if *u >= 5.0 => theme.warning,
+                _ => theme.muted,
+            }),
+            Self::Brainpower => Some(match brainpower(task) {
+                Some("H") => theme.warning,
+                _ => theme.muted,
+            }),
+            Self::Scheduled => {
+                let today = Local::now().naive_local().date();
+                Some(match task.scheduled() {
+                    Some(date) if local(**date).date() <= today => theme.next,
+                    _ => theme.muted,
+                })
+            }
+            Self::Priority => Some(match task.priority().map(String::as_str) {
+                Some("H") => theme.urgent,
+                Some("M") => theme.warning,
+                _ => theme.muted,
             }),
-            _ => Some(Color::DarkGrey),
+            Self::Depends => {
+                let deps = Dependencies::pending();
+                Some(if deps.is_blocked(task) {
+                    theme.waiting
+                } else if deps.is_blocking(task) {
+                    theme.urgent
+                } else {
+                    theme.muted
+                })
+            }
+            _ => Some(theme.muted),
         }
     }
-}
 
-impl TaskAttr for Field {
     fn get_value(&self, task: &Task) -> String {
         match self {
             Self::Description => task.description().into(),
@@ -148,7 +282,7 @@ impl TaskAttr for Field {
                 let mut ann = vec![];
                 if let Some(annotations) = task.annotations() {
                     for a in annotations {
-                        ann.push(format!("{} {}", a.entry().format("%F"), a.description()));
+                        ann.push(format!("{} {}", format_date(**a.entry()), a.description()));
                     }
                 }
                 ann.join("\n")
@@ -156,11 +290,10 @@ impl TaskAttr for Field {
             Self::Due => {
                 let today = Local::now().naive_local().date();
                 match task.due() {
-                    Some(ref date) if date.date() == today => String::from("Today"),
-                    Some(ref date) if date.date() < today => {
-                        format!("Overdue ({})", date.format("%F"))
+                    Some(date) if local(**date).date() < today => {
+                        format!("Overdue ({})", format_date(**date))
                     }
-                    Some(ref date) => date.format("%F %a").to_string(),
+                    Some(date) => format_date(**date),
                     None => String::from("None"),
                 }
             }
@@ -168,9 +301,42 @@ impl TaskAttr for Field {
             Self::Next => if task.is_next() { "N" } else { "" }.into(),
             Self::Project => task.project().unwrap_or(&String::new()).into(),
             Self::Waiting => match task.wait() {
-                Some(date) => date.format("%F %a").to_string(),
+                Some(date) => format_date(**date),
                 None => String::from("Ready"),
             },
+            Self::Tags => {
+                let mut tags = task.tags().cloned().unwrap_or_default();
+                tags.sort();
+                tags.join(" ")
+            }
+            Self::Urgency => task
+                .urgency()
+                .map(|u| format!("{:.1}", u))
+                .unwrap_or_default(),
+            Self::Estimate => estimate(task).map(humanize_minutes).unwrap_or_default(),
+            Self::Brainpower => brainpower(task).unwrap_or_default().into(),
+            Self::Age => humanize(Local::now().naive_local() - local(**task.entry())),
+            Self::Modified => task
+                .modified()
+                .map(|date| format_date(**date))
+                .unwrap_or_default(),
+            Self::Scheduled => task
+                .scheduled()
+                .map(|date| format_date(**date))
+                .unwrap_or_default(),
+            Self::Priority => task.priority().cloned().unwrap_or_default(),
+            Self::Depends => {
+                let deps = Dependencies::pending();
+                let mut state = vec![];
+                if deps.is_blocked(task) {
+                    state.push("blocked");
+                }
+                if deps.is_blocking(task) {
+                    state.push("blocking");
+                }
+                state.join(", ")
+            }
+            Self::ShortUuid => task.uuid().to_string()[..8].into(),
         }
     }
 
@@ -184,88 +350,825 @@ impl TaskAttr for Field {
             Self::Next => "Next label",
             Self::Project => "Project",
             Self::Waiting => "Waiting",
+            Self::Tags => "Tags",
+            Self::Urgency => "Urg",
+            Self::Estimate => "Est",
+            Self::Brainpower => "Brain",
+            Self::Age => "Age",
+            Self::Modified => "Modified",
+            Self::Scheduled => "Scheduled",
+            Self::Priority => "Pri",
+            Self::Depends => "Deps",
+            Self::ShortUuid => "UUID",
+        }
+    }
+}
+
+/// The `est` UDA, in minutes. Older tasks may have it stored as a string.
+fn estimate(task: &Task) -> Option<u64> {
+    match task.uda().get("est")? {
+        UDAValue::U64(minutes) => Some(*minutes),
+        UDAValue::F64(minutes) => Some(*minutes as u64),
+        UDAValue::Str(minutes) => minutes.parse().ok(),
+    }
+}
+
+pub(crate) fn brainpower(task: &Task) -> Option<&str> {
+    match task.uda().get("brain")? {
+        UDAValue::Str(brain) => Some(brain),
+        _ => None,
+    }
+}
+
+/// A rough, taskwarrior style rendering of a duration, like `3d` or `5mo`.
+fn humanize(duration: Duration) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+
+    match duration.num_seconds().max(0) {
+        s if s < MINUTE => format!("{}s", s),
+        s if s < HOUR => format!("{}min", s / MINUTE),
+        s if s < DAY => format!("{}h", s / HOUR),
+        s if s < 14 * DAY => format!("{}d", s / DAY),
+        s if s < 90 * DAY => format!("{}w", s / (7 * DAY)),
+        s if s < 365 * DAY => format!("{}mo", s / (30 * DAY)),
+        s => format!("{}y", s / (365 * DAY)),
+    }
+}
+
+/// Estimates are small and rounded, so keep the precision an hour and a half
+/// deserves.
+fn humanize_minutes(minutes: u64) -> String {
+    match minutes {
+        m if m < 60 => format!("{}min", m),
+        m if m < 600 && m % 60 != 0 => format!("{:.1}h", m as f64 / 60.0),
+        m if m < 24 * 60 => format!("{}h", m / 60),
+        m => format!("{}d", m / (24 * 60)),
+    }
+}
+
+/// Which pending tasks are held up by, or holding up, other pending tasks.
+///
+/// Taskwarrior doesn't export its `BLOCKED` and `BLOCKING` virtual tags, so
+/// this is worked out from the pending tasks' `depends`, loaded once per
+/// thread the first time a table needs it.
+#[derive(Debug, Default)]
+struct Dependencies {
+    blocked: HashSet<Uuid>,
+    blocking: HashSet<Uuid>,
+}
+
+impl Dependencies {
+    fn new(pending: &[Task]) -> Self {
+        let uuids: HashSet<_> = pending.iter().map(|t| *t.uuid()).collect();
+        let mut deps = Self::default();
+
+        for task in pending {
+            for dep in task.depends().into_iter().flatten() {
+                if uuids.contains(dep) {
+                    deps.blocked.insert(*task.uuid());
+                    deps.blocking.insert(*dep);
+                }
+            }
+        }
+
+        deps
+    }
+
+    fn pending() -> Rc<Self> {
+        thread_local! {
+            static PENDING: RefCell<Option<Rc<Dependencies>>> = const { RefCell::new(None) };
         }
+
+        PENDING.with(|pending| {
+            pending
+                .borrow_mut()
+                .get_or_insert_with(|| {
+                    // a column isn't worth failing the whole table over
+                    let tasks = load_tasks("status:pending").unwrap_or_default();
+                    Rc::new(Self::new(&tasks))
+                })
+                .clone()
+        })
+    }
+
+    fn is_blocked(&self, task: &Task) -> bool {
+        self.blocked.contains(task.uuid())
+    }
+
+    fn is_blocking(&self, task: &Task) -> bool {
+        self.blocking.contains(task.uuid())
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum UDA {
-    GithubTitle,
-    GithubBody,
-    GithubUser,
-    GithubUrl,
-    GithubState,
+/// How a UDA's values should be read and shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UdaType {
+    #[default]
+    String,
+    Numeric,
+    /// Stored by taskwarrior like `20261001T090000Z`.
+    Date,
+    /// Stored by taskwarrior as an ISO 8601 duration like `PT1H30M`.
+    Duration,
+}
+
+/// A user defined attribute, either one of the built-in github and jira ones
+/// or declared in config:
+///
+/// ```toml
+/// [[udas]]
+/// key = "points"
+/// label = "Pts"
+/// type = "numeric"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct UDA {
+    pub key: String,
+    /// The column header, the key if unset.
+    pub label: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: UdaType,
 }
 
 impl UDA {
-    pub fn uda_key(&self) -> &str {
-        match self {
-            Self::GithubTitle => "githubtitle",
-            Self::GithubBody => "githubbody",
-            Self::GithubUser => "githubuser",
-            Self::GithubUrl => "githuburl",
-            Self::GithubState => "githubstate",
+    /// A string UDA with no label, as named in a report's columns before
+    /// the config has been consulted.
+    pub fn new<S: Into<String>>(key: S) -> Self {
+        Self {
+            key: key.into(),
+            label: None,
+            kind: UdaType::String,
+        }
+    }
+
+    fn builtin(key: &str, label: &str) -> Self {
+        Self {
+            key: key.into(),
+            label: Some(label.into()),
+            kind: UdaType::String,
+        }
+    }
+
+    pub fn github_title() -> Self {
+        Self::builtin("githubtitle", "Title")
+    }
+
+    pub fn github_body() -> Self {
+        Self::builtin("githubbody", "Body")
+    }
+
+    pub fn github_user() -> Self {
+        Self::builtin("githubuser", "User")
+    }
+
+    pub fn github_url() -> Self {
+        Self::builtin("githuburl", "URL")
+    }
+
+    pub fn github_state() -> Self {
+        Self::builtin("githubstate", "State")
+    }
+
+    pub fn jira_url() -> Self {
+        Self::builtin("jiraurl", "Jira URL")
+    }
+
+    pub fn jira_summary() -> Self {
+        Self::builtin("jirasummary", "Summary")
+    }
+
+    pub fn jira_description() -> Self {
+        Self::builtin("jiradescription", "Jira description")
+    }
+
+    /// Who a delegated task is waiting on. Needs `uda.delegatee.type=string`
+    /// in your taskrc.
+    pub fn delegatee() -> Self {
+        Self::builtin("delegatee", "Delegatee")
+    }
+
+    /// How a task was delegated, like `slack` or `email`. Needs
+    /// `uda.delegation.type=string` in your taskrc.
+    pub fn delegation() -> Self {
+        Self::builtin("delegation", "Via")
+    }
+
+    /// The UDAs bugwarrior sets up for the github and jira services, and the
+    /// ones inbox delegation sets.
+    pub fn builtins() -> Vec<UDA> {
+        vec![
+            Self::github_title(),
+            Self::github_body(),
+            Self::github_user(),
+            Self::github_url(),
+            Self::github_state(),
+            Self::jira_url(),
+            Self::jira_summary(),
+            Self::jira_description(),
+            Self::delegatee(),
+            Self::delegation(),
+        ]
+    }
+
+    pub fn raw_value<'t>(&self, task: &'t Task) -> Option<&'t UDAValue> {
+        task.uda().get(&self.key)
+    }
+
+    /// The value as stored, for string UDAs holding urls and the like.
+    pub fn get_str<'t>(&self, task: &'t Task) -> Option<&'t str> {
+        match self.raw_value(task)? {
+            UDAValue::Str(value) if !value.is_empty() => Some(value),
+            _ => None,
         }
     }
 
+    /// The value as stored, without any formatting for its type.
     pub fn get_raw_value(&self, task: &Task) -> String {
-        // TODO: for now only support string values - MCL - 2021-10-24
-        if let Some(UDAValue::Str(value)) = task.uda().get(self.uda_key()) {
-            value.clone()
-        } else {
-            String::new()
+        match self.raw_value(task) {
+            Some(UDAValue::Str(value)) => value.clone(),
+            Some(UDAValue::U64(value)) => value.to_string(),
+            Some(UDAValue::F64(value)) => value.to_string(),
+            None => String::new(),
         }
     }
+
+    /// Turn a value given on the command line into one of the right type.
+    pub fn parse_value(&self, value: &str) -> Result<UDAValue> {
+        Ok(match self.kind {
+            UdaType::Numeric => match value.parse::<u64>() {
+                Ok(n) => UDAValue::U64(n),
+                Err(_) => UDAValue::F64(
+                    value
+                        .parse()
+                        .with_context(|| format!("UDA {} must be a number", self.key))?,
+                ),
+            },
+            _ => UDAValue::Str(value.into()),
+        })
+    }
 }
 
 impl TaskAttr for UDA {
+    fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(&self.key)
+    }
+
+    fn get_value(&self, task: &Task) -> String {
+        let value = match self.raw_value(task) {
+            Some(value) => value,
+            None => return String::new(),
+        };
+
+        // anything that doesn't look like its declared type is shown as is
+        let formatted = match (self.kind, value) {
+            (UdaType::String, _) => None,
+            (UdaType::Numeric, UDAValue::U64(n)) => Some(n.to_string()),
+            (UdaType::Numeric, UDAValue::F64(n)) => Some(format_number(*n)),
+            (UdaType::Numeric, UDAValue::Str(s)) => s.parse().ok().map(format_number),
+            (UdaType::Date, UDAValue::Str(s)) => NaiveDateTime::parse_from_str(s, "%Y%m%dT%H%M%SZ")
+                .ok()
+                .map(format_date),
+            (UdaType::Duration, UDAValue::U64(secs)) => Some(format_duration(*secs as i64)),
+            (UdaType::Duration, UDAValue::Str(s)) => parse_duration(s).map(format_duration),
+            _ => None,
+        };
+
+        formatted.unwrap_or_else(|| self.get_raw_value(task))
+    }
+}
+
+/// At most two decimal places, without trailing zeroes.
+fn format_number(n: f64) -> String {
+    let s = format!("{:.2}", n);
+    s.trim_end_matches('0').trim_end_matches('.').into()
+}
+
+/// Seconds in an ISO 8601 duration like `P1DT2H30M`, with months and years
+/// taken as 30 and 365 days like taskwarrior does.
+fn parse_duration(s: &str) -> Option<i64> {
+    let mut rest = s.strip_prefix('P')?;
+    let mut secs = 0;
+    let mut time = false;
+
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix('T') {
+            time = true;
+            rest = r;
+            continue;
+        }
+
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let n: i64 = rest[..digits].parse().ok()?;
+        let unit = match (time, &rest[digits..digits + 1]) {
+            (false, "Y") => 365 * 86400,
+            (false, "M") => 30 * 86400,
+            (false, "W") => 7 * 86400,
+            (false, "D") => 86400,
+            (true, "H") => 3600,
+            (true, "M") => 60,
+            (true, "S") => 1,
+            _ => return None,
+        };
+        secs += n * unit;
+        rest = &rest[digits + 1..];
+    }
+
+    Some(secs)
+}
+
+/// Every non-zero unit, largest first, like `1d2h30min`.
+pub(crate) fn format_duration(secs: i64) -> String {
+    let units = [(86400, "d"), (3600, "h"), (60, "min"), (1, "s")];
+    let mut rest = secs.max(0);
+    let mut s = String::new();
+
+    for (size, unit) in units {
+        if rest >= size {
+            s += &format!("{}{}", rest / size, unit);
+            rest %= size;
+        }
+    }
+
+    if s.is_empty() {
+        s = String::from("0s");
+    }
+    s
+}
+
+/// Any column a table can show, as named in config like `due` or
+/// `githuburl`.
+///
+/// Anything that isn't a field is taken to be a UDA key, which the config
+/// swaps for the declared UDA once it's loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum Column {
+    Field(Field),
+    Uda(UDA),
+}
+
+impl FromStr for Column {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        if let Ok(field) = s.parse() {
+            return Ok(Self::Field(field));
+        }
+
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            bail!("Unknown column '{}'", s);
+        }
+
+        Ok(Self::Uda(UDA::new(s)))
+    }
+}
+
+impl TryFrom<String> for Column {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Field> for Column {
+    fn from(field: Field) -> Self {
+        Self::Field(field)
+    }
+}
+
+impl TaskAttr for Column {
     fn name(&self) -> &str {
         match self {
-            Self::GithubTitle => "Title",
-            Self::GithubBody => "Body",
-            Self::GithubUser => "User",
-            Self::GithubUrl => "URL",
-            Self::GithubState => "State",
+            Self::Field(f) => f.name(),
+            Self::Uda(u) => u.name(),
         }
     }
 
     fn get_value(&self, task: &Task) -> String {
-        self.get_raw_value(task)
+        match self {
+            Self::Field(f) => f.get_value(task),
+            Self::Uda(u) => u.get_value(task),
+        }
     }
+
+    fn get_color(&self, task: &Task) -> Option<Color> {
+        match self {
+            Self::Field(f) => f.get_color(task),
+            Self::Uda(u) => u.get_color(task),
+        }
+    }
+}
+
+/// Task attributes that can be sorted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortAttr {
+    Description,
+    Due,
+    Entry,
+    Estimate,
+    ID,
+    Modified,
+    Project,
+    Scheduled,
+    Urgency,
+    Wait,
 }
 
-/// Convenience method for displaying a vector of tasks as a table.
-pub fn display_table(tasks: &Vec<Task>, columns: &Vec<Field>, description_color: Color) {
-    let mut seen_uuids = HashSet::new();
-    display_unique_table(tasks, columns, description_color, &mut seen_uuids);
+/// A sort key written the way taskwarrior does, like `due+` or `urgency-`.
+/// Without a direction the sort is ascending.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SortKey {
+    attr: SortAttr,
+    descending: bool,
 }
 
-/// Convenience method for displaying a vector of tasks as a table.
-///
-/// Unlike `display_table`, this allows you to specify the seen uuid cache to
-/// use when determining uniqueness. Doing so lets you have a unique constraint
-/// across a _set_ of tables.
-pub fn display_unique_table(
-    tasks: &Vec<Task>,
-    columns: &Vec<Field>,
-    description_color: Color,
-    seen_uuids: &mut HashSet<uuid::Uuid>,
-) {
-    let tasks: Vec<_> = tasks
+impl SortKey {
+    fn compare(&self, a: &Task, b: &Task) -> Ordering {
+        // tasks missing the attribute always go last, whatever the direction
+        fn missing_last<T: PartialOrd>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+            match (a, b) {
+                (Some(a), Some(b)) => {
+                    let ord = a.partial_cmp(&b).unwrap_or(Ordering::Equal);
+                    if descending {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                }
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        }
+
+        let d = self.descending;
+        match self.attr {
+            SortAttr::Description => missing_last(Some(a.description()), Some(b.description()), d),
+            SortAttr::Due => missing_last(a.due().map(|x| **x), b.due().map(|x| **x), d),
+            SortAttr::Entry => missing_last(Some(**a.entry()), Some(**b.entry()), d),
+            SortAttr::Estimate => missing_last(estimate(a), estimate(b), d),
+            SortAttr::ID => missing_last(a.id(), b.id(), d),
+            SortAttr::Modified => {
+                missing_last(a.modified().map(|x| **x), b.modified().map(|x| **x), d)
+            }
+            SortAttr::Project => missing_last(a.project(), b.project(), d),
+            SortAttr::Scheduled => {
+                missing_last(a.scheduled().map(|x| **x), b.scheduled().map(|x| **x), d)
+            }
+            SortAttr::Urgency => missing_last(a.urgency(), b.urgency(), d),
+            SortAttr::Wait => missing_last(a.wait().map(|x| **x), b.wait().map(|x| **x), d),
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let (name, descending) = match s.strip_suffix('-') {
+            Some(name) => (name, true),
+            None => (s.strip_suffix('+').unwrap_or(s), false),
+        };
+
+        let attr = match name {
+            "description" => SortAttr::Description,
+            "due" => SortAttr::Due,
+            "entry" => SortAttr::Entry,
+            "est" | "estimate" => SortAttr::Estimate,
+            "id" => SortAttr::ID,
+            "modified" => SortAttr::Modified,
+            "project" => SortAttr::Project,
+            "scheduled" => SortAttr::Scheduled,
+            "urgency" => SortAttr::Urgency,
+            "wait" => SortAttr::Wait,
+            _ => bail!("Cannot sort on '{}'", name),
+        };
+
+        Ok(Self { attr, descending })
+    }
+}
+
+impl TryFrom<String> for SortKey {
+    type Error = Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+/// Stable sort by each key in turn, so earlier keys take precedence.
+pub fn sort_tasks(tasks: &mut [Task], keys: &[SortKey]) {
+    tasks.sort_by(|a, b| {
+        keys.iter()
+            .map(|k| k.compare(a, b))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// What to split a table's tasks into groups by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ArgEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    Project,
+    /// The `@context` tags. A task with several contexts shows up under each
+    /// of them.
+    Context,
+    Status,
+    /// Who a delegated task is waiting on.
+    Delegatee,
+}
+
+impl GroupBy {
+    fn labels(&self, task: &Task) -> Vec<String> {
+        match self {
+            Self::Project => task.project().cloned().into_iter().collect(),
+            Self::Context => task
+                .tags()
+                .into_iter()
+                .flatten()
+                .filter(|t| t.starts_with('@'))
+                .cloned()
+                .collect(),
+            Self::Status => vec![task.status().to_string()],
+            Self::Delegatee => UDA::delegatee()
+                .get_str(task)
+                .map(String::from)
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn missing(&self) -> &'static str {
+        match self {
+            Self::Project => "(no project)",
+            Self::Context => "(no context)",
+            Self::Status => "(no status)",
+            Self::Delegatee => "(nobody)",
+        }
+    }
+}
+
+/// Split `tasks` into labelled groups, ordered by label with the tasks that
+/// have none last. Tasks keep their order within each group.
+pub fn group_tasks<'t>(tasks: &[&'t Task], group: GroupBy) -> Vec<(String, Vec<&'t Task>)> {
+    let mut groups: BTreeMap<(bool, String), Vec<&Task>> = BTreeMap::new();
+
+    for task in tasks {
+        let labels = group.labels(task);
+        if labels.is_empty() {
+            groups
+                .entry((true, group.missing().into()))
+                .or_default()
+                .push(task);
+        }
+        for label in labels {
+            groups.entry((false, label)).or_default().push(task);
+        }
+    }
+
+    groups
         .into_iter()
-        .filter(|t| !seen_uuids.contains(t.uuid()))
-        .collect();
+        .map(|((_, label), tasks)| (label, tasks))
+        .collect()
+}
 
-    if tasks.is_empty() {
-        println!("    {}", style("--none--").yellow());
-    } else {
-        let mut table = TaskTable::new(columns).description_color(description_color);
+/// A color by name, like `dark_yellow`, or as `#rrggbb`.
+pub fn parse_color(s: &str) -> Result<Color> {
+    let s = s.trim().to_lowercase().replace(['-', ' '], "_");
 
-        for task in tasks {
-            seen_uuids.insert(task.uuid().clone());
-            table.add_row(task);
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() == 6 {
+            if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                return Ok(Color::Rgb {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                });
+            }
         }
+        bail!("Invalid hex color '{}'", s);
+    }
 
-        println!("{}", table);
+    Ok(match s.as_str() {
+        "reset" => Color::Reset,
+        "black" => Color::Black,
+        "dark_grey" | "dark_gray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "dark_red" => Color::DarkRed,
+        "green" => Color::Green,
+        "dark_green" => Color::DarkGreen,
+        "yellow" => Color::Yellow,
+        "dark_yellow" => Color::DarkYellow,
+        "blue" => Color::Blue,
+        "dark_blue" => Color::DarkBlue,
+        "magenta" => Color::Magenta,
+        "dark_magenta" => Color::DarkMagenta,
+        "cyan" => Color::Cyan,
+        "dark_cyan" => Color::DarkCyan,
+        "white" => Color::White,
+        "grey" | "gray" => Color::Grey,
+        _ => bail!("Unknown color '{}'", s),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use task_hookrs::import::import_task;
+
+    use super::*;
+
+    fn task(id: u64, due: Option<&str>, urgency: f64) -> Task {
+        let due = due
+            .map(|d| format!(r#""due":"{}","#, d))
+            .unwrap_or_default();
+        import_task(&format!(
+            r#"{{"id":{},"uuid":"0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f0{}","status":"pending",
+            "entry":"20261001T090000Z",{}"description":"task {}","urgency":{}}}"#,
+            id, id, due, id, urgency
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn parsing_columns() {
+        assert_eq!("due".parse::<Column>().unwrap(), Column::Field(Field::Due));
+        assert_eq!(
+            " GithubUrl".parse::<Column>().unwrap(),
+            Column::Uda(UDA::new("githuburl"))
+        );
+        assert!("not a column".parse::<Column>().is_err());
+    }
+
+    #[test]
+    fn parsing_fields() {
+        assert_eq!("est".parse::<Field>().unwrap(), Field::Estimate);
+        assert_eq!("brainpower".parse::<Field>().unwrap(), Field::Brainpower);
+        assert_eq!("blocked".parse::<Field>().unwrap(), Field::Depends);
+        assert_eq!("uuid.short".parse::<Field>().unwrap(), Field::ShortUuid);
+    }
+
+    #[test]
+    fn humanized_values() {
+        let task = import_task(
+            r#"{"id":4,"uuid":"0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f04","status":"pending",
+            "entry":"20261001T090000Z","description":"task 4","urgency":7.25,
+            "tags":["work","@computer"],"priority":"H","brain":"H","est":90}"#,
+        )
+        .unwrap();
+
+        assert_eq!(Field::Tags.get_value(&task), "@computer work");
+        assert_eq!(Field::Urgency.get_value(&task), "7.2");
+        assert_eq!(Field::Urgency.get_color(&task), Some(Color::Yellow));
+        assert_eq!(Field::Estimate.get_value(&task), "1.5h");
+        assert_eq!(Field::Brainpower.get_value(&task), "H");
+        assert_eq!(Field::Priority.get_color(&task), Some(Color::Red));
+        assert_eq!(Field::ShortUuid.get_value(&task), "0a5c3a4e");
+        assert_eq!(Field::Scheduled.get_value(&task), "");
+
+        assert_eq!(humanize(Duration::seconds(42)), "42s");
+        assert_eq!(humanize(Duration::hours(30)), "1d");
+        assert_eq!(humanize(Duration::days(20)), "2w");
+        assert_eq!(humanize(Duration::days(400)), "1y");
+        assert_eq!(humanize_minutes(30), "30min");
+        assert_eq!(humanize_minutes(480), "8h");
+        assert_eq!(humanize_minutes(9999), "6d");
+    }
+
+    #[test]
+    fn dependencies() {
+        let blocker = task(1, None, 0.0);
+        let mut blocked = task(2, None, 0.0);
+        blocked.set_depends::<_, Uuid>(Some(vec![
+            *blocker.uuid(),
+            // completed tasks don't block anything
+            Uuid::parse_str("0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f09").unwrap(),
+        ]));
+        let other = task(3, None, 0.0);
+
+        let deps = Dependencies::new(&[blocker.clone(), blocked.clone(), other.clone()]);
+        assert!(deps.is_blocking(&blocker) && !deps.is_blocked(&blocker));
+        assert!(deps.is_blocked(&blocked) && !deps.is_blocking(&blocked));
+        assert!(!deps.is_blocked(&other) && !deps.is_blocking(&other));
+    }
+
+    #[test]
+    fn typed_udas() {
+        let task = import_task(
+            r#"{"id":5,"uuid":"0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f05","status":"pending",
+            "entry":"20261001T090000Z","description":"task 5","points":2.50,
+            "spent":"P1DT2H30M","tally":"3.0","githuburl":"https://github.com/a/b/pull/1"}"#,
+        )
+        .unwrap();
+        let uda = |key: &str, kind| UDA {
+            key: key.into(),
+            label: None,
+            kind,
+        };
+
+        assert_eq!(uda("points", UdaType::Numeric).get_value(&task), "2.5");
+        assert_eq!(uda("tally", UdaType::Numeric).get_value(&task), "3");
+        assert_eq!(
+            uda("spent", UdaType::Duration).get_value(&task),
+            "1d2h30min"
+        );
+        assert_eq!(uda("spent", UdaType::String).get_value(&task), "P1DT2H30M");
+        assert_eq!(uda("missing", UdaType::Date).get_value(&task), "");
+        assert_eq!(UDA::github_url().name(), "URL");
+        assert_eq!(
+            UDA::github_url().get_str(&task),
+            Some("https://github.com/a/b/pull/1")
+        );
+
+        assert_eq!(parse_duration("PT45M"), Some(45 * 60));
+        assert_eq!(parse_duration("P2W"), Some(14 * 86400));
+        assert_eq!(parse_duration("P1H"), None);
+        assert_eq!(format_duration(0), "0s");
+
+        assert_eq!(
+            uda("points", UdaType::Numeric).parse_value("3").unwrap(),
+            UDAValue::U64(3)
+        );
+        assert!(uda("points", UdaType::Numeric).parse_value("lots").is_err());
+    }
+
+    #[test]
+    fn sorting() {
+        let mut tasks = vec![
+            task(1, None, 2.0),
+            task(2, Some("20261005T000000Z"), 1.0),
+            task(3, Some("20261003T000000Z"), 5.0),
+        ];
+
+        sort_tasks(&mut tasks, &["due+".parse().unwrap()]);
+        let ids: Vec<_> = tasks.iter().filter_map(|t| t.id()).collect();
+        assert_eq!(ids, vec![3, 2, 1]);
+
+        sort_tasks(&mut tasks, &["urgency-".parse().unwrap()]);
+        let ids: Vec<_> = tasks.iter().filter_map(|t| t.id()).collect();
+        assert_eq!(ids, vec![3, 1, 2]);
+
+        assert!("size".parse::<SortKey>().is_err());
+    }
+
+    #[test]
+    fn grouping() {
+        let mut tasks = [task(1, None, 0.0), task(2, None, 0.0), task(3, None, 0.0)];
+        tasks[0].set_project(Some("work"));
+        tasks[0].set_tags(Some(vec!["@computer", "@work"]));
+        tasks[1].set_project(Some("home"));
+        tasks[2].set_tags(Some(vec!["@home"]));
+        let tasks: Vec<_> = tasks.iter().collect();
+
+        let labels = |group| -> Vec<(String, Vec<u64>)> {
+            group_tasks(&tasks, group)
+                .into_iter()
+                .map(|(label, tasks)| (label, tasks.iter().filter_map(|t| t.id()).collect()))
+                .collect()
+        };
+
+        assert_eq!(
+            labels(GroupBy::Project),
+            vec![
+                ("home".into(), vec![2]),
+                ("work".into(), vec![1]),
+                ("(no project)".into(), vec![3]),
+            ]
+        );
+        assert_eq!(
+            labels(GroupBy::Context),
+            vec![
+                ("@computer".into(), vec![1]),
+                ("@home".into(), vec![3]),
+                ("@work".into(), vec![1]),
+                ("(no context)".into(), vec![2]),
+            ]
+        );
+        assert_eq!(
+            labels(GroupBy::Status),
+            vec![("Pending".into(), vec![1, 2, 3])]
+        );
+    }
+
+    #[test]
+    fn parsing_colors() {
+        assert_eq!(parse_color("Dark Yellow").unwrap(), Color::DarkYellow);
+        assert_eq!(
+            parse_color("#ff8000").unwrap(),
+            Color::Rgb {
+                r: 255,
+                g: 128,
+                b: 0
+            }
+        );
+        assert!(parse_color("#ff80").is_err());
+        assert!(parse_color("chartreuse").is_err());
     }
 }
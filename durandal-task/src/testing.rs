@@ -0,0 +1,161 @@
+//! A stand-in for the `task` binary, so code that shells out to taskwarrior
+//! can be exercised without touching anyone's real task list.
+use std::cell::RefCell;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde_json::Value;
+
+use crate::config::Config;
+
+thread_local! {
+    static TASK_BINARY: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+/// The binary `task_command` should run on this thread, if not `task`.
+pub fn task_binary() -> Option<PathBuf> {
+    TASK_BINARY.with(|bin| bin.borrow().clone())
+}
+
+// Tasks are stored one json object per line. Imports are given the next id
+// and everything that modifies a task just reports success.
+const SCRIPT: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+printf '%s\n' "$*" >> "$dir/calls"
+
+cmd=""
+uuid=""
+for arg in "$@"; do
+    case "$arg" in
+        rc.*) ;;
+        import|export|_projects|_udas|calc|done|delete|modify|annotate|start|stop)
+            [ -z "$cmd" ] && cmd="$arg" ;;
+        *-*-*-*-*) uuid="$arg" ;;
+    esac
+done
+
+case "$cmd" in
+    import)
+        echo "Importing 'STDIN'."
+        count=0
+        while IFS= read -r json || [ -n "$json" ]; do
+            [ -z "$json" ] && continue
+            new=$(printf '%s' "$json" | grep -o '"uuid":"[^"]*"' | head -n1 | cut -d'"' -f4)
+            id=$(($(wc -l < "$dir/tasks") + 1))
+            printf '%s\n' "$json" | sed "s/^{/{\"id\":$id,/" >> "$dir/tasks"
+            echo " add  $new"
+            count=$((count + 1))
+        done
+        echo "Imported $count tasks."
+        ;;
+    export)
+        printf '['
+        grep -e "$uuid" "$dir/tasks" | paste -sd, -
+        printf ']\n'
+        ;;
+    _projects)
+        grep -o '"project":"[^"]*"' "$dir/tasks" | cut -d'"' -f4 | sort -u
+        ;;
+    _udas) cat "$dir/udas" ;;
+    calc) echo "2030-01-01T00:00:00" ;;
+    done) echo "Completed 1 task." ;;
+    delete) echo "Deleted 1 task." ;;
+    modify) echo "Modified 1 task." ;;
+    annotate) echo "Annotated 1 task." ;;
+    start) echo "Started 1 task." ;;
+    stop) echo "Stopped 1 task." ;;
+esac
+"#;
+
+/// A config with just the required sections, plus whatever `extra` adds.
+pub fn config(extra: &str) -> Config {
+    let mut raw = config::Config::default();
+    raw.merge(config::File::from_str(
+        &format!(
+            r#"
+            [iou]
+            servers = []
+            [rfcs]
+            filter = ""
+            rnr_task_project = ""
+            [requests]
+            filter = ""
+            [scrum]
+            completed = ""
+            modified = ""
+            {}
+            "#,
+            extra
+        ),
+        config::FileFormat::Toml,
+    ))
+    .unwrap();
+
+    let mut config: Config = raw.try_into().unwrap();
+    config.resolve_udas().unwrap();
+    config
+}
+
+/// Installs a fake `task` for the current thread until dropped.
+pub struct FakeTask {
+    dir: PathBuf,
+}
+
+impl FakeTask {
+    pub fn new(tasks: &[Value]) -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let dir = std::env::temp_dir().join(format!(
+            "durandal-task-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::SeqCst)
+        ));
+        fs::create_dir_all(&dir).unwrap();
+
+        let lines: String = tasks.iter().map(|t| format!("{}\n", t)).collect();
+        fs::write(dir.join("tasks"), lines).unwrap();
+        fs::write(dir.join("calls"), "").unwrap();
+        fs::write(dir.join("udas"), "brain\nest\ndelegatee\ndelegation\n").unwrap();
+
+        let bin = dir.join("task");
+        fs::write(&bin, SCRIPT).unwrap();
+        fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+        TASK_BINARY.with(|b| *b.borrow_mut() = Some(bin));
+
+        Self { dir }
+    }
+
+    /// Have `task _udas` report just `names`.
+    pub fn declare_udas(&self, names: &[&str]) {
+        let lines: String = names.iter().map(|n| format!("{}\n", n)).collect();
+        fs::write(self.dir.join("udas"), lines).unwrap();
+    }
+
+    /// The arguments of every `task` invocation so far, one line each.
+    pub fn calls(&self) -> Vec<String> {
+        fs::read_to_string(self.dir.join("calls"))
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Every task the fake knows about, including imported ones.
+    pub fn tasks(&self) -> Vec<Value> {
+        fs::read_to_string(self.dir.join("tasks"))
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+}
+
+impl Drop for FakeTask {
+    fn drop(&mut self) {
+        TASK_BINARY.with(|b| *b.borrow_mut() = None);
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
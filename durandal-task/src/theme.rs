@@ -0,0 +1,219 @@
+//! The colors tables and section headers are drawn with, by what they mean
+//! rather than what they look like.
+//!
+//! ```toml
+//! [theme]
+//! base = "light"
+//!
+//! [theme.colors]
+//! overdue = "#d70000"
+//! header = "dark_blue"
+//! ```
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+use anyhow::{bail, Context, Result};
+use comfy_table::Color;
+use console::StyledObject;
+use serde::Deserialize;
+
+use crate::task_table::parse_color;
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Base {
+    #[default]
+    Dark,
+    Light,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ThemeConfig {
+    #[serde(default)]
+    pub base: Base,
+
+    /// Overrides for individual roles of the base theme, by role name.
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Task descriptions, unless a command has something better to say.
+    pub description: Color,
+    /// Section titles above tables.
+    pub header: Color,
+    /// Supporting columns like ids and dates.
+    pub muted: Color,
+    pub overdue: Color,
+    /// Next actions, and tasks that are ready to be worked on.
+    pub next: Color,
+    pub waiting: Color,
+    /// Tasks in progress.
+    pub active: Color,
+    pub completed: Color,
+    /// Whatever a listing is mainly about.
+    pub highlight: Color,
+    /// High urgency and priority.
+    pub urgent: Color,
+    /// Medium urgency and priority.
+    pub warning: Color,
+    pub tags: Color,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            description: Color::White,
+            header: Color::Cyan,
+            muted: Color::DarkGrey,
+            overdue: Color::DarkRed,
+            next: Color::Red,
+            waiting: Color::Magenta,
+            active: Color::Blue,
+            completed: Color::Green,
+            highlight: Color::DarkYellow,
+            urgent: Color::Red,
+            warning: Color::Yellow,
+            tags: Color::DarkCyan,
+        }
+    }
+
+    /// Only dark colors, so everything stays readable on a white background.
+    pub fn light() -> Self {
+        Self {
+            description: Color::Black,
+            header: Color::DarkBlue,
+            muted: Color::DarkGrey,
+            overdue: Color::DarkRed,
+            next: Color::DarkRed,
+            waiting: Color::DarkMagenta,
+            active: Color::DarkBlue,
+            completed: Color::DarkGreen,
+            highlight: Color::DarkYellow,
+            urgent: Color::DarkRed,
+            warning: Color::DarkYellow,
+            tags: Color::DarkCyan,
+        }
+    }
+
+    pub fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let mut theme = match config.base {
+            Base::Dark => Self::dark(),
+            Base::Light => Self::light(),
+        };
+
+        for (role, color) in &config.colors {
+            let color = parse_color(color).with_context(|| format!("In theme color {}", role))?;
+
+            *match role.as_str() {
+                "description" => &mut theme.description,
+                "header" => &mut theme.header,
+                "muted" => &mut theme.muted,
+                "overdue" => &mut theme.overdue,
+                "next" => &mut theme.next,
+                "waiting" => &mut theme.waiting,
+                "active" => &mut theme.active,
+                "completed" => &mut theme.completed,
+                "highlight" => &mut theme.highlight,
+                "urgent" => &mut theme.urgent,
+                "warning" => &mut theme.warning,
+                "tags" => &mut theme.tags,
+                _ => bail!("Unknown theme color '{}'", role),
+            } = color;
+        }
+
+        Ok(theme)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// Use the theme described by `config` for all subsequent output, and turn
+/// color off entirely if `NO_COLOR` asks for it.
+pub fn configure(config: &ThemeConfig) -> Result<()> {
+    let theme = Theme::from_config(config)?;
+
+    if no_color() {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+
+    // if this was already set, the first configuration wins
+    let _ = THEME.set(theme);
+    Ok(())
+}
+
+/// The configured theme, or the dark one.
+pub fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+/// Whether stdout should be colored at all, which it isn't when piped or
+/// when `NO_COLOR` is set.
+pub fn colors_enabled() -> bool {
+    console::colors_enabled() && !no_color()
+}
+
+fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+/// Style `value` in a theme color for printing outside of a table.
+pub fn paint<D: Display>(color: Color, value: D) -> StyledObject<D> {
+    let style = console::style(value);
+
+    match color {
+        Color::Black => style.black(),
+        Color::DarkGrey => style.black().bright(),
+        Color::DarkRed => style.red(),
+        Color::Red => style.red().bright(),
+        Color::DarkGreen => style.green(),
+        Color::Green => style.green().bright(),
+        Color::DarkYellow => style.yellow(),
+        Color::Yellow => style.yellow().bright(),
+        Color::DarkBlue => style.blue(),
+        Color::Blue => style.blue().bright(),
+        Color::DarkMagenta => style.magenta(),
+        Color::Magenta => style.magenta().bright(),
+        Color::DarkCyan => style.cyan(),
+        Color::Cyan => style.cyan().bright(),
+        Color::Grey => style.white(),
+        Color::White => style.white().bright(),
+        Color::AnsiValue(n) => style.color256(n),
+        // the nearest color in the 6x6x6 cube of the 256 color palette
+        Color::Rgb { r, g, b } => {
+            let level = |c: u8| (c as u16 * 5 / 255) as u8;
+            style.color256(16 + 36 * level(r) + 6 * level(g) + level(b))
+        }
+        Color::Reset => style,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn building_themes() {
+        let mut config = ThemeConfig::default();
+        assert_eq!(Theme::from_config(&config).unwrap(), Theme::dark());
+
+        config.base = Base::Light;
+        config.colors.insert("overdue".into(), "#ff0000".into());
+        let theme = Theme::from_config(&config).unwrap();
+        assert_eq!(theme.overdue, Color::Rgb { r: 255, g: 0, b: 0 });
+        assert_eq!(theme.description, Color::Black);
+
+        config.colors.insert("sparkle".into(), "red".into());
+        assert!(Theme::from_config(&config).is_err());
+    }
+}
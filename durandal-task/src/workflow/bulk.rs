@@ -0,0 +1,322 @@
+//! Triaging the inbox a batch at a time, for when it has piled up. Whatever
+//! isn't picked for one of the bulk actions is left for the one item at a
+//! time review.
+use std::fmt;
+
+use anyhow::Result;
+use console::style;
+use task_hookrs::task::Task;
+
+use crate::config::Config;
+use crate::prompt::{is_go_back, items, Prompter};
+use crate::task::Processable;
+
+use super::create::pick_project;
+use super::Outcome;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum BulkAction {
+    Trash,
+    Tickle,
+    Someday,
+    Reference,
+    Project,
+    Contexts,
+}
+
+impl BulkAction {
+    pub fn list() -> Vec<BulkAction> {
+        vec![
+            Self::Trash,
+            Self::Tickle,
+            Self::Someday,
+            Self::Reference,
+            Self::Project,
+            Self::Contexts,
+        ]
+    }
+}
+
+impl fmt::Display for BulkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Trash => write!(f, "Trash"),
+            Self::Tickle => write!(f, "Tickle"),
+            Self::Someday => write!(f, "Someday/maybe"),
+            Self::Reference => write!(f, "Reference"),
+            Self::Project => write!(f, "Move to a project"),
+            Self::Contexts => write!(f, "Add contexts"),
+        }
+    }
+}
+
+/// Ask which of `tasks` to deal with together and what to do with them.
+///
+/// Returns `None` once the user picks nothing, meaning the rest should be
+/// gone through one at a time.
+pub fn triage(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    tasks: &[Task],
+) -> Result<Option<Vec<Outcome>>> {
+    let descriptions: Vec<_> = tasks.iter().map(|t| t.description().clone()).collect();
+
+    loop {
+        let selections = prompter.multi_select(
+            "Which items go together? (space to select/unselect, none to go one at a time)",
+            &descriptions,
+            &vec![false; tasks.len()],
+        )?;
+
+        if selections.is_empty() {
+            return Ok(None);
+        }
+
+        let selected: Vec<_> = selections.iter().map(|s| &tasks[*s]).collect();
+
+        match apply(config, prompter, &selected) {
+            // pick the items again
+            Err(err) if is_go_back(&err) => continue,
+            result => return result.map(Some),
+        }
+    }
+}
+
+fn apply(config: &Config, prompter: &mut dyn Prompter, tasks: &[&Task]) -> Result<Vec<Outcome>> {
+    let actions = BulkAction::list();
+    let selection = prompter.select(
+        &format!(
+            "What would you like to do with these {} items?",
+            tasks.len()
+        ),
+        &items(&actions),
+        0,
+    )?;
+
+    let action = match actions[selection] {
+        BulkAction::Trash => {
+            for task in tasks {
+                task.delete()?;
+            }
+            prompter.tell(&style(format!("    {} tasks deleted", tasks.len())).red());
+            "deleted"
+        }
+        BulkAction::Tickle => {
+            let wait = prompter.input(
+                "When would you like to be reminded? (any valid 'wait:' value)",
+                Some("+1d"),
+            )?;
+            for task in tasks {
+                task.tickle(&wait)?;
+            }
+            prompter.tell(&style(format!("    {} tasks incubated", tasks.len())).green());
+            "incubated"
+        }
+        BulkAction::Someday => {
+            for task in tasks {
+                task.someday(&config.categories)?;
+            }
+            prompter.tell(&style(format!("    {} tasks incubated", tasks.len())).green());
+            "incubated"
+        }
+        BulkAction::Reference => {
+            for task in tasks {
+                task.reference(&config.categories)?;
+            }
+            prompter.tell(&style(format!("    {} tasks filed for reference", tasks.len())).green());
+            "referenced"
+        }
+        BulkAction::Project => {
+            let project = pick_project(config, prompter)?;
+            for task in tasks {
+                task.move_to(&project)?;
+            }
+            prompter
+                .tell(&style(format!("    {} tasks moved to {}", tasks.len(), project)).green());
+            "moved"
+        }
+        BulkAction::Contexts => {
+            let choices = &config.categories.contexts;
+            let selections = prompter.multi_select(
+                "What context(s) fit these tasks? (space to select/unselect)",
+                &items(choices),
+                &vec![false; choices.len()],
+            )?;
+            let tags: Vec<_> = selections.iter().map(|s| choices[*s].tag()).collect();
+
+            if tags.is_empty() {
+                prompter.tell(&style("    No contexts added").yellow());
+                return Ok(Vec::new());
+            }
+
+            for task in tasks {
+                task.categorize(&tags)?;
+            }
+            prompter.tell(
+                &style(format!(
+                    "    Added {} to {} tasks",
+                    tags.join(" "),
+                    tasks.len()
+                ))
+                .green(),
+            );
+            "categorized"
+        }
+    };
+
+    Ok(tasks.iter().map(|t| Outcome::new(action, t)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::prompt::{Answer, Scripted};
+    use crate::task::load_tasks;
+    use crate::testing::{config, FakeTask};
+
+    /// Three inbox items, triaged with `answers`.
+    fn triage_with(answers: Vec<Answer>) -> (Option<Vec<Outcome>>, FakeTask) {
+        triage_in(&config(""), answers)
+    }
+
+    fn triage_in(config: &Config, answers: Vec<Answer>) -> (Option<Vec<Outcome>>, FakeTask) {
+        let tasks: Vec<_> = ["call the plumber", "old newsletter", "gym membership"]
+            .iter()
+            .enumerate()
+            .map(|(i, description)| {
+                json!({
+                    "id": i + 1,
+                    "uuid": format!("00000000-0000-0000-0000-00000000000{}", i + 1),
+                    "status": "pending",
+                    "entry": "20261001T090000Z",
+                    "description": description,
+                    "project": "house",
+                    "tags": ["in"],
+                })
+            })
+            .collect();
+        let fake = FakeTask::new(&tasks);
+        let tasks = load_tasks("+in").unwrap();
+
+        let mut prompter = Scripted::new(answers);
+        let outcomes = triage(config, &mut prompter, &tasks).unwrap();
+        assert_eq!(prompter.remaining(), 0);
+
+        (outcomes, fake)
+    }
+
+    fn modified(fake: &FakeTask) -> Vec<String> {
+        fake.calls()
+            .into_iter()
+            .filter(|c| c.contains("delete") || c.contains("modify"))
+            .collect()
+    }
+
+    #[test]
+    fn trashing_several() {
+        let (outcomes, fake) = triage_with(vec![
+            Answer::MultiSelect(vec!["old newsletter".into(), "gym membership".into()]),
+            Answer::Select("Trash".into()),
+        ]);
+
+        let outcomes = outcomes.unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(outcomes.iter().all(|o| o.action == "deleted"));
+        assert_eq!(
+            modified(&fake),
+            vec![
+                "rc.confirmation=off 2 delete",
+                "rc.confirmation=off 3 delete"
+            ]
+        );
+    }
+
+    #[test]
+    fn tickling_with_one_date() {
+        let (outcomes, fake) = triage_with(vec![
+            Answer::MultiSelect(vec![0.into(), 2.into()]),
+            Answer::Select("Tickle".into()),
+            Answer::Input("+1w".into()),
+        ]);
+
+        assert_eq!(outcomes.unwrap().len(), 2);
+        assert_eq!(
+            modified(&fake),
+            vec![
+                "rc.confirmation=off 1 modify +tickle wait:+1w",
+                "rc.confirmation=off 3 modify +tickle wait:+1w",
+            ]
+        );
+    }
+
+    #[test]
+    fn filing_away_from_configured_contexts() {
+        let config = config(
+            r#"
+            [[categories.contexts]]
+            label = "Lab"
+            tag = "@lab"
+
+            [[categories.contexts]]
+            label = "On call"
+            tag = "@oncall"
+            "#,
+        );
+        let (_, fake) = triage_in(
+            &config,
+            vec![
+                Answer::MultiSelect(vec![1.into()]),
+                Answer::Select("Reference".into()),
+            ],
+        );
+
+        assert_eq!(
+            modified(&fake),
+            vec!["rc.confirmation=off 2 modify -in -@lab -@oncall +reference"]
+        );
+    }
+
+    #[test]
+    fn moving_and_categorizing() {
+        let (outcomes, fake) = triage_with(vec![
+            Answer::MultiSelect(vec![0.into()]),
+            Answer::Select("Move to a project".into()),
+            Answer::Select("house".into()),
+        ]);
+
+        assert_eq!(outcomes.unwrap()[0].action, "moved");
+        assert_eq!(
+            modified(&fake),
+            vec!["rc.confirmation=off 1 modify project:house -in"]
+        );
+
+        let (outcomes, fake) = triage_with(vec![
+            Answer::MultiSelect(vec![1.into(), 2.into()]),
+            Answer::Select("Add contexts".into()),
+            Answer::MultiSelect(vec!["Errands".into(), "Phone".into()]),
+        ]);
+
+        assert_eq!(outcomes.unwrap().len(), 2);
+        assert_eq!(
+            modified(&fake),
+            vec![
+                "rc.confirmation=off 2 modify +@errands +@phone -in",
+                "rc.confirmation=off 3 modify +@errands +@phone -in",
+            ]
+        );
+    }
+
+    #[test]
+    fn going_one_at_a_time() {
+        let (outcomes, fake) = triage_with(vec![
+            Answer::MultiSelect(vec![1.into()]),
+            Answer::Back,
+            Answer::MultiSelect(vec![]),
+        ]);
+
+        assert!(outcomes.is_none());
+        assert!(modified(&fake).is_empty());
+    }
+}
@@ -1,10 +1,14 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use console::style;
-use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
 use std::convert::{TryFrom, TryInto};
 use task_hookrs::task::Task;
 
-use crate::task::{projects, ActionCategory, Brainpower, Estimate, Project, TaskBuilder};
+use crate::config::Config;
+use crate::project::{activity, Project, ProjectPolicy, ProjectTree};
+use crate::prompt::{items, Prompter};
+use crate::task::{load_tasks, projects_excluding, TaskBuilder};
+
+use super::StatefulEnum;
 
 // Things that are required for a new task:
 // * Action (required)
@@ -23,9 +27,26 @@ pub enum NewTask {
 }
 
 impl NewTask {
-    pub fn new() -> Self {
+    /// Start from a builder that may already have some attributes set, the
+    /// prompts only fill in the rest.
+    pub fn with_builder(config: &Config, builder: TaskBuilder) -> Self {
+        Self::ProjectInfo(Workflow {
+            builder,
+            config: config.clone(),
+            existing: None,
+            state: ProjectInfo,
+        })
+    }
+
+    /// Ask the same questions about a task that already exists, and update it
+    /// with the answers instead of adding a new one.
+    pub fn for_task(config: &Config, task: Task) -> Self {
+        let builder = TaskBuilder::from_task(&config.categories, &task);
+
         Self::ProjectInfo(Workflow {
-            builder: TaskBuilder::new(),
+            builder,
+            config: config.clone(),
+            existing: Some(task),
             state: ProjectInfo,
         })
     }
@@ -33,22 +54,39 @@ impl NewTask {
     /// This is useful for adding a task to an existing project, namely in the
     /// workflow where we're determining what the next action should be for a
     /// project.
-    pub fn for_project(project: Project) -> Self {
-        let mut builder = TaskBuilder::new();
-        builder.with_project(project);
+    pub fn for_project(config: &Config, project: Project) -> Self {
+        let mut builder = TaskBuilder::new(&config.categories);
+        builder
+            .with_policy(&config.policy(&project))
+            .with_project(project);
 
         Self::Action(Workflow {
             builder,
+            config: config.clone(),
+            existing: None,
             state: Action,
         })
     }
 
-    pub fn step(self) -> Result<Self> {
+    /// The task that was added or updated, once the workflow is done.
+    pub fn created(self) -> Result<Task> {
+        match self {
+            Self::Done(wf) => Ok(wf.state.task),
+            _ => Err(anyhow::anyhow!(
+                "New task workflow stopped before adding a task {:?}",
+                self
+            )),
+        }
+    }
+}
+
+impl StatefulEnum for NewTask {
+    fn step(self, prompter: &mut dyn Prompter) -> Result<Self> {
         match self {
-            Self::ProjectInfo(workflow) => workflow.step(),
-            Self::Action(workflow) => workflow.step(),
-            Self::Context(workflow) => workflow.step(),
-            Self::Timing(workflow) => workflow.step(),
+            Self::ProjectInfo(workflow) => workflow.step(prompter),
+            Self::Action(workflow) => workflow.step(prompter),
+            Self::Context(workflow) => workflow.step(prompter),
+            Self::Timing(workflow) => workflow.step(prompter),
             _ => Err(anyhow::anyhow!(
                 "Attempted to prompt for a terminal state {:?}",
                 self
@@ -56,17 +94,80 @@ impl NewTask {
         }
     }
 
-    pub fn terminated(&self) -> bool {
+    fn terminated(&self) -> bool {
+        matches!(self, Self::Done(_))
+    }
+
+    fn state(&self) -> &'static str {
         match self {
-            Self::Done(_) => true,
-            _ => false,
+            Self::ProjectInfo(_) => "ProjectInfo",
+            Self::Action(_) => "Action",
+            Self::Context(_) => "Context",
+            Self::Timing(_) => "Timing",
+            Self::Done(_) => "Done",
         }
     }
 }
 
+/// Choose a project, with the most recently active ones first, or create a
+/// new one at the top level or under an existing project.
+pub(super) fn pick_project(config: &Config, prompter: &mut dyn Prompter) -> Result<Project> {
+    let recent = load_tasks("project.any: (status:pending or status:waiting)")?;
+    let tree = ProjectTree::new(projects_excluding(&config.excluded_projects)?)
+        .by_activity(&activity(&recent));
+    let (projects, labels): (Vec<_>, Vec<_>) = tree.labels().into_iter().unzip();
+
+    // the tree is shown indented, but searched by full project name
+    let with_first = |first: &str, rest: Vec<String>| {
+        std::iter::once(first.to_string())
+            .chain(rest)
+            .collect::<Vec<_>>()
+    };
+
+    // place a new project option at the front of the list
+    let new = "--New project--";
+    let choice = prompter.fuzzy_select(
+        "Select a project (type to search)",
+        &with_first(new, labels.clone()),
+        &with_first(new, items(&projects)),
+    )?;
+
+    if choice > 0 {
+        return Ok(projects[choice - 1].clone());
+    }
+
+    let top = "--Top level--";
+    let parent = prompter.fuzzy_select(
+        "Under which project?",
+        &with_first(top, labels),
+        &with_first(top, items(&projects)),
+    )?;
+
+    // Prompt the user for a new project
+    let name = prompter.input("Project: ", None)?;
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("A project needs a name");
+    }
+
+    Ok(match parent {
+        0 => name.into(),
+        n => format!("{}.{}", projects[n - 1], name).into(),
+    })
+}
+
+/// The project's policy, with the contexts and brainpower it leaves open
+/// suggested by the project's other tasks.
+fn project_policy(config: &Config, project: &Project) -> Result<ProjectPolicy> {
+    Ok(config.policy(project).with_suggestions(&project.tasks()?))
+}
+
 #[derive(Debug, Clone)]
 pub struct Workflow<S> {
     builder: TaskBuilder,
+    config: Config,
+    /// The task to update, rather than adding a new one.
+    existing: Option<Task>,
     pub state: S,
 }
 
@@ -74,34 +175,13 @@ pub struct Workflow<S> {
 pub struct ProjectInfo;
 
 impl Workflow<ProjectInfo> {
-    pub fn step(mut self) -> Result<NewTask> {
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Is this part of a project?")
-            .default(true)
-            .interact()?
-        {
-            let mut projects = projects()?;
-
-            // place a new project option at the front of the list
-            projects.insert(0, "--New project--".into());
-
-            let choice = Select::with_theme(&ColorfulTheme::default())
-                .with_prompt("Select a project")
-                .items(&projects)
-                .default(0)
-                .interact()?;
-
-            let project: Project = if choice == 0 {
-                // Prompt the user for a new project
-                let s: String = Input::with_theme(&ColorfulTheme::default())
-                    .with_prompt("Project: ")
-                    .interact_text()?;
-                s.into()
-            } else {
-                projects[choice].clone()
-            };
+    pub fn step(mut self, prompter: &mut dyn Prompter) -> Result<NewTask> {
+        if prompter.confirm("Is this part of a project?", true)? {
+            let project = pick_project(&self.config, prompter)?;
 
-            self.builder.with_project(project);
+            self.builder
+                .with_policy(&self.config.policy(&project))
+                .with_project(project);
         }
 
         Ok(NewTask::Action(self.into()))
@@ -112,6 +192,8 @@ impl From<Workflow<ProjectInfo>> for Workflow<Action> {
     fn from(value: Workflow<ProjectInfo>) -> Self {
         Workflow {
             builder: value.builder,
+            config: value.config,
+            existing: value.existing,
             state: Action {},
         }
     }
@@ -121,10 +203,13 @@ impl From<Workflow<ProjectInfo>> for Workflow<Action> {
 pub struct Action;
 
 impl Workflow<Action> {
-    pub fn step(mut self) -> Result<NewTask> {
-        let desc: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("What is the action?")
-            .interact_text()?;
+    pub fn step(mut self, prompter: &mut dyn Prompter) -> Result<NewTask> {
+        // an existing task's description is a good place to start
+        let current = self.builder.description().to_string();
+        let desc = prompter.input(
+            "What is the action?",
+            Some(current.as_str()).filter(|d| !d.is_empty()),
+        )?;
 
         self.builder.with_description(desc);
 
@@ -136,6 +221,8 @@ impl From<Workflow<Action>> for Workflow<Context> {
     fn from(value: Workflow<Action>) -> Self {
         Workflow {
             builder: value.builder,
+            config: value.config,
+            existing: value.existing,
             state: Context {},
         }
     }
@@ -145,50 +232,74 @@ impl From<Workflow<Action>> for Workflow<Context> {
 pub struct Context;
 
 impl Workflow<Context> {
-    pub fn step(mut self) -> Result<NewTask> {
-        let choices = ActionCategory::list();
+    pub fn step(mut self, prompter: &mut dyn Prompter) -> Result<NewTask> {
+        let choices = &self.config.categories.contexts;
+
+        // preselect the contexts the task already has, or else whatever the
+        // project's policy or its other tasks suggest
+        let policy = match self.builder.project() {
+            Some(project) => project_policy(&self.config, project)?,
+            None => ProjectPolicy::default(),
+        };
+        self.builder.with_policy(&policy);
+        let current = self.builder.contexts().to_vec();
+        let suggested = match policy.contexts {
+            Some(ref tags) if current.is_empty() => tags.clone(),
+            _ => current,
+        };
+        let preselected: Vec<bool> = choices
+            .iter()
+            .map(|c| suggested.iter().any(|t| t == c.tag()))
+            .collect();
 
         let selected = loop {
-            let selections = MultiSelect::with_theme(&ColorfulTheme::default())
-                .with_prompt("What context(s) fit this task? (space to select/unselect)")
-                .items(&choices)
-                .interact()?;
+            let selections = prompter.multi_select(
+                "What context(s) fit this task? (space to select/unselect)",
+                &items(choices),
+                &preselected,
+            )?;
 
-            let selected: Vec<_> = selections.iter().map(|s| choices[*s]).collect();
+            let selected: Vec<_> = selections.iter().map(|s| choices[*s].clone()).collect();
 
             if !selected.is_empty() {
                 break selected;
             }
 
-            println!(
+            prompter.tell(&format!(
                 "  {}  ",
                 style("You must specify at least one context").red()
-            );
+            ));
         };
 
-        self.builder.with_contexts(&selected);
+        self.builder.clear_contexts().with_contexts(&selected);
 
         // brainpower
-        let choices = Brainpower::list();
+        let choices = &self.config.categories.brainpower;
 
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("How much brainpower will this take?")
-            .items(&choices)
-            .default(1)
-            .interact()?;
+        let selection = prompter.select(
+            "How much brainpower will this take?",
+            &items(choices),
+            self.config
+                .categories
+                .brainpower_index(self.builder.brainpower())
+                .unwrap_or_default(),
+        )?;
 
-        self.builder.with_brainpower(choices[selection]);
+        self.builder.with_brainpower(&choices[selection]);
 
         // estimate in minutes
-        let choices = Estimate::list();
+        let choices = &self.config.categories.estimates;
 
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Rough estimate for how long this task will take?")
-            .default(0)
-            .items(&choices)
-            .interact()?;
+        let selection = prompter.select(
+            "Rough estimate for how long this task will take?",
+            &items(choices),
+            self.config
+                .categories
+                .estimate_index(self.builder.estimate())
+                .unwrap_or_default(),
+        )?;
 
-        self.builder.with_estimate(choices[selection]);
+        self.builder.with_estimate(&choices[selection]);
 
         Ok(NewTask::Timing(self.into()))
     }
@@ -198,6 +309,8 @@ impl From<Workflow<Context>> for Workflow<Timing> {
     fn from(value: Workflow<Context>) -> Self {
         Workflow {
             builder: value.builder,
+            config: value.config,
+            existing: value.existing,
             state: Timing,
         }
     }
@@ -207,19 +320,30 @@ impl From<Workflow<Context>> for Workflow<Timing> {
 pub struct Timing;
 
 impl Workflow<Timing> {
-    pub fn step(mut self) -> Result<NewTask> {
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Is there a specific due date?")
-            .default(false)
-            .interact()?
-        {
-            let due: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("When is it due?")
-                .interact_text()?;
+    pub fn step(mut self, prompter: &mut dyn Prompter) -> Result<NewTask> {
+        let current = self.builder.due().map(String::from);
+        if prompter.confirm("Is there a specific due date?", current.is_some())? {
+            let due = prompter.input("When is it due?", current.as_deref())?;
             self.builder.with_due(due);
         }
 
-        Ok(NewTask::Done(self.try_into()?))
+        let updating = self.existing.is_some();
+        let done: Workflow<Done> = self.try_into()?;
+        let msg = format!(
+            "{} task with id {}",
+            if updating {
+                "Updated the"
+            } else {
+                "Added a new"
+            },
+            done.state
+                .task
+                .id()
+                .expect("Expected id, since we already read it in once")
+        );
+        prompter.tell(&format!("    {}", style(msg).green()));
+
+        Ok(NewTask::Done(done))
     }
 }
 
@@ -227,17 +351,15 @@ impl TryFrom<Workflow<Timing>> for Workflow<Done> {
     type Error = anyhow::Error;
 
     fn try_from(value: Workflow<Timing>) -> Result<Self> {
-        let task = value.builder.build()?;
-        let msg = format!(
-            "Added a new task with id {}",
-            task.id()
-                .expect("Expected id, since we already read it in once")
-        );
-
-        println!("    {}", style(msg).green());
+        let task = match value.existing {
+            Some(ref task) => value.builder.modify(task)?,
+            None => value.builder.build()?,
+        };
 
         Ok(Workflow {
             builder: value.builder,
+            config: value.config,
+            existing: value.existing,
             state: Done { task },
         })
     }
@@ -1,15 +1,20 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt;
+use std::time::Duration;
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use chrono::{NaiveDateTime, Utc};
 use console::style;
-use dialoguer::{theme::ColorfulTheme, Confirm};
-use dialoguer::{Input, Select};
 use task_hookrs::task::Task;
 
-use crate::task::Processable;
+use crate::config::Config;
+use crate::prompt::{is_go_back, items, Prompter};
+use crate::task::{delegatees, load_tasks, require_udas, Processable};
+use crate::task_table::format_duration;
 
 use super::create::NewTask;
+use super::session::Session;
+use super::{Driver, Outcome, StatefulEnum};
 
 #[derive(Debug, Clone)]
 pub enum InboxItem {
@@ -19,6 +24,7 @@ pub enum InboxItem {
     Incubated(Workflow<Incubated>),
     Referenced(Workflow<Referenced>),
     Actioning(Workflow<Actioning>),
+    Doing(Workflow<Doing>),
     Delegating(Workflow<Delegating>),
     Delegated(Workflow<Delegated>),
     Deferring(Workflow<Deferring>),
@@ -28,18 +34,119 @@ pub enum InboxItem {
 }
 
 impl InboxItem {
-    pub fn new(task: Task) -> Self {
-        Self::Starting(Workflow::new(task))
+    pub fn new(config: &Config, task: Task) -> Self {
+        Self::Starting(Workflow::new(config, task))
     }
 
-    pub fn step(self) -> Result<Self> {
+    /// Pick an item back up where `session` left it, or `None` if it's no
+    /// longer in the inbox.
+    pub fn resume(config: &Config, session: &Session) -> Result<Option<Self>> {
+        let task = match load_tasks(&format!("status:pending {}", session.uuid))?.pop() {
+            Some(task) => task,
+            None => return Ok(None),
+        };
+
+        let config = config.clone();
+        let item = match session.state.as_str() {
+            "Starting" => Self::Starting(Workflow::new(&config, task)),
+            "Inactioning" => Self::Inactioning(Workflow {
+                task,
+                config,
+                state: Inactioning,
+            }),
+            "Incubating" => Self::Incubating(Workflow {
+                task,
+                config,
+                state: Incubating,
+            }),
+            "Actioning" => Self::Actioning(Workflow {
+                task,
+                config,
+                state: Actioning,
+            }),
+            "Doing" => {
+                // the clock picks up from when the task was started
+                let since = task.start().map(|d| **d).unwrap_or_else(now);
+                Self::Doing(Workflow {
+                    task,
+                    config,
+                    state: Doing { since },
+                })
+            }
+            "Deferring" => Self::Deferring(Workflow {
+                task,
+                config,
+                state: Deferring {
+                    elapsed: session.elapsed.map(Duration::from_secs),
+                },
+            }),
+            "Delegating" => Self::Delegating(Workflow {
+                task,
+                config,
+                state: Delegating,
+            }),
+            state => bail!("Can't resume an inbox item from {}", state),
+        };
+
+        Ok(Some(item))
+    }
+
+    /// Enough to resume the item from its current state.
+    pub fn session(&self) -> Session {
+        let task = match self {
+            Self::Starting(wf) => &wf.task,
+            Self::Inactioning(wf) => &wf.task,
+            Self::Incubating(wf) => &wf.task,
+            Self::Incubated(wf) => &wf.task,
+            Self::Referenced(wf) => &wf.task,
+            Self::Actioning(wf) => &wf.task,
+            Self::Doing(wf) => &wf.task,
+            Self::Delegating(wf) => &wf.task,
+            Self::Delegated(wf) => &wf.task,
+            Self::Deferring(wf) => &wf.task,
+            Self::Deferred(wf) => &wf.task,
+            Self::Finished(wf) => &wf.task,
+            Self::Deleted(wf) => &wf.task,
+        };
+
+        let elapsed = match self {
+            Self::Deferring(wf) => wf.state.elapsed.map(|e| e.as_secs()),
+            _ => None,
+        };
+
+        Session {
+            uuid: task.uuid().to_string(),
+            state: self.state().into(),
+            elapsed,
+        }
+    }
+
+    /// What happened to the task, once the item has been processed.
+    pub fn outcome(&self) -> Option<Outcome> {
+        let (action, task) = match self {
+            Self::Incubated(wf) => ("incubated", &wf.task),
+            Self::Referenced(wf) => ("referenced", &wf.task),
+            Self::Delegated(wf) => ("delegated", &wf.task),
+            Self::Deferred(wf) => ("deferred", &wf.task),
+            Self::Finished(wf) => ("finished", &wf.task),
+            Self::Deleted(wf) => ("deleted", &wf.task),
+            _ => return None,
+        };
+
+        Some(Outcome::new(action, task))
+    }
+}
+
+impl StatefulEnum for InboxItem {
+    fn step(self, prompter: &mut dyn Prompter) -> Result<Self> {
         match self {
-            Self::Starting(machine) => machine.step(),
-            Self::Inactioning(machine) => machine.step(),
-            Self::Incubating(machine) => machine.step(),
-            Self::Actioning(machine) => machine.step(),
-            Self::Deferring(machine) => machine.step(),
-            Self::Delegating(machine) => machine.step(),
+            Self::Starting(machine) => machine.step(prompter),
+            Self::Inactioning(machine) => machine.step(prompter),
+            Self::Incubating(machine) => machine.step(prompter),
+            Self::Actioning(machine) => machine.step(prompter),
+            Self::Doing(machine) => machine.step(prompter),
+            Self::Deferring(machine) => machine.step(prompter),
+            Self::Delegating(machine) => machine.step(prompter),
             _ => Err(anyhow::anyhow!(
                 "Attempted to prompt for a terminal state {:?}",
                 self
@@ -47,7 +154,7 @@ impl InboxItem {
         }
     }
 
-    pub fn terminated(&self) -> bool {
+    fn terminated(&self) -> bool {
         match self {
             Self::Incubated(_)
             | Self::Referenced(_)
@@ -58,31 +165,59 @@ impl InboxItem {
             _ => false,
         }
     }
+
+    fn state(&self) -> &'static str {
+        match self {
+            Self::Starting(_) => "Starting",
+            Self::Inactioning(_) => "Inactioning",
+            Self::Incubating(_) => "Incubating",
+            Self::Incubated(_) => "Incubated",
+            Self::Referenced(_) => "Referenced",
+            Self::Actioning(_) => "Actioning",
+            Self::Doing(_) => "Doing",
+            Self::Delegating(_) => "Delegating",
+            Self::Delegated(_) => "Delegated",
+            Self::Deferring(_) => "Deferring",
+            Self::Deferred(_) => "Deferred",
+            Self::Finished(_) => "Finished",
+            Self::Deleted(_) => "Deleted",
+        }
+    }
+
+    /// The task is already started by the time it's being done.
+    fn committed(&self) -> bool {
+        self.terminated() || matches!(self, Self::Doing(_))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Workflow<S> {
     task: Task,
-    _state: S,
+    config: Config,
+    state: S,
+}
+
+fn now() -> NaiveDateTime {
+    Utc::now().naive_utc()
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct Starting;
 
 impl Workflow<Starting> {
-    pub fn new(task: Task) -> Workflow<Starting> {
+    pub fn new(config: &Config, task: Task) -> Workflow<Starting> {
         Workflow {
             task,
-            _state: Starting {},
+            config: config.clone(),
+            state: Starting {},
         }
     }
 
-    pub fn step(self) -> Result<InboxItem> {
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Is this actionable (could you start work on this now)?")
-            .default(true)
-            .interact()?
-        {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
+        if prompter.confirm(
+            "Is this actionable (could you start work on this now)?",
+            true,
+        )? {
             Ok(InboxItem::Actioning(self.into()))
         } else {
             Ok(InboxItem::Inactioning(self.into()))
@@ -94,7 +229,8 @@ impl From<Workflow<Starting>> for Workflow<Inactioning> {
     fn from(item: Workflow<Starting>) -> Self {
         Workflow {
             task: item.task,
-            _state: Inactioning {},
+            config: item.config,
+            state: Inactioning {},
         }
     }
 }
@@ -103,7 +239,8 @@ impl From<Workflow<Starting>> for Workflow<Actioning> {
     fn from(value: Workflow<Starting>) -> Self {
         Workflow {
             task: value.task,
-            _state: Actioning {},
+            config: value.config,
+            state: Actioning {},
         }
     }
 }
@@ -135,18 +272,22 @@ impl fmt::Display for NonAction {
 pub struct Inactioning;
 
 impl Workflow<Inactioning> {
-    pub fn step(self) -> Result<InboxItem> {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
         let unactionable = NonAction::list();
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like to do?")
-            .default(0)
-            .items(&unactionable)
-            .interact()?;
+        let selection = prompter.select("What would you like to do?", &items(&unactionable), 0)?;
 
         Ok(match unactionable[selection] {
-            NonAction::Trash => InboxItem::Deleted(self.try_into()?),
+            NonAction::Trash => {
+                let deleted = self.try_into()?;
+                prompter.tell(&style("    Task deleted").red());
+                InboxItem::Deleted(deleted)
+            }
             NonAction::Incubate => InboxItem::Incubating(self.into()),
-            NonAction::Reference => InboxItem::Referenced(self.try_into()?),
+            NonAction::Reference => {
+                let referenced = self.try_into()?;
+                prompter.tell(&style("    Task filed for reference").green());
+                InboxItem::Referenced(referenced)
+            }
         })
     }
 }
@@ -156,11 +297,11 @@ impl TryFrom<Workflow<Inactioning>> for Workflow<Deleted> {
 
     fn try_from(value: Workflow<Inactioning>) -> Result<Self> {
         value.task.delete()?;
-        println!("{}", style("    Task deleted").red());
 
         Ok(Workflow {
             task: value.task,
-            _state: Deleted {},
+            config: value.config,
+            state: Deleted {},
         })
     }
 }
@@ -169,7 +310,8 @@ impl From<Workflow<Inactioning>> for Workflow<Incubating> {
     fn from(value: Workflow<Inactioning>) -> Self {
         Workflow {
             task: value.task,
-            _state: Incubating {},
+            config: value.config,
+            state: Incubating {},
         }
     }
 }
@@ -178,12 +320,12 @@ impl TryFrom<Workflow<Inactioning>> for Workflow<Referenced> {
     type Error = anyhow::Error;
 
     fn try_from(value: Workflow<Inactioning>) -> Result<Self> {
-        value.task.reference()?;
-        println!("{}", style("    Task filed for reference").green());
+        value.task.reference(&value.config.categories)?;
 
         Ok(Workflow {
             task: value.task,
-            _state: Referenced {},
+            config: value.config,
+            state: Referenced {},
         })
     }
 }
@@ -192,34 +334,31 @@ impl TryFrom<Workflow<Inactioning>> for Workflow<Referenced> {
 pub struct Incubating;
 
 impl Workflow<Incubating> {
-    pub fn step(self) -> Result<InboxItem> {
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Would you like to be reminded about this task later?")
-            .default(true)
-            .interact()?
-        {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
+        if prompter.confirm("Would you like to be reminded about this task later?", true)? {
             // tickler
-            let wait: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("When would you like to be reminded? (any valid 'wait:' value)")
-                .default("+1d".into())
-                .interact_text()?;
+            let wait = prompter.input(
+                "When would you like to be reminded? (any valid 'wait:' value)",
+                Some("+1d"),
+            )?;
 
             self.task.tickle(&wait)?;
         } else {
             // someday/maybe
-            self.task.someday()?;
+            self.task.someday(&self.config.categories)?;
         }
 
+        prompter.tell(&style("    Task incubated").green());
         Ok(InboxItem::Incubated(self.into()))
     }
 }
 
 impl From<Workflow<Incubating>> for Workflow<Incubated> {
     fn from(value: Workflow<Incubating>) -> Self {
-        println!("{}", style("    Task incubated").green());
         Workflow {
             task: value.task,
-            _state: Incubated {},
+            config: value.config,
+            state: Incubated {},
         }
     }
 }
@@ -254,31 +393,14 @@ impl fmt::Display for Action {
 pub struct Actioning;
 
 impl Workflow<Actioning> {
-    pub fn step(self) -> Result<InboxItem> {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
         let choices = Action::list();
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like to do?")
-            .default(0)
-            .items(&choices)
-            .interact()?;
+        let selection = prompter.select("What would you like to do?", &items(&choices), 0)?;
 
         Ok(match choices[selection] {
             Action::Do => {
-                // So this is a little dumb, but if you've decided to do something
-                // in the inbox, we need to wait for it to be done and there should
-                // be no normal way to get around that.
-                //
-                // Since this is so simple, I decided against having a "Doing" state
-                // which would have just increased the complexity
-                loop {
-                    if Confirm::with_theme(&ColorfulTheme::default())
-                        .with_prompt("Is it done?")
-                        .default(true)
-                        .interact()?
-                    {
-                        break InboxItem::Finished(self.try_into()?);
-                    }
-                }
+                self.task.begin()?;
+                InboxItem::Doing(self.into())
             }
             Action::Defer => InboxItem::Deferring(self.into()),
             Action::Delegate => InboxItem::Delegating(self.into()),
@@ -286,16 +408,70 @@ impl Workflow<Actioning> {
     }
 }
 
-impl TryFrom<Workflow<Actioning>> for Workflow<Finished> {
+impl From<Workflow<Actioning>> for Workflow<Doing> {
+    fn from(value: Workflow<Actioning>) -> Self {
+        Workflow {
+            task: value.task,
+            config: value.config,
+            state: Doing { since: now() },
+        }
+    }
+}
+
+/// The task has been started, and is timed against the two-minute rule
+/// until it's done or turned into a deferred task.
+#[derive(Debug, Clone, Copy)]
+pub struct Doing {
+    /// When the task was started, in UTC like taskwarrior's dates.
+    since: NaiveDateTime,
+}
+
+impl Workflow<Doing> {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
+        let threshold = Duration::from_secs(self.config.inbox.do_it_minutes * 60);
+        let mut elapsed = (now() - self.state.since).to_std().unwrap_or_default();
+
+        // if you've decided to do something in the inbox, it needs to get done
+        // or deferred, there's no normal way to get around that
+        loop {
+            elapsed = prompter.timer("Doing it, press enter when done", elapsed, threshold)?;
+
+            if prompter.confirm("Is it done?", true)? {
+                self.task.annotate(&format!(
+                    "Done in {}",
+                    format_duration(elapsed.as_secs() as i64)
+                ))?;
+                let finished = self.try_into()?;
+                prompter.tell(&style("    Task finished").green());
+                return Ok(InboxItem::Finished(finished));
+            }
+
+            if elapsed > threshold
+                && prompter.confirm("This is taking a while, defer it instead?", true)?
+            {
+                self.task.stop()?;
+                return Ok(InboxItem::Deferring(Workflow {
+                    task: self.task,
+                    config: self.config,
+                    state: Deferring {
+                        elapsed: Some(elapsed),
+                    },
+                }));
+            }
+        }
+    }
+}
+
+impl TryFrom<Workflow<Doing>> for Workflow<Finished> {
     type Error = anyhow::Error;
 
-    fn try_from(value: Workflow<Actioning>) -> Result<Self> {
+    fn try_from(value: Workflow<Doing>) -> Result<Self> {
         value.task.finish()?;
-        println!("{}", style("    Task finished").green());
 
         Ok(Workflow {
             task: value.task,
-            _state: Finished {},
+            config: value.config,
+            state: Finished {},
         })
     }
 }
@@ -304,7 +480,8 @@ impl From<Workflow<Actioning>> for Workflow<Deferring> {
     fn from(value: Workflow<Actioning>) -> Self {
         Workflow {
             task: value.task,
-            _state: Deferring {},
+            config: value.config,
+            state: Deferring::default(),
         }
     }
 }
@@ -313,43 +490,52 @@ impl From<Workflow<Actioning>> for Workflow<Delegating> {
     fn from(value: Workflow<Actioning>) -> Self {
         Workflow {
             task: value.task,
-            _state: Delegating {},
+            config: value.config,
+            state: Delegating {},
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Deferring;
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Deferring {
+    /// Time already spent doing the task, when it took too long to just do.
+    elapsed: Option<Duration>,
+}
 
 impl Workflow<Deferring> {
-    pub fn step(self) -> Result<InboxItem> {
-        let mut newtask = NewTask::new();
-
-        loop {
-            newtask = newtask
-                .step()
-                .with_context(|| "Attempting to create a task as part of the deferring step")?;
-
-            if newtask.terminated() {
-                break;
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
+        // going back from the first question of the new task lands on
+        // what to do with the item again
+        let deferred = match Driver::new(NewTask::for_task(&self.config, self.task.clone()))
+            .nested()
+            .run(prompter)
+        {
+            // back to doing it, which was stopped on the way here
+            Err(err) if is_go_back(&err) && self.state.elapsed.is_some() => {
+                self.task.begin()?;
+                return Err(err);
             }
+            result => result,
         }
+        .and_then(NewTask::created)
+        .with_context(|| "Attempting to update the task as part of the deferring step")?;
 
-        Ok(InboxItem::Deferred(self.try_into()?))
-    }
-}
+        deferred.remove_tags(&["in"])?;
 
-impl TryFrom<Workflow<Deferring>> for Workflow<Deferred> {
-    type Error = anyhow::Error;
+        if let Some(elapsed) = self.state.elapsed {
+            deferred.annotate(&format!(
+                "Spent {} on it before deferring",
+                format_duration(elapsed.as_secs() as i64)
+            ))?;
+        }
 
-    fn try_from(value: Workflow<Deferring>) -> Result<Self> {
-        value.task.delete()?;
-        println!("{}", style("    Task deferred (original deleted)").green());
+        prompter.tell(&style("    Task deferred").green());
 
-        Ok(Workflow {
-            task: value.task,
-            _state: Deferred,
-        })
+        Ok(InboxItem::Deferred(Workflow {
+            task: deferred,
+            config: self.config,
+            state: Deferred,
+        }))
     }
 }
 
@@ -374,6 +560,16 @@ impl Delegate {
             Self::Agenda => "Meant to bring it up in the next meeting",
         }
     }
+
+    /// The value of the `delegation` UDA.
+    fn uda(&self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Email => "email",
+            Self::Phone => "phone",
+            Self::Agenda => "agenda",
+        }
+    }
 }
 
 impl fmt::Display for Delegate {
@@ -391,42 +587,60 @@ impl fmt::Display for Delegate {
 pub struct Delegating;
 
 impl Workflow<Delegating> {
-    pub fn step(self) -> Result<InboxItem> {
-        let choices = Delegate::list();
-        let selection = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like to do?")
-            .default(0)
-            .items(&choices)
-            .interact()?;
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<InboxItem> {
+        require_udas(&["delegatee", "delegation"])?;
+
+        let mut delegatees = delegatees()?;
+
+        // place a new person option at the front of the list
+        delegatees.insert(0, "--Someone new--".into());
+
+        let choice = prompter.fuzzy_select(
+            "Who is this delegated to? (type to search)",
+            &delegatees,
+            &delegatees,
+        )?;
+        let delegatee = if choice == 0 {
+            loop {
+                let who = prompter.input("Who?", None)?;
+                if !who.trim().is_empty() {
+                    break who;
+                }
+                prompter.tell(&style("    It has to be delegated to someone").yellow());
+            }
+        } else {
+            delegatees.swap_remove(choice)
+        };
 
-        let msg: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("What would you like the reminder for this follow-up to be?")
-            .interact()?;
+        let choices = Delegate::list();
+        let selection = prompter.select("What would you like to do?", &items(&choices), 0)?;
 
-        let wait: String = Input::with_theme(&ColorfulTheme::default())
-            .with_prompt("When should this follow-up appear in your inbox?")
-            .interact()?;
+        let msg = prompter.input(
+            "What would you like the reminder for this follow-up to be?",
+            None,
+        )?;
 
-        let follow_up = self.task.follow_up(&msg, &wait)?;
+        let wait = prompter.input("When should this follow-up appear in your inbox?", None)?;
 
         // Annotate with what delegation option we chose for later reference
-        follow_up.annotate(choices[selection].annotation())?;
-
-        Ok(InboxItem::Delegated(self.try_into()?))
-    }
-}
-
-impl TryFrom<Workflow<Delegating>> for Workflow<Delegated> {
-    type Error = anyhow::Error;
-
-    fn try_from(value: Workflow<Delegating>) -> Result<Self> {
-        value.task.delete()?;
-        println!("{}", style("    Task delegated (original deleted)").green());
-
-        Ok(Workflow {
-            task: value.task,
-            _state: Delegated {},
-        })
+        let follow_up = self.task.follow_up(
+            &self.config.categories,
+            &msg,
+            &wait,
+            &[choices[selection].annotation()],
+            &[
+                ("delegatee", delegatee.trim()),
+                ("delegation", choices[selection].uda()),
+            ],
+        )?;
+
+        prompter.tell(&style("    Task delegated").green());
+
+        Ok(InboxItem::Delegated(Workflow {
+            task: follow_up,
+            config: self.config,
+            state: Delegated,
+        }))
     }
 }
 
@@ -444,3 +658,579 @@ pub struct Finished;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Deleted;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::prompt::{Answer, Scripted};
+    use crate::task::load_tasks;
+    use crate::testing::{config, FakeTask};
+    use crate::workflow::Transition::{self, Back, Forward};
+
+    const UUID: &str = "0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f00";
+
+    /// Run an inbox item through the whole workflow with the given answers.
+    fn process(answers: Vec<Answer>) -> (InboxItem, FakeTask) {
+        let (item, _, fake) = drive(answers);
+        (item, fake)
+    }
+
+    fn drive(answers: Vec<Answer>) -> (InboxItem, Vec<Transition>, FakeTask) {
+        drive_among(vec![], answers)
+    }
+
+    /// Like `drive`, with `others` in the task list after the inbox item.
+    fn drive_among(
+        others: Vec<serde_json::Value>,
+        answers: Vec<Answer>,
+    ) -> (InboxItem, Vec<Transition>, FakeTask) {
+        let item = json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+            "tags": ["in"],
+        });
+        drive_item(item, others, answers)
+    }
+
+    /// Like `drive_among`, with `item` as the inbox item.
+    fn drive_item(
+        item: serde_json::Value,
+        others: Vec<serde_json::Value>,
+        answers: Vec<Answer>,
+    ) -> (InboxItem, Vec<Transition>, FakeTask) {
+        let mut tasks = vec![item];
+        tasks.extend(others);
+        let fake = FakeTask::new(&tasks);
+        let config = config("");
+        let task = load_tasks("+in").unwrap().remove(0);
+
+        let mut prompter = Scripted::new(answers);
+        let mut driver = Driver::new(InboxItem::new(&config, task));
+        let item = driver.run(&mut prompter).unwrap();
+
+        assert_eq!(prompter.remaining(), 0);
+
+        (item, driver.trace().to_vec(), fake)
+    }
+
+    fn called(fake: &FakeTask, args: &str) -> bool {
+        fake.calls().iter().any(|c| c == args)
+    }
+
+    #[test]
+    fn trashing() {
+        let (item, fake) = process(vec![Answer::Confirm(false), Answer::Select(0.into())]);
+
+        assert!(matches!(item, InboxItem::Deleted(_)));
+        assert!(called(&fake, "rc.confirmation=off 1 delete"));
+    }
+
+    #[test]
+    fn referencing() {
+        let (item, fake) = process(vec![Answer::Confirm(false), Answer::Select(2.into())]);
+
+        assert!(matches!(item, InboxItem::Referenced(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify -in -@agenda -@anywhere -@computer -@errands \
+             -@home -@phone -@rnr -@work +reference"
+        ));
+    }
+
+    #[test]
+    fn incubating() {
+        let (item, fake) = process(vec![
+            Answer::Confirm(false),
+            Answer::Select(1.into()),
+            Answer::Confirm(true),
+            Answer::Default,
+        ]);
+
+        assert!(matches!(item, InboxItem::Incubated(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify +tickle wait:+1d"
+        ));
+
+        let (item, fake) = process(vec![
+            Answer::Confirm(false),
+            Answer::Select(1.into()),
+            Answer::Confirm(false),
+        ]);
+
+        assert!(matches!(item, InboxItem::Incubated(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify -in -@agenda -@anywhere -@computer -@errands \
+             -@home -@phone -@rnr -@work proj:maybe"
+        ));
+    }
+
+    #[test]
+    fn doing_it_now() {
+        let (item, fake) = process(vec![
+            Answer::Confirm(true),
+            Answer::Select(0.into()),
+            Answer::Timer(45),
+            Answer::Confirm(false),
+            // still within two minutes, so no offer to defer
+            Answer::Timer(30),
+            Answer::Confirm(true),
+        ]);
+
+        assert!(matches!(item, InboxItem::Finished(_)));
+        assert!(called(&fake, "rc.confirmation=off 1 start"));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 annotate Done in 1min15s"
+        ));
+        assert!(called(&fake, "rc.confirmation=off 1 done"));
+    }
+
+    #[test]
+    fn doing_it_after_all() {
+        let (item, trace, fake) = drive(vec![
+            Answer::Confirm(true),
+            Answer::Select("Do it".into()),
+            Answer::Timer(200),
+            Answer::Confirm(false),
+            Answer::Confirm(true),
+            // back from the new task to doing it
+            Answer::Back,
+            Answer::Timer(10),
+            Answer::Confirm(true),
+        ]);
+
+        assert!(matches!(item, InboxItem::Finished(_)));
+        assert_eq!(trace[3], Back("Deferring", "Doing"));
+        let started: Vec<_> = fake
+            .calls()
+            .into_iter()
+            .filter(|c| c.ends_with(" start") || c.ends_with(" stop"))
+            .collect();
+        assert_eq!(
+            started,
+            vec![
+                "rc.confirmation=off 1 start",
+                "rc.confirmation=off 1 stop",
+                "rc.confirmation=off 1 start",
+            ]
+        );
+        assert!(called(&fake, "rc.confirmation=off 1 done"));
+    }
+
+    #[test]
+    fn doing_it_for_too_long() {
+        let (item, fake) = process(vec![
+            Answer::Confirm(true),
+            Answer::Select("Do it".into()),
+            Answer::Timer(200),
+            Answer::Confirm(false),
+            Answer::Confirm(true),
+            // the new task
+            Answer::Confirm(false),
+            Answer::Input("finish calling the plumber".into()),
+            Answer::MultiSelect(vec!["Home".into()]),
+            Answer::Default,
+            Answer::Default,
+            Answer::Confirm(false),
+        ]);
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert!(called(&fake, "rc.confirmation=off 1 stop"));
+        assert!(called(&fake, "rc.confirmation=off 1 modify -in"));
+        assert!(fake
+            .calls()
+            .iter()
+            .any(|c| c.ends_with("annotate Spent 3min20s on it before deferring")));
+    }
+
+    #[test]
+    fn deferring() {
+        let (item, fake) = process(vec![
+            Answer::Confirm(true),
+            Answer::Select("Defer".into()),
+            // the new task
+            Answer::Confirm(false),
+            Answer::Input("book the plumber".into()),
+            Answer::MultiSelect(vec!["Computer".into(), "Home".into()]),
+            Answer::Select("High".into()),
+            Answer::Default,
+            Answer::Confirm(false),
+        ]);
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify description:book the plumber +@computer +@home brain:H est:30"
+        ));
+        assert!(called(&fake, "rc.confirmation=off 1 modify -in"));
+        assert!(!called(&fake, "rc.confirmation=off 1 delete"));
+
+        // it's still the same task
+        assert_eq!(fake.tasks().len(), 1);
+        let outcome = item.outcome().unwrap();
+        assert_eq!(outcome.action, "deferred");
+        assert_eq!(outcome.uuid, UUID);
+    }
+
+    #[test]
+    fn deferring_keeps_what_the_item_has() {
+        let original = json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+            "project": "house",
+            "tags": ["in", "@phone"],
+            "due": "20301001T090000Z",
+            "brain": "H",
+            "est": 360,
+        });
+        let (item, _, fake) = drive_item(
+            original.clone(),
+            vec![],
+            vec![
+                Answer::Confirm(true),
+                Answer::Select("Defer".into()),
+                // the new task, accepting everything as it is
+                Answer::Confirm(false),
+                Answer::Default,
+                Answer::Default,
+                Answer::Default,
+                Answer::Default,
+                Answer::Default,
+                Answer::Default,
+            ],
+        );
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify description:call the plumber project:house \
+             +@phone due:20301001T090000Z brain:H est:360"
+        ));
+
+        // choosing other contexts drops the old ones
+        let (_, _, fake) = drive_item(
+            original,
+            vec![],
+            vec![
+                Answer::Confirm(true),
+                Answer::Select("Defer".into()),
+                Answer::Confirm(false),
+                Answer::Default,
+                Answer::MultiSelect(vec!["Computer".into()]),
+                Answer::Default,
+                Answer::Default,
+                Answer::Confirm(false),
+            ],
+        );
+
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify description:call the plumber project:house \
+             +@computer -@phone due:20301001T090000Z brain:H est:360"
+        ));
+    }
+
+    #[test]
+    fn delegating() {
+        let original = json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+            "tags": ["in"],
+            "brain": "H",
+            "est": 60,
+        });
+        let (item, _, fake) = drive_item(
+            original,
+            vec![],
+            vec![
+                Answer::Confirm(true),
+                Answer::Select(2.into()),
+                Answer::Select("--Someone new--".into()),
+                // nobody isn't an option
+                Answer::Input("  ".into()),
+                Answer::Input("Bob".into()),
+                Answer::Select(1.into()),
+                Answer::Input("did the plumber reply?".into()),
+                Answer::Input("+2d".into()),
+            ],
+        );
+
+        assert!(matches!(item, InboxItem::Delegated(_)));
+        assert!(called(&fake, "_udas"));
+        assert_eq!(fake.tasks()[0]["brain"], json!("H"));
+        assert_eq!(fake.tasks()[0]["est"], json!(60));
+        assert!(!called(&fake, "rc.confirmation=off 1 delete"));
+        assert_eq!(fake.tasks().len(), 1);
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify description:did the plumber reply? \
+             +@work +@home +in +tickle wait:+2d delegatee:Bob delegation:email"
+        ));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 annotate follow up from call the plumber"
+        ));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 annotate Sent an email"
+        ));
+    }
+
+    #[test]
+    fn delegating_without_udas() {
+        let fake = FakeTask::new(&[json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+            "tags": ["in"],
+        })]);
+        fake.declare_udas(&["brain", "est"]);
+        let task = load_tasks("+in").unwrap().remove(0);
+
+        let mut prompter = Scripted::new(vec![
+            Answer::Confirm(true),
+            Answer::Select("Delegate".into()),
+        ]);
+        let err = Driver::new(InboxItem::new(&config(""), task))
+            .run(&mut prompter)
+            .unwrap_err();
+
+        assert!(err.to_string().contains("uda.delegatee.type=string"));
+        assert_eq!(fake.tasks().len(), 1);
+    }
+
+    #[test]
+    fn remembering_delegatees() {
+        let delegated = |n: u8, who: &str, modified: &str| {
+            json!({
+                "id": 0,
+                "uuid": format!("0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f0{}", n),
+                "status": "completed",
+                "entry": "20260901T090000Z",
+                "modified": modified,
+                "description": "an old follow-up",
+                "delegatee": who,
+            })
+        };
+        let others = vec![
+            delegated(1, "Alice", "20260902T090000Z"),
+            delegated(2, "Carol", "20260910T090000Z"),
+            delegated(3, "Alice", "20260905T090000Z"),
+        ];
+
+        let (item, _, fake) = drive_among(
+            others,
+            vec![
+                Answer::Confirm(true),
+                Answer::Select("Delegate".into()),
+                Answer::Select(2.into()),
+                Answer::Select("Make a note for the next meeting with this person".into()),
+                Answer::Input("ask about the plumber".into()),
+                Answer::Input("+1w".into()),
+            ],
+        );
+
+        assert!(matches!(item, InboxItem::Delegated(_)));
+        assert_eq!(crate::task::delegatees().unwrap(), vec!["Carol", "Alice"]);
+        assert!(fake
+            .calls()
+            .iter()
+            .any(|c| c.ends_with("delegatee:Alice delegation:agenda")));
+    }
+
+    #[test]
+    fn deferring_into_a_subproject() {
+        let others = vec![json!({
+            "id": 2,
+            "uuid": "0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f01",
+            "status": "pending",
+            "entry": "20260901T090000Z",
+            "description": "fix the gutter",
+            "project": "house",
+            "tags": ["@home"],
+            "brain": "L",
+        })];
+
+        let (item, _, fake) = drive_among(
+            others,
+            vec![
+                Answer::Confirm(true),
+                Answer::Select("Defer".into()),
+                Answer::Confirm(true),
+                Answer::Select("--New project--".into()),
+                Answer::Select("house".into()),
+                Answer::Input("plumbing".into()),
+                Answer::Default,
+                // the new subproject has no tasks of its own to suggest from
+                Answer::MultiSelect(vec!["Home".into()]),
+                Answer::Default,
+                Answer::Default,
+                Answer::Confirm(false),
+            ],
+        );
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 modify description:call the plumber \
+             project:house.plumbing +@home brain:M est:30"
+        ));
+    }
+
+    #[test]
+    fn going_back() {
+        let (item, trace, fake) = drive(vec![
+            // nothing before the first question, so it's asked again
+            Answer::Back,
+            Answer::Confirm(true),
+            Answer::Back,
+            Answer::Confirm(false),
+            Answer::Select("Trash".into()),
+        ]);
+
+        assert!(matches!(item, InboxItem::Deleted(_)));
+        assert!(called(&fake, "rc.confirmation=off 1 delete"));
+        assert_eq!(
+            trace,
+            vec![
+                Forward("Starting", "Actioning"),
+                Back("Actioning", "Starting"),
+                Forward("Starting", "Inactioning"),
+                Forward("Inactioning", "Deleted"),
+            ]
+        );
+
+        let (item, trace, fake) = drive(vec![
+            Answer::Confirm(true),
+            Answer::Select("Defer".into()),
+            // the new task, with a typo in the action
+            Answer::Confirm(false),
+            Answer::Input("bok the plumber".into()),
+            Answer::Back,
+            Answer::Input("book the plumber".into()),
+            Answer::MultiSelect(vec!["Home".into()]),
+            Answer::Default,
+            Answer::Default,
+            Answer::Confirm(false),
+        ]);
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert!(fake
+            .calls()
+            .iter()
+            .any(|c| c.contains("modify description:book the plumber")));
+        assert_eq!(
+            trace,
+            vec![
+                Forward("Starting", "Actioning"),
+                Forward("Actioning", "Deferring"),
+                Forward("Deferring", "Deferred"),
+            ]
+        );
+
+        // backing out of the whole new task goes back to the choice to defer
+        let (item, trace, _) = drive(vec![
+            Answer::Confirm(true),
+            Answer::Select("Defer".into()),
+            Answer::Back,
+            Answer::Select("Do it".into()),
+            Answer::Default,
+            Answer::Confirm(true),
+        ]);
+
+        assert!(matches!(item, InboxItem::Finished(_)));
+        assert_eq!(trace[2], Back("Deferring", "Actioning"));
+    }
+
+    #[test]
+    fn resuming() {
+        let fake = FakeTask::new(&[json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+            "tags": ["in"],
+        })]);
+        let config = config("");
+
+        // interrupted while deferring something that took too long
+        let session = Session {
+            uuid: UUID.into(),
+            state: "Deferring".into(),
+            elapsed: Some(150),
+        };
+        let item = InboxItem::resume(&config, &session).unwrap().unwrap();
+        assert!(matches!(item, InboxItem::Deferring(_)));
+        assert_eq!(item.session(), session);
+
+        let mut saved = Vec::new();
+        let mut prompter = Scripted::new([
+            Answer::Confirm(false),
+            Answer::Default,
+            Answer::MultiSelect(vec!["Home".into()]),
+            Answer::Default,
+            Answer::Default,
+            Answer::Confirm(false),
+        ]);
+        let item = Driver::new(item)
+            .run_saving(&mut prompter, |item| {
+                saved.push(item.state());
+                Ok(())
+            })
+            .unwrap();
+
+        assert!(matches!(item, InboxItem::Deferred(_)));
+        assert_eq!(saved, vec!["Deferred"]);
+        assert!(called(
+            &fake,
+            "rc.confirmation=off 1 annotate Spent 2min30s on it before deferring"
+        ));
+
+        let finished = Session {
+            state: "Deferred".into(),
+            ..session.clone()
+        };
+        assert!(InboxItem::resume(&config, &finished).is_err());
+
+        // dealt with some other way in the meantime
+        let gone = Session {
+            uuid: "7d0e5b1c-2f3a-4c6d-8e9f-0a1b2c3d4e5f".into(),
+            ..session
+        };
+        assert!(InboxItem::resume(&config, &gone).unwrap().is_none());
+    }
+
+    #[test]
+    fn mismatched_answers() {
+        let _fake = FakeTask::new(&[json!({
+            "id": 1,
+            "uuid": UUID,
+            "status": "pending",
+            "entry": "20261001T090000Z",
+            "description": "call the plumber",
+        })]);
+        let task = load_tasks("").unwrap().remove(0);
+        let mut prompter = Scripted::new([Answer::Select("Trash".into())]);
+
+        let err = Driver::new(InboxItem::new(&config(""), task))
+            .run(&mut prompter)
+            .unwrap_err();
+        assert!(err.to_string().contains("landed on confirm prompt"));
+    }
+}
@@ -1,87 +1,272 @@
+use crate::config::Config;
 use crate::iou_client::IouClient;
-use crate::task::Project;
+use crate::project::Project;
+use crate::prompt::{is_go_back, Prompter};
+use crate::task::TaskBuilder;
 use crate::workflow::create::NewTask;
 use crate::workflow::inbox::InboxItem;
 use crate::workflow::next::NextTask;
 use crate::workflow::pr::Pr;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use console::style;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
 use task_hookrs::task::Task;
 
+mod bulk;
 mod create;
 mod inbox;
 mod next;
 mod pr;
+mod session;
 
-pub trait StatefulEnum {
-    type Output;
-    fn step(self) -> Result<Self::Output>;
-    fn terminated(&self) -> bool;
+pub use bulk::triage;
+pub use session::Session;
+
+/// What a workflow ended up doing with a task, for reports.
+#[derive(Debug, Clone, Serialize)]
+pub struct Outcome {
+    pub action: &'static str,
+    pub uuid: String,
+    pub description: String,
+    /// How the workflow got there, like `Starting -> Actioning`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub trace: Vec<String>,
 }
 
-pub fn inbox_task(task: Task) -> Result<()> {
-    let mut item = InboxItem::new(task);
-    loop {
-        item = item.step()?;
-        if item.terminated() {
-            break;
+impl Outcome {
+    pub fn new(action: &'static str, task: &Task) -> Self {
+        Self {
+            action,
+            uuid: task.uuid().to_string(),
+            description: task.description().clone(),
+            trace: Vec::new(),
         }
     }
-    Ok(())
+
+    pub fn with_trace(mut self, trace: &[Transition]) -> Self {
+        self.trace = trace.iter().map(|t| t.to_string()).collect();
+        self
+    }
 }
 
-pub fn new_task() -> Result<Task> {
-    let mut workflow = NewTask::new();
-    loop {
-        workflow = workflow.step()?;
+/// A workflow made of typed states, stepped by a `Driver` until it ends up in
+/// a terminal one.
+pub trait StatefulEnum: Clone + fmt::Debug {
+    fn step(self, prompter: &mut dyn Prompter) -> Result<Self>;
+    fn terminated(&self) -> bool;
+
+    /// The name of the current state, for transition traces.
+    fn state(&self) -> &'static str;
 
-        match workflow {
-            NewTask::Done(wf) => return Ok(wf.state.task),
-            _ => {}
-        }
+    /// Whether getting to this state changed tasks, in which case there is no
+    /// going back past it.
+    fn committed(&self) -> bool {
+        self.terminated()
     }
 }
 
-pub fn add_to_project(project: Project) -> Result<Task> {
-    let mut workflow = NewTask::for_project(project);
-    loop {
-        workflow = workflow.step()?;
+/// One step of a workflow, as recorded by a `Driver`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transition {
+    Forward(&'static str, &'static str),
+    /// Went back from the first state to the second at the user's request.
+    Back(&'static str, &'static str),
+}
 
-        match workflow {
-            NewTask::Done(wf) => return Ok(wf.state.task),
-            _ => {}
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Forward(from, to) => write!(f, "{} -> {}", from, to),
+            Self::Back(from, to) => write!(f, "{} <- {}", to, from),
         }
     }
 }
 
-pub fn set_next_task(project: Project) -> Result<()> {
-    let mut workflow = NextTask::new(project);
-    loop {
-        workflow = workflow.step()?;
-        if workflow.terminated() {
-            break;
+/// Steps a workflow to completion, keeping the states it passed through so a
+/// `GoBack` from the prompter can return to the previous one.
+///
+/// Only states that haven't changed anything are kept, once a step commits
+/// to something the history before it is dropped.
+#[derive(Debug)]
+pub struct Driver<M> {
+    machine: M,
+    history: Vec<M>,
+    trace: Vec<Transition>,
+    nested: bool,
+}
+
+impl<M: StatefulEnum> Driver<M> {
+    pub fn new(machine: M) -> Self {
+        Self {
+            machine,
+            history: Vec::new(),
+            trace: Vec::new(),
+            nested: false,
         }
     }
-    Ok(())
-}
 
-pub fn force_next_task(project: Project) -> Result<()> {
-    let mut workflow = NextTask::with_force(project);
-    loop {
-        workflow = workflow.step()?;
-        if workflow.terminated() {
-            break;
+    /// For a workflow run as part of another's step. Going back from its
+    /// first state is passed on to the outer workflow instead of asking again.
+    pub fn nested(mut self) -> Self {
+        self.nested = true;
+        self
+    }
+
+    /// The transitions so far, in order.
+    pub fn trace(&self) -> &[Transition] {
+        &self.trace
+    }
+
+    pub fn run(&mut self, prompter: &mut dyn Prompter) -> Result<M> {
+        self.run_saving(prompter, |_| Ok(()))
+    }
+
+    /// Like `run`, but calls `save` with every state the workflow moves to so
+    /// that it can be picked up again after an interruption.
+    pub fn run_saving(
+        &mut self,
+        prompter: &mut dyn Prompter,
+        mut save: impl FnMut(&M) -> Result<()>,
+    ) -> Result<M> {
+        while !self.machine.terminated() {
+            match self.machine.clone().step(prompter) {
+                Ok(next) => {
+                    self.trace
+                        .push(Transition::Forward(self.machine.state(), next.state()));
+
+                    let previous = std::mem::replace(&mut self.machine, next);
+                    if self.machine.committed() {
+                        self.history.clear();
+                    } else {
+                        self.history.push(previous);
+                    }
+
+                    save(&self.machine)?;
+                }
+                Err(err) if is_go_back(&err) => match self.history.pop() {
+                    Some(previous) => {
+                        self.trace
+                            .push(Transition::Back(self.machine.state(), previous.state()));
+                        self.machine = previous;
+                        save(&self.machine)?;
+                    }
+                    None if self.nested => return Err(err),
+                    None => prompter.tell(&style("    Nothing to go back to").yellow()),
+                },
+                Err(err) => return Err(err),
+            }
         }
+
+        Ok(self.machine.clone())
     }
-    Ok(())
 }
 
-pub fn process_pr(task: Task, iou_client: &IouClient) -> Result<()> {
-    let mut workflow = Pr::new(task, iou_client);
-    loop {
-        workflow = workflow.step()?;
-        if workflow.terminated() {
-            break;
+pub fn inbox_task(config: &Config, prompter: &mut dyn Prompter, task: Task) -> Result<Outcome> {
+    let mut driver = Driver::new(InboxItem::new(config, task));
+    let item = driver.run(prompter)?;
+
+    inbox_outcome(item, driver.trace())
+}
+
+/// Like `inbox_task`, but keeps the item's progress in the session file at
+/// `path` until it's done.
+pub fn inbox_task_saving(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    task: Task,
+    path: &Path,
+) -> Result<Outcome> {
+    process_saving(prompter, InboxItem::new(config, task), path)
+}
+
+/// Finish the item an interrupted inbox was on, if there was one. Items that
+/// were dealt with in the meantime are let go.
+pub fn resume_inbox_task(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    path: &Path,
+) -> Result<Option<Outcome>> {
+    let session = match Session::load(path)? {
+        Some(session) => session,
+        None => return Ok(None),
+    };
+
+    match InboxItem::resume(config, &session)? {
+        Some(item) => process_saving(prompter, item, path).map(Some),
+        None => {
+            prompter.tell(&style("    The interrupted item is no longer in the inbox").yellow());
+            Session::clear(path)?;
+            Ok(None)
         }
     }
+}
+
+fn process_saving(prompter: &mut dyn Prompter, item: InboxItem, path: &Path) -> Result<Outcome> {
+    item.session().save(path)?;
+
+    let mut driver = Driver::new(item);
+    let item = driver.run_saving(prompter, |item| match item.terminated() {
+        true => Session::clear(path),
+        false => item.session().save(path),
+    })?;
+
+    inbox_outcome(item, driver.trace())
+}
+
+fn inbox_outcome(item: InboxItem, trace: &[Transition]) -> Result<Outcome> {
+    item.outcome()
+        .map(|outcome| outcome.with_trace(trace))
+        .ok_or_else(|| anyhow!("Inbox item finished without an outcome {:?}", item))
+}
+
+pub fn new_task(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    builder: TaskBuilder,
+) -> Result<Task> {
+    Driver::new(NewTask::with_builder(config, builder))
+        .run(prompter)?
+        .created()
+}
+
+pub fn add_to_project(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    project: Project,
+) -> Result<Task> {
+    Driver::new(NewTask::for_project(config, project))
+        .run(prompter)?
+        .created()
+}
+
+/// Returns what was made next, if the project needed a next task.
+pub fn set_next_task(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    project: Project,
+) -> Result<Option<Outcome>> {
+    next_task(NextTask::new(config, project), prompter)
+}
+
+/// Returns what was made next, if the project needed a next task.
+pub fn force_next_task(
+    config: &Config,
+    prompter: &mut dyn Prompter,
+    project: Project,
+) -> Result<Option<Outcome>> {
+    next_task(NextTask::with_force(config, project), prompter)
+}
+
+fn next_task(workflow: NextTask, prompter: &mut dyn Prompter) -> Result<Option<Outcome>> {
+    let mut driver = Driver::new(workflow);
+    let outcome = driver.run(prompter)?.outcome();
+
+    Ok(outcome.map(|outcome| outcome.with_trace(driver.trace())))
+}
+
+pub fn process_pr(prompter: &mut dyn Prompter, task: Task, iou_client: &IouClient) -> Result<()> {
+    Driver::new(Pr::new(task, iou_client)).run(prompter)?;
     Ok(())
 }
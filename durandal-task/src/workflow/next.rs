@@ -1,11 +1,13 @@
 use anyhow::{Context, Result};
 use console::style;
-use dialoguer::{theme::ColorfulTheme, Select};
 use task_hookrs::task::Task;
 
 use crate::{
-    task::{Processable, Project},
-    workflow::add_to_project,
+    config::Config,
+    project::{needs_next_action, Project},
+    prompt::{is_go_back, Prompter},
+    task::Processable,
+    workflow::{create::NewTask, Driver, Outcome, StatefulEnum},
 };
 
 #[derive(Debug, Clone)]
@@ -16,24 +18,36 @@ pub enum NextTask {
 }
 
 impl NextTask {
-    pub fn new(project: Project) -> Self {
+    pub fn new(config: &Config, project: Project) -> Self {
         Self::Checking(Workflow {
             project,
+            config: config.clone(),
             state: Checking::default(),
         })
     }
 
-    pub fn with_force(project: Project) -> Self {
+    pub fn with_force(config: &Config, project: Project) -> Self {
         Self::Checking(Workflow {
             project,
+            config: config.clone(),
             state: Checking { force: true },
         })
     }
 
-    pub fn step(self) -> Result<Self> {
+    /// The task that was made next, if one had to be picked.
+    pub fn outcome(&self) -> Option<Outcome> {
         match self {
-            Self::Checking(workflow) => workflow.step(),
-            Self::Picking(workflow) => workflow.step(),
+            Self::Done(wf) => wf.state.next.clone(),
+            _ => None,
+        }
+    }
+}
+
+impl StatefulEnum for NextTask {
+    fn step(self, prompter: &mut dyn Prompter) -> Result<Self> {
+        match self {
+            Self::Checking(workflow) => workflow.step(prompter),
+            Self::Picking(workflow) => workflow.step(prompter),
             _ => Err(anyhow::anyhow!(
                 "Attempted to step for a terminal state {:?}",
                 self
@@ -41,17 +55,28 @@ impl NextTask {
         }
     }
 
-    pub fn terminated(&self) -> bool {
+    fn terminated(&self) -> bool {
+        matches!(self, Self::Done(_))
+    }
+
+    fn state(&self) -> &'static str {
         match self {
-            Self::Done(_) => true,
-            _ => false,
+            Self::Checking(_) => "Checking",
+            Self::Picking(_) => "Picking",
+            Self::Done(_) => "Done",
         }
     }
+
+    /// Checking clears the old next tags before anything is picked.
+    fn committed(&self) -> bool {
+        !matches!(self, Self::Checking(_))
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Workflow<S> {
     project: Project,
+    config: Config,
     state: S,
 }
 
@@ -61,8 +86,9 @@ pub struct Checking {
 }
 
 impl Workflow<Checking> {
-    pub fn step(self) -> Result<NextTask> {
-        let tasks = self.project.tasks()?;
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<NextTask> {
+        // subprojects are checked on their own
+        let tasks = self.project.own_tasks()?;
         let target_tag = String::from("next");
         let cur_tasks: Vec<_> = tasks
             .iter()
@@ -77,27 +103,25 @@ impl Workflow<Checking> {
             })
             .collect();
 
-        if !cur_tasks.is_empty() {
-            // there is a task with the next tag, so decide what do to
-            if self.state.force {
-                // clear the next tag for the next tasks before we continue
-                for task in cur_tasks {
-                    task.remove_tags(&["next"])?;
-                }
-            } else {
-                // otherwise, we're done
-                return Ok(NextTask::Done(self.into()));
-            }
+        let skip = self.config.policy(&self.project).skips_next_action();
+
+        if !self.state.force && (skip || !needs_next_action(&tasks)) {
+            return Ok(NextTask::Done(self.into()));
+        }
+
+        // clear the next tag for the next tasks before we continue
+        for task in cur_tasks {
+            task.remove_tags(&["next"])?;
         }
 
         // there isn't one so we have to decide what to do in another step
 
         // this is a bit wonky but I'd prefer if step didn't have to take
         // mut self
-        println!(
+        prompter.tell(&format!(
             "\n\nThe following project does not have a next task:\n -> {}\n",
             style(&self.project).yellow()
-        );
+        ));
 
         let mut nt: Workflow<Picking> = self.into();
         nt.state.tasks = tasks;
@@ -110,7 +134,8 @@ impl From<Workflow<Checking>> for Workflow<Done> {
     fn from(value: Workflow<Checking>) -> Self {
         Workflow {
             project: value.project,
-            state: Done,
+            config: value.config,
+            state: Done::default(),
         }
     }
 }
@@ -119,6 +144,7 @@ impl From<Workflow<Checking>> for Workflow<Picking> {
     fn from(value: Workflow<Checking>) -> Self {
         Workflow {
             project: value.project,
+            config: value.config,
             state: Picking { tasks: Vec::new() },
         }
     }
@@ -130,7 +156,7 @@ pub struct Picking {
 }
 
 impl Workflow<Picking> {
-    pub fn step(self) -> Result<NextTask> {
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<NextTask> {
         let mut choices: Vec<_> = self
             .state
             .tasks
@@ -140,23 +166,32 @@ impl Workflow<Picking> {
 
         choices.push("--New task--".into());
 
-        let choice = Select::with_theme(&ColorfulTheme::default())
-            .with_prompt("Which task should be next?")
-            .items(&choices)
-            .default(0)
-            .interact()?;
+        let task = loop {
+            let choice = prompter.select("Which task should be next?", &choices, 0)?;
 
-        let task = match self.state.tasks.get(choice) {
-            Some(task) => task.clone(),
-            None => add_to_project(self.project.clone())?,
+            if let Some(task) = self.state.tasks.get(choice) {
+                break task.clone();
+            }
+
+            // backing out of the new task comes back to the choices
+            match Driver::new(NewTask::for_project(&self.config, self.project.clone()))
+                .nested()
+                .run(prompter)
+            {
+                Err(err) if is_go_back(&err) => continue,
+                created => break created?.created()?,
+            }
         };
 
         task.execute(["modify", "+next"])
             .with_context(|| "Failed attempting to modify task with +next")?;
 
-        println!("    {}", style("Next task selected").green());
+        prompter.tell(&format!("    {}", style("Next task selected").green()));
 
-        Ok(NextTask::Done(self.into()))
+        let mut done: Workflow<Done> = self.into();
+        done.state.next = Some(Outcome::new("next", &task));
+
+        Ok(NextTask::Done(done))
     }
 }
 
@@ -164,10 +199,13 @@ impl From<Workflow<Picking>> for Workflow<Done> {
     fn from(value: Workflow<Picking>) -> Self {
         Workflow {
             project: value.project,
-            state: Done,
+            config: value.config,
+            state: Done::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub struct Done;
+#[derive(Debug, Default, Clone)]
+pub struct Done {
+    next: Option<Outcome>,
+}
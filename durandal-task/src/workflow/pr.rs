@@ -1,10 +1,10 @@
 use anyhow::Result;
 use console::style;
-use dialoguer::{theme::ColorfulTheme, Confirm, Input};
 use task_hookrs::task::Task;
 
 use crate::{
     iou_client::IouClient,
+    prompt::Prompter,
     task::Processable,
     task_table::{Field, TaskDetail, UDA},
 };
@@ -25,12 +25,10 @@ impl Pr {
 }
 
 impl StatefulEnum for Pr {
-    type Output = Self;
-
-    fn step(self) -> Result<Self::Output> {
+    fn step(self, prompter: &mut dyn Prompter) -> Result<Self> {
         match self {
-            Self::Starting(machine) => machine.step(),
-            Self::Processing(machine) => machine.step(),
+            Self::Starting(machine) => machine.step(prompter),
+            Self::Processing(machine) => machine.step(prompter),
             _ => Err(anyhow::anyhow!(
                 "Attempted to prompt for a terminal state {:?}",
                 self
@@ -39,9 +37,14 @@ impl StatefulEnum for Pr {
     }
 
     fn terminated(&self) -> bool {
+        matches!(self, Self::Done(_))
+    }
+
+    fn state(&self) -> &'static str {
         match self {
-            Self::Done(_) => true,
-            _ => false,
+            Self::Starting(_) => "Starting",
+            Self::Processing(_) => "Processing",
+            Self::Done(_) => "Done",
         }
     }
 }
@@ -65,34 +68,26 @@ impl Workflow<Starting> {
         }
     }
 
-    pub fn step(self) -> Result<Pr> {
-        println!("{}", style("Next PR:").cyan());
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<Pr> {
+        prompter.tell(&style("Next PR:").cyan());
         let mut desc = TaskDetail::new(&self.task);
         desc.add_row(&Field::ID);
         desc.add_rows(&[
-            UDA::GithubTitle,
-            UDA::GithubUser,
-            UDA::GithubState,
-            UDA::GithubUrl,
-            UDA::GithubBody,
+            UDA::github_title(),
+            UDA::github_user(),
+            UDA::github_state(),
+            UDA::github_url(),
+            UDA::github_body(),
         ]);
         desc.add_row(&Field::Annotations);
 
-        println!("{}", desc.output());
-
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Open in browser?")
-            .default(true)
-            .interact()?
-        {
-            let url = UDA::GithubUrl.get_raw_value(&self.task);
-            if url.is_empty() {
-                anyhow::bail!(format!(
-                    "Task is missing the github url UDA: {:?}",
-                    self.task
-                ));
-            }
-            self.iou_client.open(&url)?;
+        prompter.tell(&desc.output());
+
+        if prompter.confirm("Open in browser?", true)? {
+            let url = UDA::github_url().get_str(&self.task).ok_or_else(|| {
+                anyhow::anyhow!("Task is missing the github url UDA: {:?}", self.task)
+            })?;
+            self.iou_client.open(url)?;
         }
 
         Ok(Pr::Processing(self.into()))
@@ -113,16 +108,12 @@ impl From<Workflow<Starting>> for Workflow<Processing> {
 pub struct Processing;
 
 impl Workflow<Processing> {
-    pub fn step(self) -> Result<Pr> {
-        if Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt("Would you like to hide this PR until later?")
-            .default(false)
-            .interact()?
-        {
-            let wait: String = Input::with_theme(&ColorfulTheme::default())
-                .with_prompt("When would you like to see this PR again? (any valid 'wait:' value)")
-                .default("+1d".into())
-                .interact_text()?;
+    pub fn step(self, prompter: &mut dyn Prompter) -> Result<Pr> {
+        if prompter.confirm("Would you like to hide this PR until later?", false)? {
+            let wait = prompter.input(
+                "When would you like to see this PR again? (any valid 'wait:' value)",
+                Some("+1d"),
+            )?;
 
             self.task.tickle(&wait)?;
         }
@@ -0,0 +1,82 @@
+//! Where an interrupted `inbox` left off, so `inbox --resume` can pick the
+//! item back up instead of starting it over.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// The inbox item being processed.
+    pub uuid: String,
+    /// The state the item was last in.
+    pub state: String,
+    /// Seconds already spent doing the item, when it's being deferred for
+    /// taking too long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed: Option<u64>,
+}
+
+impl Session {
+    /// The saved session, if there is one.
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let raw = match fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("Could not read inbox session {:?}", path))
+            }
+        };
+
+        serde_json::from_str(&raw)
+            .map(Some)
+            .with_context(|| format!("Inbox session {:?} is not valid", path))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_json::to_string(self)?)
+            .with_context(|| format!("Could not save inbox session {:?}", path))
+    }
+
+    pub fn clear(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                Err(err).with_context(|| format!("Could not remove inbox session {:?}", path))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saving_sessions() {
+        let path = std::env::temp_dir()
+            .join(format!("durandal-session-{}", std::process::id()))
+            .join("inbox.json");
+        assert_eq!(Session::load(&path).unwrap(), None);
+
+        let session = Session {
+            uuid: "0a5c3a4e-5f0c-4a1e-9a59-1c3b4e6a1f00".into(),
+            state: "Deferring".into(),
+            elapsed: Some(150),
+        };
+        session.save(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), Some(session));
+
+        Session::clear(&path).unwrap();
+        Session::clear(&path).unwrap();
+        assert_eq!(Session::load(&path).unwrap(), None);
+
+        let _ = fs::remove_dir(path.parent().unwrap());
+    }
+}